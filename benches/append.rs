@@ -0,0 +1,49 @@
+//! Times a streamed session of 1000 appends: parsing each chunk on its own, as the viewer does,
+//! against re-parsing the whole accumulated document after every append.
+//!
+//! Run with `cargo bench --no-default-features --bench append`.
+
+use std::time::{Duration, Instant};
+
+use homo::markdown::{RenderSettings, parse_markdown, parse_markdown_fragment};
+
+const APPENDS: usize = 1000;
+
+/// A twelve-line chunk ending at a block boundary, like the ones the reader sends.
+fn chunk(index: usize) -> String {
+    format!(
+        "## Entry {index}\n\nSome *text* for entry {index}, with `code` and a [link](https://example.com).\n\n- first\n- second\n\n```rust\nfn entry() -> usize {{\n    {index}\n}}\n```\n\n"
+    )
+}
+
+fn appending(chunks: &[String]) -> Duration {
+    let start = Instant::now();
+    let mut html = String::new();
+    let mut first_line = 0;
+    for chunk in chunks {
+        html.push_str(&parse_markdown_fragment(chunk, first_line));
+        first_line += chunk.matches('\n').count();
+    }
+    std::hint::black_box(html);
+    start.elapsed()
+}
+
+fn reparsing(chunks: &[String]) -> Duration {
+    let start = Instant::now();
+    let mut markdown = String::new();
+    for chunk in chunks {
+        markdown.push_str(chunk);
+        std::hint::black_box(parse_markdown(&markdown));
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let _settings = RenderSettings::default().scoped();
+    let chunks: Vec<String> = (0..APPENDS).map(chunk).collect();
+
+    let appended = appending(&chunks);
+    println!("{APPENDS} appends, chunk by chunk: {appended:.2?}");
+    let reparsed = reparsing(&chunks);
+    println!("{APPENDS} appends, re-parsing everything: {reparsed:.2?}");
+}
@@ -0,0 +1,247 @@
+//! Conversion of ANSI-colored terminal output (e.g. `cargo build 2>&1 | homo`) to HTML.
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// The 16 standard and bright terminal colors.
+const BASIC_COLORS: [&str; 16] = [
+    "#000000", "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#e5e5e5",
+    "#666666", "#f14c4c", "#23d18b", "#f5f543", "#3b8eea", "#d670d6", "#29b8db", "#ffffff",
+];
+
+/// Returns whether the text contains an ANSI CSI escape sequence (`ESC [`).
+pub fn contains_ansi(text: &str) -> bool {
+    text.contains("\x1b[")
+}
+
+/// Converts a complete piece of terminal output to HTML.
+pub fn ansi_to_html(text: &str) -> String {
+    AnsiRenderer::new().render(text)
+}
+
+/// Text attributes set by SGR (`ESC [ ... m`) sequences.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    foreground: Option<String>,
+    background: Option<String>,
+}
+
+impl Style {
+    fn css(&self) -> String {
+        let mut css = String::new();
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if self.dim {
+            css.push_str("opacity:0.7;");
+        }
+        if self.italic {
+            css.push_str("font-style:italic;");
+        }
+        if self.underline {
+            css.push_str("text-decoration:underline;");
+        }
+        if let Some(color) = &self.foreground {
+            css.push_str(&format!("color:{color};"));
+        }
+        if let Some(color) = &self.background {
+            css.push_str(&format!("background-color:{color};"));
+        }
+        css
+    }
+
+    /// Applies the `;`-separated parameters of an SGR sequence.
+    fn apply_sgr(&mut self, params: &str) {
+        let codes: Vec<u32> = params
+            .split(';')
+            .map(|code| code.parse().unwrap_or(0))
+            .collect();
+
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                code @ 30..=37 => self.foreground = Some(BASIC_COLORS[(code - 30) as usize].into()),
+                code @ 90..=97 => {
+                    self.foreground = Some(BASIC_COLORS[(code - 90 + 8) as usize].into())
+                }
+                39 => self.foreground = None,
+                code @ 40..=47 => self.background = Some(BASIC_COLORS[(code - 40) as usize].into()),
+                code @ 100..=107 => {
+                    self.background = Some(BASIC_COLORS[(code - 100 + 8) as usize].into())
+                }
+                49 => self.background = None,
+                code @ (38 | 48) => {
+                    let (color, consumed) = extended_color(&codes[i + 1..]);
+                    if code == 38 {
+                        self.foreground = color;
+                    } else {
+                        self.background = color;
+                    }
+                    i += consumed;
+                }
+                _ => {} // Blink, inverse and the like have no useful HTML equivalent
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Parses the arguments following a 38/48 code: `5;n` (256 colors) or `2;r;g;b` (true color).
+/// Returns the color and how many arguments were consumed.
+fn extended_color(args: &[u32]) -> (Option<String>, usize) {
+    match args {
+        [5, n, ..] => (Some(palette_color(*n)), 2),
+        [2, r, g, b, ..] => (Some(format!("#{r:02x}{g:02x}{b:02x}")), 4),
+        _ => (None, args.len()),
+    }
+}
+
+/// Maps a 256-color palette index to a hex color.
+fn palette_color(index: u32) -> String {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize].to_string(),
+        16..=231 => {
+            const LEVELS: [u32; 6] = [0, 95, 135, 175, 215, 255];
+            let index = index - 16;
+            let (r, g, b) = (
+                LEVELS[(index / 36) as usize],
+                LEVELS[(index / 6 % 6) as usize],
+                LEVELS[(index % 6) as usize],
+            );
+            format!("#{r:02x}{g:02x}{b:02x}")
+        }
+        _ => {
+            let gray = 8 + 10 * (index.min(255) - 232);
+            format!("#{gray:02x}{gray:02x}{gray:02x}")
+        }
+    }
+}
+
+/// Converts terminal output to HTML, carrying colors across lines and across streamed chunks.
+#[derive(Debug, Default)]
+pub struct AnsiRenderer {
+    style: Style,
+}
+
+impl AnsiRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders a chunk of complete lines as a `<pre>` block.
+    pub fn render(&mut self, text: &str) -> String {
+        let mut html = String::from("<pre class=\"ansi-output\"><code>");
+
+        for line in text.split_inclusive('\n') {
+            let (line, newline) = match line.strip_suffix('\n') {
+                Some(line) => (line.strip_suffix('\r').unwrap_or(line), true),
+                None => (line, false),
+            };
+
+            // A carriage return redraws the line (progress bars); only the last redraw is shown,
+            // but style changes in the overwritten text still apply
+            let (overwritten, visible) = match line.rfind('\r') {
+                Some(index) => (&line[..index], &line[index + 1..]),
+                None => ("", line),
+            };
+            self.render_segment(overwritten, &mut String::new());
+            self.render_segment(visible, &mut html);
+
+            if newline {
+                html.push('\n');
+            }
+        }
+
+        html.push_str("</code></pre>\n");
+        html
+    }
+
+    fn render_segment(&mut self, text: &str, html: &mut String) {
+        // Spans are opened lazily so a style change right before another one emits nothing
+        let mut span_open = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let escaped = match c {
+                ESC => {
+                    match chars.next() {
+                        // CSI: parameters, then a final byte in '@'..='~'
+                        Some('[') => {
+                            let mut params = String::new();
+                            let mut final_byte = None;
+                            for c in chars.by_ref() {
+                                if ('@'..='~').contains(&c) {
+                                    final_byte = Some(c);
+                                    break;
+                                }
+                                params.push(c);
+                            }
+                            // Cursor movement, erase and other CSI sequences can't be represented
+                            if final_byte == Some('m') {
+                                if span_open {
+                                    html.push_str("</span>");
+                                    span_open = false;
+                                }
+                                self.style.apply_sgr(&params);
+                            }
+                        }
+                        // OSC (window titles, hyperlinks): skip to BEL or ST (`ESC \`)
+                        Some(']') => {
+                            while let Some(c) = chars.next() {
+                                if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                                    break;
+                                }
+                            }
+                        }
+                        _ => {} // Other two-character escapes
+                    }
+                    continue;
+                }
+                '&' => "&amp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '\t' => "\t",
+                c if c.is_control() => continue, // Backspace, bell and other stray control characters
+                _ => "",
+            };
+
+            if !span_open {
+                span_open = self.open_span(html);
+            }
+            if escaped.is_empty() {
+                html.push(c);
+            } else {
+                html.push_str(escaped);
+            }
+        }
+
+        if span_open {
+            html.push_str("</span>");
+        }
+    }
+
+    /// Opens a span for the current style, returning whether one was needed.
+    fn open_span(&self, html: &mut String) -> bool {
+        let css = self.style.css();
+        if css.is_empty() {
+            return false;
+        }
+        html.push_str(&format!("<span style=\"{css}\">"));
+        true
+    }
+}
@@ -0,0 +1,238 @@
+//! Command-line argument handling.
+
+use log::warn;
+
+use crate::markdown::Dialect;
+
+/// Text printed for `--help`.
+pub const USAGE: &str = "\
+Hoss' Opinionated Markdown Output
+
+Usage:
+  homo [OPTIONS] FILE      View a markdown file, or follow a named pipe across writers
+  homo [OPTIONS] URL       View a markdown document fetched over http(s)
+  command | homo [OPTIONS] View piped markdown, terminal output or plain text as it arrives
+
+Options:
+  --stream-files           Render FILE incrementally with a progress bar
+  --first-lines N          Lines of streamed markdown shown in the first update (default 5)
+  --flush-lines N          Lines of streamed markdown collected per update (default 10)
+  --plain                  Show piped input verbatim instead of as markdown
+  --max-buffer SIZE        Keep at most SIZE (e.g. 50MB) of streamed input, trimming the oldest
+  --stay-open              Open an empty window when there is no file or piped input
+  --source                 Open in the Source view rather than the rendered preview
+  --low-power              Don't animate, and only wake for new input, even while streaming
+  --title TEXT             Window title for piped input instead of its first heading
+  --no-tables              Leave pipe tables as plain text
+  --no-footnotes           Leave [^note] footnotes as plain text
+  --no-strikethrough       Leave ~~strikethrough~~ as plain text
+  --no-tasklists           Show - [ ] task items as plain list items
+  --no-highlight           Leave ==highlighted== text as plain text
+  --math                   Render $inline$ and $$display$$ math with KaTeX
+  --sub-sup                Render H~2~O subscripts and x^2^ superscripts
+  --crossref               Number {#fig:label} figures and {#eq:label} equations and resolve
+                           @fig:label references
+  --smart                  Use typographic quotes and dashes for this launch
+  --sanitize-html          Remove scripts, styles and event handlers from raw HTML
+  --confirm-links          Ask before opening clicked web links, remembered for later launches
+  --syntax-theme PATH      Highlight code with a .tmTheme file
+  --inline-lang LANG       Highlight `inline code` as LANG, e.g. rust
+  --css PATH               Stylesheet applied after the built-in styles
+  --font NAME              Font family for the document, remembered for later launches
+  --code-background COLOR  Code block background such as #fdf6e3 in every theme, remembered
+                           for later launches; none returns to the theme's
+  --wiki-base URL          URL prefix that [[wiki links]] resolve against
+  --offline                Don't contact diagram servers; show diagram source instead
+  --cdn-base URL           Load Mermaid, KaTeX and highlight.js from a mirror of
+                           cdn.jsdelivr.net/npm/ (or set HOMO_CDN_BASE)
+  --export-html PATH       Write a standalone HTML document and exit
+  --dump-json              Print the headings, code blocks, links and word count of FILE (or
+                           piped markdown) as JSON and exit
+  --serve PORT             Serve FILE over HTTP with live reload instead of opening a window
+                           (builds with the server feature)
+  -h, --help               Print this help and exit
+  -V, --version            Print the version and exit
+";
+
+/// Options parsed from the command line.
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    /// Markdown file to open. `None` means read from stdin.
+    pub file: Option<String>,
+    /// Stream the file line-by-line (with a progress bar) instead of loading it at once.
+    pub stream_files: bool,
+    /// Path to a `.tmTheme` file used for syntax highlighting instead of the built-in themes.
+    pub syntax_theme: Option<String>,
+    /// Language to highlight inline code spans as, e.g. `rust`; they stay plain without it.
+    pub inline_lang: Option<String>,
+    /// Write a standalone HTML document to this path and exit without opening a window.
+    pub export_html: Option<String>,
+    /// Print the document's outline as JSON and exit without opening a window.
+    pub dump_json: bool,
+    /// Don't contact diagram servers; show diagram source instead.
+    pub offline: bool,
+    /// Mirror of jsdelivr's npm tree that scripts and stylesheets load from.
+    pub cdn_base: Option<String>,
+    /// Show piped input verbatim as preformatted text instead of parsing it as markdown.
+    pub plain: bool,
+    /// Window title for piped input, used instead of any title found in the content.
+    pub title: Option<String>,
+    /// Open an empty window instead of exiting when there is no file or piped input.
+    pub stay_open: bool,
+    /// Show the first document in the Source view instead of the rendered preview.
+    pub source: bool,
+    /// Go without animations and the polling thread that keeps streamed input flowing.
+    pub low_power: bool,
+    /// Stylesheet appended after the built-in styles, instead of `~/.config/homo/custom.css`.
+    pub css: Option<String>,
+    /// Font family to use for the document, remembered for later launches.
+    pub font: Option<String>,
+    /// Code block background (`#rgb`/`#rrggbb`, or `none` for the theme's), remembered for
+    /// later launches.
+    pub code_background: Option<String>,
+    /// Serve the file over HTTP on this port instead of opening a window.
+    pub serve: Option<u16>,
+    /// Print usage and exit.
+    pub help: bool,
+    /// Print the version and exit.
+    pub version: bool,
+    /// Lines of streamed markdown collected before the first update.
+    pub first_lines: Option<usize>,
+    /// Lines of streamed markdown collected before an update is forced.
+    pub flush_lines: Option<usize>,
+    /// Most streamed input kept in memory, in bytes; older content is trimmed beyond it.
+    pub max_buffer: Option<usize>,
+    /// URL prefix that `[[wiki links]]` resolve against, e.g. `file:///Users/me/vault/`.
+    pub wiki_base: Option<String>,
+    /// Markdown extensions to parse.
+    pub dialect: Dialect,
+    /// Turn on smart punctuation regardless of the saved preference.
+    pub smart: bool,
+    /// Remove `<script>`, `<style>` and event handler attributes from raw HTML in markdown.
+    pub sanitize_html: bool,
+    /// Ask before opening clicked web links, remembered for later launches.
+    pub confirm_links: bool,
+}
+
+impl CliOptions {
+    /// Parses the process arguments (including the program name at index 0).
+    pub fn parse(args: &[String]) -> Self {
+        let mut options = Self::default();
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => options.help = true,
+                "-V" | "--version" => options.version = true,
+                "--stream-files" => options.stream_files = true,
+                "--offline" => options.offline = true,
+                "--plain" => options.plain = true,
+                "--stay-open" => options.stay_open = true,
+                "--low-power" => options.low_power = true,
+                "--dump-json" => options.dump_json = true,
+                "--source" => options.source = true,
+                "--no-tables" => options.dialect.tables = false,
+                "--no-footnotes" => options.dialect.footnotes = false,
+                "--no-strikethrough" => options.dialect.strikethrough = false,
+                "--no-tasklists" => options.dialect.tasklists = false,
+                "--no-highlight" => options.dialect.highlight = false,
+                "--math" => options.dialect.math = true,
+                "--sub-sup" => options.dialect.sub_sup = true,
+                "--crossref" => options.dialect.crossref = true,
+                "--smart" => options.smart = true,
+                "--sanitize-html" => options.sanitize_html = true,
+                "--confirm-links" => options.confirm_links = true,
+                "--syntax-theme" => match args.next() {
+                    Some(path) => options.syntax_theme = Some(path.clone()),
+                    None => warn!("--syntax-theme requires a path to a .tmTheme file"),
+                },
+                "--inline-lang" => match args.next() {
+                    Some(language) if !language.trim().is_empty() => {
+                        options.inline_lang = Some(language.trim().to_string())
+                    }
+                    _ => warn!("--inline-lang requires a language such as rust"),
+                },
+                "--title" => match args.next() {
+                    Some(title) if !title.trim().is_empty() => {
+                        options.title = Some(title.trim().to_string())
+                    }
+                    _ => warn!("--title requires a title"),
+                },
+                "--css" => match args.next() {
+                    Some(path) => options.css = Some(path.clone()),
+                    None => warn!("--css requires a path to a stylesheet"),
+                },
+                "--font" => match args.next() {
+                    Some(name) if !name.trim().is_empty() => {
+                        options.font = Some(name.trim().to_string())
+                    }
+                    _ => warn!("--font requires a font family name"),
+                },
+                "--code-background" => match args.next() {
+                    Some(color) if !color.trim().is_empty() => {
+                        options.code_background = Some(color.trim().to_string())
+                    }
+                    _ => warn!("--code-background requires a color such as #fdf6e3, or none"),
+                },
+                "--cdn-base" => match args.next() {
+                    Some(base) => options.cdn_base = Some(base.clone()),
+                    None => warn!("--cdn-base requires a URL"),
+                },
+                "--wiki-base" => match args.next() {
+                    Some(base) => options.wiki_base = Some(base.clone()),
+                    None => warn!("--wiki-base requires a URL"),
+                },
+                "--first-lines" => match parse_line_count(args.next()) {
+                    Some(lines) => options.first_lines = Some(lines),
+                    None => warn!("--first-lines requires a positive number of lines"),
+                },
+                "--flush-lines" => match parse_line_count(args.next()) {
+                    Some(lines) => options.flush_lines = Some(lines),
+                    None => warn!("--flush-lines requires a positive number of lines"),
+                },
+                "--max-buffer" => match args.next().and_then(|size| parse_byte_size(size)) {
+                    Some(bytes) => options.max_buffer = Some(bytes),
+                    None => warn!("--max-buffer requires a size such as 50MB"),
+                },
+                "--export-html" => match args.next() {
+                    Some(path) => options.export_html = Some(path.clone()),
+                    None => warn!("--export-html requires an output path"),
+                },
+                "--serve" => match args.next().and_then(|port| port.parse().ok()) {
+                    Some(port) if port > 0 => options.serve = Some(port),
+                    _ => warn!("--serve requires a port number"),
+                },
+                flag if flag.starts_with('-') => warn!("Ignoring unknown option: {flag}"),
+                file => {
+                    if options.file.is_none() {
+                        options.file = Some(file.to_string());
+                    } else {
+                        warn!("Ignoring extra file argument: {file}");
+                    }
+                }
+            }
+        }
+
+        options
+    }
+}
+
+/// Parses a line-count argument, rejecting zero, negative and non-numeric values.
+fn parse_line_count(arg: Option<&String>) -> Option<usize> {
+    arg?.parse().ok().filter(|lines| *lines > 0)
+}
+
+/// Parses a size such as `50MB`, `512K` or `1048576` (bytes), with binary units.
+fn parse_byte_size(arg: &str) -> Option<usize> {
+    let arg = arg.trim();
+    let digits = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let multiplier = match arg[digits..].trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    let size: usize = arg[..digits].parse().ok()?;
+    size.checked_mul(multiplier).filter(|bytes| *bytes > 0)
+}
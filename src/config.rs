@@ -0,0 +1,44 @@
+//! User configuration loaded from `~/.config/homo/config.json`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::error::AppError;
+
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Settings read from the user's config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Names of plugins that should not process any content.
+    pub disabled_plugins: Vec<String>,
+    /// Per-plugin settings keyed by plugin name, passed to `Plugin::configure`.
+    pub plugins: HashMap<String, serde_json::Value>,
+    /// Plugin name keyed by code block language, for languages more than one plugin claims.
+    pub language_overrides: HashMap<String, String>,
+}
+
+/// Returns the directory that holds homo's user configuration files.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("homo"))
+}
+
+impl AppConfig {
+    /// Reads the config file, returning the defaults when it does not exist.
+    pub fn load() -> Result<Self, AppError> {
+        let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)) else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| AppError::Config(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(AppError::from(e)),
+        }
+    }
+}
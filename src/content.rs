@@ -1,8 +1,49 @@
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
+use crate::ansi;
 use crate::gui::types::StylePreferences;
 use crate::markdown;
 
+/// Reading speed used for the estimated reading time.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Window title used when a document offers nothing better.
+pub const DEFAULT_TITLE: &str = "Hoss' Opinionated Markdown Output";
+
+/// Percent-encodes a filesystem path for use in a `file://` URL.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Returns the filesystem path of a `file://` URL, without any `#fragment` or `?query`.
+pub fn file_url_path(url: &str) -> Option<String> {
+    let path = url.strip_prefix("file://")?;
+    let path = path.split(['#', '?']).next().unwrap_or(path);
+
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub enum ViewMode {
     #[default]
@@ -10,10 +51,41 @@ pub enum ViewMode {
     Source,
 }
 
+/// What kind of text a document holds, which decides how it is rendered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ContentFormat {
+    #[default]
+    Markdown,
+    /// Terminal output with ANSI escape codes, shown as colored preformatted text
+    Ansi,
+    /// Text that isn't markdown, such as logs, shown verbatim as preformatted text
+    Plain,
+}
+
+/// Renders text verbatim as a preformatted block.
+///
+/// Streamed chunks each get their own block; the blocks have no margins between them, so the
+/// page reads as one continuous block.
+pub fn plain_to_html(text: &str) -> String {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!("<pre class=\"plain-output\"><code>{escaped}</code></pre>\n")
+}
+
 #[derive(Debug, Clone)]
 pub enum ContentUpdate {
-    FullReplace(DocumentContent),
-    Append { markdown: String, html: String }, // Both markdown and HTML chunks to append
+    FullReplace(Box<DocumentContent>), // Boxed, as a document dwarfs the other updates
+    Append {
+        markdown: String,
+        html: String,
+        standalone: bool, // Whether the chunk renders the same on its own as in the document
+    }, // Both markdown and HTML chunks to append
+    Progress(f64), // Fraction (0.0 to 1.0) of a known-length input that has been read
+    StreamEnd,     // The input has closed; no more updates will follow
+    Clear,         // Empty the document; later appends start from nothing
+    Error(String), // The input couldn't be read; shown to the user
 }
 
 #[derive(Debug, Clone)]
@@ -21,10 +93,15 @@ pub struct DocumentContent {
     pub markdown: String,
     pub html: String,
     pub mode: ViewMode,
-    #[allow(dead_code)]
+    pub format: ContentFormat,
     pub title: String,
-    #[allow(dead_code)]
     pub file_path: Option<String>,
+    /// The `http(s)` URL the document was fetched from, if any
+    pub source_url: Option<String>,
+    /// Title given with `--title`, which wins over any title found in the content
+    pub title_override: Option<String>,
+    /// Heading anchor to scroll to when the document is first shown, from `file.md#section`
+    pub fragment: Option<String>,
     pub style_preferences: StylePreferences,
 }
 
@@ -34,15 +111,85 @@ impl DocumentContent {
             markdown,
             html,
             mode: ViewMode::default(),
+            format: ContentFormat::default(),
             title,
             file_path,
+            source_url: None,
+            title_override: None,
+            fragment: None,
             style_preferences: StylePreferences::default(),
         }
     }
 
+    /// Returns the title to display for this document: a `--title` override, then the front
+    /// matter `title`, then the first H1, then the file name (or last URL path segment), then
+    /// [`DEFAULT_TITLE`].
+    pub fn preferred_title(&self) -> String {
+        if let Some(title) = &self.title_override {
+            return title.clone();
+        }
+        markdown::front_matter_title(&self.markdown)
+            .or_else(|| markdown::first_heading(&self.markdown))
+            .or_else(|| {
+                self.file_path.as_deref().and_then(|path| {
+                    Path::new(path)
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map(str::to_string)
+                })
+            })
+            // Fetched documents are titled after their URL when created
+            .or_else(|| self.source_url.as_ref().map(|_| self.title.clone()))
+            .unwrap_or_else(|| DEFAULT_TITLE.to_string())
+    }
+
+    /// Returns the URL that relative image and link paths resolve against: the file's directory
+    /// for file documents, the source URL for fetched ones, and `None` for piped input.
+    pub fn base_url(&self) -> Option<String> {
+        if let Some(url) = &self.source_url {
+            return Some(url.clone());
+        }
+
+        let path = Path::new(self.file_path.as_deref()?);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let directory = path.parent()?.to_string_lossy();
+        Some(format!("file://{}/", encode_path(&directory)))
+    }
+
+    /// Number of words of prose, excluding code blocks and HTML.
+    pub fn word_count(&self) -> usize {
+        match self.format {
+            ContentFormat::Markdown => markdown::count_words(&self.markdown),
+            ContentFormat::Ansi | ContentFormat::Plain => 0,
+        }
+    }
+
+    /// Estimated reading time, rounded up to whole minutes.
+    pub fn reading_time_minutes(&self) -> usize {
+        self.word_count().div_ceil(WORDS_PER_MINUTE)
+    }
+
+    /// Adds a streamed chunk to the end of the document. A `standalone` chunk's HTML is
+    /// appended as is; otherwise the chunk may continue or change what came before, so the
+    /// whole document is rendered again. Returns whether it was.
+    pub fn append_chunk(&mut self, markdown: &str, html: &str, standalone: bool) -> bool {
+        self.markdown.push_str(markdown);
+        if standalone {
+            self.html.push_str(html);
+        } else {
+            self.regenerate_html();
+        }
+        !standalone
+    }
+
     /// Regenerates the HTML content with the current theme
     pub fn regenerate_html(&mut self) {
-        self.html =
-            markdown::parse_markdown_with_theme(&self.markdown, &self.style_preferences.theme);
+        self.html = match self.format {
+            ContentFormat::Markdown => {
+                markdown::parse_markdown_with_theme(&self.markdown, &self.style_preferences.theme)
+            }
+            ContentFormat::Ansi => ansi::ansi_to_html(&self.markdown),
+            ContentFormat::Plain => plain_to_html(&self.markdown),
+        };
     }
 }
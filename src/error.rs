@@ -1,6 +1,8 @@
 use std::sync::mpsc::SendError;
 use thiserror::Error;
 
+use crate::content::ContentUpdate;
+
 /// The unified error type for the entire application.
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -10,9 +12,26 @@ pub enum AppError {
     #[error("I/O Error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Represents an error that can occur when sending a message
+    /// Represents an error that can occur when sending a content update
     /// from the streaming thread to the GUI thread. This happens if the
     /// GUI has already closed and the channel is broken.
-    #[error("Channel Send Error: {0}")]
-    ChannelSend(#[from] SendError<String>),
+    #[error("Channel Send Error: the GUI is no longer receiving content updates")]
+    ChannelSend,
+
+    /// Represents a malformed user configuration file.
+    #[error("Config Error: {0}")]
+    Config(String),
+
+    /// Represents a failure to fetch a document from a URL, either a
+    /// connection problem or an HTTP error status.
+    #[error("Network Error: {0}")]
+    Network(String),
+}
+
+/// Lets streaming code propagate failed sends with `?`. The undelivered update is dropped;
+/// it is large and of no use once the GUI has gone away.
+impl From<SendError<ContentUpdate>> for AppError {
+    fn from(_: SendError<ContentUpdate>) -> Self {
+        AppError::ChannelSend
+    }
 }
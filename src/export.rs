@@ -0,0 +1,290 @@
+//! Standalone HTML generation shared by the viewer and the headless export mode.
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use log::{debug, info, warn};
+
+use crate::config;
+use crate::content::{DEFAULT_TITLE, DocumentContent};
+use crate::error::AppError;
+use crate::gui::types::{StylePreferences, ThemeMode};
+use crate::markdown::{self, Dialect, RenderSettings};
+use crate::plugins::manager::{PLUGIN_MANAGER, initialize_plugins};
+use crate::plugins::{PluginContext, cdn_url};
+
+/// File name of the stylesheet loaded from the config directory when `--css` isn't given.
+const CUSTOM_CSS_FILE_NAME: &str = "custom.css";
+
+/// User stylesheet given with `--css`, overriding the config directory's `custom.css`.
+static CUSTOM_CSS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses the stylesheet at `path` instead of `~/.config/homo/custom.css`.
+pub fn set_custom_css_path(path: &str) {
+    if CUSTOM_CSS_PATH.set(PathBuf::from(path)).is_err() {
+        warn!("Custom stylesheet already set, ignoring {path}");
+    }
+}
+
+/// Reads the user stylesheet. It is read on every render so edits show up on reload.
+///
+/// A missing `custom.css` in the config directory is expected; any other read failure logs a
+/// warning and leaves only the built-in styles.
+fn load_custom_css() -> Option<String> {
+    let (path, explicit) = match CUSTOM_CSS_PATH.get() {
+        Some(path) => (path.clone(), true),
+        None => (config::config_dir()?.join(CUSTOM_CSS_FILE_NAME), false),
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(css) => Some(css),
+        Err(e) if e.kind() == ErrorKind::NotFound && !explicit => None,
+        Err(e) => {
+            warn!("Failed to read stylesheet {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Builds the document stylesheet: base styles, then plugin CSS, then the user's custom CSS.
+pub fn generate_stylesheet(content: &DocumentContent) -> String {
+    let base_css = content.style_preferences.generate_css();
+
+    // Get plugin CSS
+    let context = PluginContext {
+        theme_mode: content.style_preferences.theme.clone(),
+        is_streaming: false,
+        content_id: "main".to_string(),
+    };
+
+    let plugin_css = PLUGIN_MANAGER.get_all_css(&context);
+
+    let mut stylesheet = if plugin_css.is_empty() {
+        base_css
+    } else {
+        format!("{base_css}\n\n/* Plugin Styles */\n{plugin_css}")
+    };
+
+    // Last, so user rules win over built-in ones of the same specificity
+    if let Some(custom_css) = load_custom_css() {
+        stylesheet.push_str("\n\n/* Custom Styles */\n");
+        stylesheet.push_str(&custom_css);
+    }
+    stylesheet
+}
+
+/// npm package of highlight.js, loaded from the CDN when client-side highlighting is enabled.
+const HIGHLIGHT_JS_PACKAGE: &str = "@highlightjs/cdn-assets@11.9.0";
+
+/// Builds the `<link>`/`<script>` tags and inline plugin JavaScript for the page head.
+pub fn generate_scripts_html(content: &DocumentContent) -> String {
+    let context = PluginContext {
+        theme_mode: content.style_preferences.theme.clone(),
+        is_streaming: false,
+        content_id: "main".to_string(),
+    };
+
+    let mut html_parts = Vec::new();
+
+    // Get external CSS URLs
+    let external_css = PLUGIN_MANAGER.get_all_external_css();
+    let external_css_tags: Vec<String> = external_css
+        .iter()
+        .map(|url| format!(r#"<link rel="stylesheet" href="{url}">"#))
+        .collect();
+
+    html_parts.extend(external_css_tags);
+
+    // Get external script URLs
+    let external_scripts = PLUGIN_MANAGER.get_all_external_scripts();
+    let external_script_tags: Vec<String> = external_scripts
+        .iter()
+        .map(|url| format!(r#"<script src="{url}"></script>"#))
+        .collect();
+
+    html_parts.extend(external_script_tags);
+
+    if content.style_preferences.client_side_highlighting {
+        html_parts.push(highlight_js_tags(&content.style_preferences.theme));
+    }
+
+    // Get plugin JavaScript
+    let plugin_js = PLUGIN_MANAGER.get_all_javascript(&context);
+
+    if !plugin_js.is_empty() {
+        html_parts.push(format!("<script>\n{plugin_js}\n</script>"));
+    }
+
+    html_parts.join("\n")
+}
+
+/// Builds the highlight.js stylesheet and script tags for client-side code highlighting.
+///
+/// In System mode both GitHub styles are included behind `prefers-color-scheme` media
+/// queries, so code colors follow the system theme without a reload.
+fn highlight_js_tags(theme: &ThemeMode) -> String {
+    let base = cdn_url(HIGHLIGHT_JS_PACKAGE);
+    let stylesheet = |name: &str, media: &str| {
+        format!(r#"<link rel="stylesheet" href="{base}/styles/{name}.min.css" media="{media}">"#)
+    };
+    let stylesheets = match theme {
+        ThemeMode::Light => stylesheet("github", "all"),
+        ThemeMode::Dark => stylesheet("github-dark", "all"),
+        ThemeMode::System => format!(
+            "{}\n{}",
+            stylesheet("github", "(prefers-color-scheme: light)"),
+            stylesheet("github-dark", "(prefers-color-scheme: dark)")
+        ),
+    };
+
+    format!(
+        r#"{stylesheets}
+<style>pre code.hljs {{ background: transparent; padding: 0; }}</style>
+<script src="{base}/highlight.min.js"></script>
+<script>
+window.highlightCodeBlocks = function(root) {{
+    if (typeof hljs === 'undefined') {{
+        return;
+    }}
+    (root || document).querySelectorAll('pre code[class*="language-"]:not(.hljs)').forEach(function(block) {{
+        hljs.highlightElement(block);
+    }});
+}};
+document.addEventListener('DOMContentLoaded', function() {{
+    window.highlightCodeBlocks();
+}});
+</script>"#
+    )
+}
+
+/// Wraps rendered content in a complete HTML document with inlined styles and plugin assets.
+pub fn standalone_document(content: &DocumentContent) -> String {
+    let stylesheet = generate_stylesheet(content);
+    let scripts = generate_scripts_html(content);
+    let title = content
+        .preferred_title()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let body = &content.html;
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>{stylesheet}</style>
+    {scripts}
+</head>
+<body>
+{body}
+</body>
+</html>"#
+    )
+}
+
+/// How [`render_document`] renders a document.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Page style, including the parser settings among the preferences (smart punctuation,
+    /// autolinks, emoji shortcodes, syntax theme, code background and client-side
+    /// highlighting)
+    pub style_preferences: StylePreferences,
+    /// Markdown extensions to parse
+    pub dialect: Dialect,
+    /// Whether raw HTML in the markdown is sanitized
+    pub sanitize_html: bool,
+}
+
+/// Renders markdown to a standalone HTML document, including the stylesheet and plugin assets.
+///
+/// This is the entry point for embedding homo's rendering. Plugins are registered on first
+/// use. The parser's settings are process-wide: they are switched to `options` for this
+/// render and restored afterwards. Calls to `render_document` wait for each other, but
+/// rendering through [`markdown`] on another thread meanwhile isn't thread-safe, since it
+/// would pick up these settings.
+pub fn render_document(markdown_text: &str, options: &RenderOptions) -> String {
+    if let Err(e) = initialize_plugins() {
+        warn!("Failed to initialize plugins: {e}");
+    }
+    let style_preferences = &options.style_preferences;
+    let settings = RenderSettings {
+        dialect: options.dialect,
+        smart_punctuation: style_preferences.smart_punctuation,
+        autolinks: style_preferences.autolinks,
+        emoji_shortcodes: style_preferences.emoji_shortcodes,
+        sanitize_html: options.sanitize_html,
+        syntax_theme: style_preferences.syntax_theme.clone(),
+        code_background: style_preferences.code_background.clone(),
+        client_side_highlighting: style_preferences.client_side_highlighting,
+        ..RenderSettings::current()
+    };
+
+    let html = {
+        let _settings = settings.scoped();
+        markdown::parse_markdown_with_theme(markdown_text, &style_preferences.theme)
+    };
+    let title =
+        markdown::front_matter_title(markdown_text).unwrap_or_else(|| DEFAULT_TITLE.to_string());
+    let mut content = DocumentContent::new(markdown_text.to_string(), html, title, None);
+    content.style_preferences = style_preferences.clone();
+    standalone_document(&content)
+}
+
+/// Renders markdown from `input` (or stdin when `None`) to a standalone HTML file at `output`.
+pub fn export_html(
+    input: Option<&str>,
+    output: &str,
+    style_preferences: &StylePreferences,
+) -> Result<(), AppError> {
+    let markdown_text = read_markdown(input)?;
+    let title = markdown::front_matter_title(&markdown_text).unwrap_or_else(|| match input {
+        Some(path) => Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("Untitled")
+            .to_string(),
+        None => "Piped Input".to_string(),
+    });
+
+    let html = markdown::parse_markdown_with_theme(&markdown_text, &style_preferences.theme);
+
+    let mut content = DocumentContent::new(markdown_text, html, title, input.map(str::to_string));
+    content.style_preferences = style_preferences.clone();
+
+    fs::write(output, standalone_document(&content))?;
+    info!("Exported HTML to {output}");
+    Ok(())
+}
+
+/// Prints the outline of the markdown in `input` (or stdin when `None`) to stdout as JSON:
+/// headings with their anchors, code blocks, links and the word count.
+pub fn dump_json(input: Option<&str>) -> Result<(), AppError> {
+    let markdown_text = read_markdown(input)?;
+    let outline = markdown::document_outline(&markdown_text);
+
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &outline).map_err(io::Error::from)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Reads the markdown of a file, with a diagram file as a document holding just that diagram,
+/// or of stdin when `input` is `None`.
+fn read_markdown(input: Option<&str>) -> Result<String, AppError> {
+    match input {
+        Some(path) => {
+            debug!("Reading markdown from {path}");
+            Ok(markdown::file_markdown(path, fs::read_to_string(path)?))
+        }
+        None => {
+            debug!("Reading markdown from stdin");
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            Ok(buffer)
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Detection of the system light/dark appearance.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use cocoa::appkit::NSApp;
+use cocoa::base::{BOOL, NO, id, nil};
+use cocoa::foundation::{NSArray, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+
+const AQUA: &str = "NSAppearanceNameAqua";
+const DARK_AQUA: &str = "NSAppearanceNameDarkAqua";
+
+/// Returns whether the app is currently drawn with a dark appearance.
+///
+/// Uses `NSApp.effectiveAppearance` once the application exists. Before that (the first
+/// document is parsed while the app is still starting up) the global `AppleInterfaceStyle`
+/// default is used instead, since creating `NSApp` early would bypass cacao's app class.
+pub fn system_is_dark() -> bool {
+    unsafe {
+        let app: id = NSApp();
+        if app == nil {
+            // Only called once at startup, before any autorelease pool exists
+            let user_defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+            let key = NSString::alloc(nil).init_str("AppleInterfaceStyle");
+            let style: id = msg_send![user_defaults, stringForKey: key];
+            if style == nil {
+                return false;
+            }
+            let is_dark: BOOL =
+                msg_send![style, isEqualToString: NSString::alloc(nil).init_str("Dark")];
+            return is_dark != NO;
+        }
+
+        let appearance: id = msg_send![app, effectiveAppearance];
+        if appearance == nil {
+            return false;
+        }
+
+        let aqua: id = msg_send![NSString::alloc(nil).init_str(AQUA), autorelease];
+        let dark_aqua: id = msg_send![NSString::alloc(nil).init_str(DARK_AQUA), autorelease];
+        let names = NSArray::arrayWithObjects(nil, &[aqua, dark_aqua]);
+        let best_match: id = msg_send![appearance, bestMatchFromAppearancesWithNames: names];
+        if best_match == nil {
+            return false;
+        }
+
+        let is_dark: BOOL = msg_send![best_match, isEqualToString: dark_aqua];
+        is_dark != NO
+    }
+}
@@ -0,0 +1,80 @@
+//! The alert shown when homo panics, so a crash doesn't just make the window vanish.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use std::panic::{self, PanicHookInfo};
+use std::thread;
+
+use cacao::pasteboard::Pasteboard;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use log::error;
+use objc::{class, msg_send, sel, sel_impl};
+
+/// `NSAlertSecondButtonReturn`
+const SECOND_BUTTON_RETURN: isize = 1001;
+
+/// Exit status after a panic, the same one Rust uses when a panic ends the process
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// Replaces the panic hook with one that logs the panic, shows it in an alert on the main
+/// thread, and exits. Panics on background threads would otherwise leave the window open but
+/// no longer updating.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let details = panic_details(info);
+        error!("{details}");
+        if thread::current().name() == Some("main") {
+            show_crash_alert(&details);
+        } else {
+            cacao::utils::sync_main_thread(move || show_crash_alert(&details));
+        }
+        std::process::exit(PANIC_EXIT_CODE);
+    }));
+}
+
+/// Describes a panic for the alert and the log: version, thread, location and message.
+fn panic_details(info: &PanicHookInfo) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(no message)");
+    let thread = thread::current();
+    let location = info
+        .location()
+        .map_or_else(|| "unknown".to_string(), ToString::to_string);
+    format!(
+        "homo {} panicked on thread '{}' at {location}:\n{message}",
+        env!("CARGO_PKG_VERSION"),
+        thread.name().unwrap_or("unnamed"),
+    )
+}
+
+/// Runs a modal alert with the panic details, copying them to the clipboard if asked. Must be
+/// called on the main thread.
+fn show_crash_alert(details: &str) {
+    unsafe {
+        let alert: id = msg_send![class!(NSAlert), new];
+        let message = NSString::alloc(nil).init_str("homo quit unexpectedly");
+        let _: () = msg_send![alert, setMessageText: message];
+        let informative = NSString::alloc(nil).init_str(details);
+        let _: () = msg_send![alert, setInformativeText: informative];
+        let _: id = msg_send![alert, addButtonWithTitle: NSString::alloc(nil).init_str("Quit")];
+        let _: id =
+            msg_send![alert, addButtonWithTitle: NSString::alloc(nil).init_str("Copy Details")];
+
+        let response: isize = msg_send![alert, runModal];
+        let _: () = msg_send![alert, release];
+        if response == SECOND_BUTTON_RETURN {
+            let pasteboard = Pasteboard::default();
+            pasteboard.clear_contents();
+            pasteboard.copy_text(details);
+        }
+    }
+}
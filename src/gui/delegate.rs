@@ -5,6 +5,8 @@
 
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::fs;
+use std::io;
 use std::rc::Rc;
 use std::sync::mpsc::{self};
 use std::sync::{Arc, Mutex};
@@ -13,22 +15,101 @@ use std::time::Duration;
 
 use cacao::appkit::window::Window;
 use cacao::appkit::{App, AppDelegate};
-use log::{debug, info};
-
-use crate::content::{ContentUpdate, DocumentContent};
+use cacao::foundation::NSString;
+use cacao::pasteboard::{Pasteboard, PasteboardType};
+use log::{debug, error, info, warn};
+
+use crate::config::AppConfig;
+use crate::content::{ContentUpdate, DocumentContent, ViewMode, file_url_path};
+use crate::error::AppError;
+use crate::export;
+use crate::gui::appearance;
+use crate::gui::link_alert;
+use crate::gui::open_panel;
+use crate::gui::save_panel;
+use crate::gui::settings_window::{self, SettingsWindow};
 use crate::gui::types::{FontFamily, StylePreferences, ThemeMode};
-use crate::gui::view::{MarkdownView, ScrollBehavior};
-use crate::gui::window::{create_main_window, create_main_window_with_content};
+use crate::gui::view::{MarkdownView, ScrollBehavior, StreamState};
+use crate::gui::window::{
+    EMPTY_WINDOW_MESSAGE, LOADING_MESSAGE, create_main_window, create_main_window_with_content,
+    fit_window_to_content, is_full_screen, toggle_full_screen,
+};
+use crate::markdown;
 use crate::menu::{self, MenuMessage};
+use crate::plugins::manager::PLUGIN_MANAGER;
+use crate::recent_files;
+use crate::streaming;
+
+/// Applies a task checkbox change to the file on disk, preserving its line endings.
+fn write_task_to_file(path: &str, line: usize, checked: bool) -> io::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let updated = markdown::set_task_checked(&source, line, checked)
+        .ok_or_else(|| io::Error::other(format!("no task on line {}", line + 1)))?;
+    fs::write(path, updated)
+}
+
+/// File extensions of linked documents that can open in homo rather than another app.
+const MARKDOWN_EXTENSIONS: [&str; 4] = ["md", "markdown", "mdown", "mkd"];
+
+/// Returns whether a path names a markdown document, or a diagram file shown like one.
+pub fn is_markdown_path(path: &str) -> bool {
+    let markdown = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            MARKDOWN_EXTENSIONS
+                .iter()
+                .any(|markdown| extension.eq_ignore_ascii_case(markdown))
+        });
+    markdown || markdown::diagram_language(path).is_some()
+}
+
+/// Opens a linked document (a local path or an `http(s)` URL) in another homo window.
+fn open_in_new_window(document: &str) {
+    info!("Opening linked document in a new window: {document}");
+    let result = std::env::current_exe()
+        .and_then(|exe| std::process::Command::new(exe).arg(document).spawn());
+    if let Err(e) = result {
+        error!("Failed to open {document} in a new window: {e}");
+    }
+}
+
+/// Puts an HTML document on the general pasteboard, both as rich HTML and as its plain source.
+fn copy_html_to_pasteboard(html: &str) {
+    let pasteboard = Pasteboard::default();
+    pasteboard.clear_contents();
+    pasteboard.copy_text(html);
+
+    let contents = NSString::new(html);
+    let html_type: NSString = PasteboardType::HTML.into();
+    unsafe {
+        use objc::{msg_send, sel, sel_impl};
+
+        let _: () = msg_send![&*pasteboard.0, setString: &*contents forType: html_type];
+    }
+}
 
 /// Handles the main window and markdown content updates.
 pub struct GuiDelegate {
     window: RefCell<Option<Window>>,
+    /// The Settings window, while it's open
+    settings_window: RefCell<Option<Window<SettingsWindow>>>,
     view: Rc<MarkdownView>,
     menu_setup: RefCell<bool>,
     current_document: RefCell<Option<DocumentContent>>,
     menu_receiver: RefCell<Option<mpsc::Receiver<MenuMessage>>>,
     is_pipe_mode: bool,
+    /// Whether content will arrive without user action; if not, the window starts empty
+    has_input: bool,
+    /// Whether to go without the polling thread and page animations, for `--low-power`
+    low_power: bool,
+    /// View mode for the first document, Source with `--source`; later documents open in
+    /// Preview as usual
+    initial_mode: RefCell<Option<ViewMode>>,
+    /// Title given with `--title`, used instead of any title found in the content
+    title_override: Option<String>,
+    /// Whether the input failed with an error, which then stays on screen when the stream ends
+    input_failed: RefCell<bool>,
     pending_content: Arc<Mutex<VecDeque<ContentUpdate>>>,
     style_preferences: RefCell<StylePreferences>,
     last_update_time: RefCell<std::time::Instant>,
@@ -48,7 +129,13 @@ enum InputRateCategory {
 
 impl GuiDelegate {
     /// Creates a new GUI delegate with an optional receiver for streamed ContentUpdate.
-    pub fn new(receiver: Option<mpsc::Receiver<ContentUpdate>>, is_pipe_mode: bool) -> Self {
+    pub fn new(
+        receiver: Option<mpsc::Receiver<ContentUpdate>>,
+        is_pipe_mode: bool,
+        title_override: Option<String>,
+        low_power: bool,
+        initial_mode: ViewMode,
+    ) -> Self {
         // Set up menu message channel
         let (menu_sender, menu_receiver) = mpsc::channel();
         menu::set_menu_sender(menu_sender);
@@ -56,8 +143,17 @@ impl GuiDelegate {
         // Create shared state for pending content queue
         let pending_content = Arc::new(Mutex::new(VecDeque::new()));
 
+        let view = Rc::new(MarkdownView::new());
+        view.set_low_power(low_power);
+        view.set_mode(initial_mode.clone());
+        let has_input = receiver.is_some();
+
         // Start background thread to continuously poll original receiver
         if let Some(orig_receiver) = receiver {
+            // Streamed input shows a badge, which turns to "done" once the sender is dropped
+            if is_pipe_mode {
+                view.set_stream_state(StreamState::Open);
+            }
             let pending_content_clone = pending_content.clone();
             thread::spawn(move || {
                 while let Ok(content_update) = orig_receiver.recv() {
@@ -65,17 +161,29 @@ impl GuiDelegate {
                         pending.push_back(content_update);
                         debug!("Queued content update, queue size: {}", pending.len());
                     }
+                    wake_main_thread();
                 }
+                debug!("Content sender closed");
+                if let Ok(mut pending) = pending_content_clone.lock() {
+                    pending.push_back(ContentUpdate::StreamEnd);
+                }
+                wake_main_thread();
             });
         }
 
         GuiDelegate {
             window: RefCell::new(None),
-            view: Rc::new(MarkdownView::new()),
+            settings_window: RefCell::new(None),
+            view,
             menu_setup: RefCell::new(false),
             current_document: RefCell::new(None),
             menu_receiver: RefCell::new(Some(menu_receiver)),
             is_pipe_mode,
+            has_input,
+            low_power,
+            initial_mode: RefCell::new(Some(initial_mode)),
+            title_override,
+            input_failed: RefCell::new(false),
             pending_content,
             style_preferences: RefCell::new(StylePreferences::load_from_user_defaults()),
             last_update_time: RefCell::new(std::time::Instant::now()),
@@ -97,10 +205,88 @@ impl GuiDelegate {
         *self.menu_setup.borrow_mut() = true;
     }
 
+    /// Rebuilds the menu bar so File → Open Recent reflects the current list
+    fn refresh_menu(&self) {
+        App::set_menu(menu::create_menus());
+    }
+
+    /// Copies the whole document's markdown source to the clipboard
+    pub fn copy_markdown(&self) {
+        let Some(document) = self.current_document.borrow().clone() else {
+            return;
+        };
+        let pasteboard = Pasteboard::default();
+        pasteboard.clear_contents();
+        pasteboard.copy_text(&document.markdown);
+        info!("Copied {} bytes of markdown", document.markdown.len());
+    }
+
+    /// Copies the whole document as standalone HTML with its stylesheet inlined, so it pastes
+    /// with formatting into rich-text editors
+    pub fn copy_html(&self) {
+        let Some(mut document) = self.current_document.borrow().clone() else {
+            return;
+        };
+        document.style_preferences = self.style_preferences.borrow().clone();
+        let html = export::standalone_document(&document);
+        copy_html_to_pasteboard(&html);
+        info!("Copied {} bytes of HTML", html.len());
+    }
+
+    /// Writes what the window shows to a file chosen in a save panel: the markdown source, or
+    /// a standalone HTML page when the chosen name ends in `.html`
+    pub fn save_as(&self) {
+        let Some(document) = self.current_document.borrow().clone() else {
+            return;
+        };
+        let stem = std::path::Path::new(&document.preferred_title())
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("Untitled")
+            .to_string();
+        let Some(path) = save_panel::choose_save_path(&format!("{stem}.md")) else {
+            return;
+        };
+
+        match self.write_document(document, &path) {
+            Ok(()) => info!("Saved document to {}", path.display()),
+            Err(e) => error!("Failed to save document to {}: {e}", path.display()),
+        }
+    }
+
+    fn write_document(
+        &self,
+        mut document: DocumentContent,
+        path: &std::path::Path,
+    ) -> Result<(), AppError> {
+        // The view also holds chunks streamed since the document was last replaced
+        document.markdown = self.view.accumulated_markdown();
+        document.html = self.view.accumulated_content();
+        document.style_preferences = self.style_preferences.borrow().clone();
+
+        let contents = if save_panel::is_html_path(path) {
+            export::standalone_document(&document)
+        } else {
+            document.markdown
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Forgets the recently opened files
+    pub fn clear_recent_files(&self) {
+        recent_files::clear_recent_files();
+        self.refresh_menu();
+    }
+
     /// Handles the toggle mode action
     pub fn toggle_mode(&self) {
         let style_preferences = self.style_preferences.borrow().clone();
-        self.view.toggle_mode(&style_preferences);
+        let mode = self.view.toggle_mode(&style_preferences);
+        // Style changes re-render the document in the mode it is shown in
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            current_document.mode = mode;
+        }
     }
 
     /// Handles font family change
@@ -131,6 +317,250 @@ impl GuiDelegate {
         self.update_content_with_new_styles();
     }
 
+    /// Changes the page zoom. Unlike font changes this needs no re-render, the WebView
+    /// scales the existing page.
+    fn zoom(&self, change: fn(&mut StylePreferences)) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        change(&mut style_preferences);
+        style_preferences.save_to_user_defaults();
+        self.view.set_page_zoom(style_preferences.zoom_level);
+
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            current_document.style_preferences = style_preferences.clone();
+        }
+    }
+
+    /// Changes the scale of Mermaid diagrams. Like page zoom this restyles the existing page
+    /// instead of re-rendering it.
+    fn scale_diagrams(&self, change: fn(&mut StylePreferences)) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        change(&mut style_preferences);
+        style_preferences.save_to_user_defaults();
+        self.view.set_mermaid_scale(style_preferences.mermaid_scale);
+
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            current_document.style_preferences = style_preferences.clone();
+        }
+    }
+
+    /// Switches to the next maximum content width preset
+    pub fn cycle_content_width(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.content_width = style_preferences.content_width.next();
+            info!("Content width: {:?}", style_preferences.content_width);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Switches to the next body line height preset
+    pub fn cycle_line_height(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.cycle_line_height();
+            info!("Line height: {}", style_preferences.line_height);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Switches to the next tab width preset for code
+    pub fn cycle_tab_width(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.cycle_tab_width();
+            info!("Tab width: {}", style_preferences.tab_width);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Switches code blocks between syntect and in-page highlight.js highlighting
+    pub fn toggle_client_side_highlighting(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.client_side_highlighting =
+                !style_preferences.client_side_highlighting;
+            markdown::set_client_side_highlighting(style_preferences.client_side_highlighting);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Turns typographic quotes and dashes on or off
+    pub fn toggle_smart_punctuation(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.smart_punctuation = !style_preferences.smart_punctuation;
+            markdown::set_smart_punctuation(style_preferences.smart_punctuation);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Turns linking of bare URLs on or off
+    pub fn toggle_autolinks(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.autolinks = !style_preferences.autolinks;
+            markdown::set_autolinks(style_preferences.autolinks);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Turns expansion of `:shortcode:` emoji on or off
+    pub fn toggle_emoji_shortcodes(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.emoji_shortcodes = !style_preferences.emoji_shortcodes;
+            markdown::set_emoji_shortcodes(style_preferences.emoji_shortcodes);
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Shows or hides the word count and reading time overlay
+    pub fn toggle_stats(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.show_stats = !style_preferences.show_stats;
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Turns the typewriter effect for appended content on or off, without reloading the page
+    pub fn toggle_typewriter(&self) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        style_preferences.typewriter = !style_preferences.typewriter;
+        style_preferences.save_to_user_defaults();
+        self.view.set_typewriter(style_preferences.typewriter);
+
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            current_document.style_preferences = style_preferences.clone();
+        }
+    }
+
+    /// Turns following appended content and the scroll-to-bottom button on or off, without
+    /// reloading the page
+    pub fn toggle_auto_scroll(&self) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        style_preferences.auto_scroll = !style_preferences.auto_scroll;
+        style_preferences.save_to_user_defaults();
+        self.view.set_auto_scroll(style_preferences.auto_scroll);
+
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            current_document.style_preferences = style_preferences.clone();
+        }
+    }
+
+    /// Shows or hides line numbers in the Source view
+    pub fn toggle_line_numbers(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.line_numbers = !style_preferences.line_numbers;
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Marks spaces, tabs and trailing whitespace in the Source view. In the Preview the
+    /// setting is only saved, for the next time the source is shown.
+    pub fn toggle_whitespace(&self) {
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.show_whitespace = !style_preferences.show_whitespace;
+            style_preferences.save_to_user_defaults();
+        }
+        let showing_source = self
+            .current_document
+            .borrow()
+            .as_ref()
+            .is_some_and(|document| document.mode == ViewMode::Source);
+        if showing_source {
+            self.update_content_with_new_styles();
+        }
+    }
+
+    /// Opens a clicked link. Local markdown files open in a new homo window, as do remote ones
+    /// when internal links are on; everything else goes to the system's default app. Web links
+    /// are confirmed first when link confirmation is on.
+    fn open_link(&self, url: &str) {
+        if let Some(path) = file_url_path(url).filter(|path| is_markdown_path(path)) {
+            open_in_new_window(&path);
+            return;
+        }
+
+        // Read before the alert, whose modal loop keeps handling menu messages
+        let confirm_links = self.style_preferences.borrow().confirm_links;
+        if confirm_links && streaming::is_url(url) && !link_alert::confirm_open_link(url) {
+            info!("Not opening link: {url}");
+            return;
+        }
+
+        let remote_markdown =
+            streaming::is_url(url) && url.split(['?', '#']).next().is_some_and(is_markdown_path);
+        if remote_markdown && self.style_preferences.borrow().internal_links {
+            open_in_new_window(url);
+        } else {
+            info!("Opening external link: {url}");
+            open::that(url).ok();
+        }
+    }
+
+    /// Switches links to remote markdown documents between homo and the browser
+    pub fn toggle_internal_links(&self) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        style_preferences.internal_links = !style_preferences.internal_links;
+        info!(
+            "Open markdown links in homo: {}",
+            style_preferences.internal_links
+        );
+        style_preferences.save_to_user_defaults();
+    }
+
+    /// Turns asking before web links are opened on or off
+    pub fn toggle_confirm_links(&self) {
+        let mut style_preferences = self.style_preferences.borrow_mut();
+        style_preferences.confirm_links = !style_preferences.confirm_links;
+        info!("Confirm links: {}", style_preferences.confirm_links);
+        style_preferences.save_to_user_defaults();
+    }
+
+    /// Opens the Settings window, or brings it to the front if it's already open
+    pub fn show_settings(&self) {
+        let mut settings_window = self.settings_window.borrow_mut();
+        match settings_window.as_ref() {
+            Some(window) => window.make_key_and_order_front(),
+            None => {
+                let preferences = self.style_preferences.borrow();
+                *settings_window = Some(settings_window::create_settings_window(&preferences));
+            }
+        }
+    }
+
+    /// Applies the Settings window's choices to the document without saving them yet
+    pub fn apply_settings(&self) {
+        if let Some(settings) = self
+            .settings_window
+            .borrow()
+            .as_ref()
+            .and_then(|window| window.delegate.as_deref())
+        {
+            settings.apply_to(&mut self.style_preferences.borrow_mut());
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Saves the settings chosen in the Settings window once it has closed
+    pub fn close_settings(&self) {
+        info!("Settings window closed, saving preferences");
+        self.style_preferences.borrow().save_to_user_defaults();
+        self.settings_window.borrow_mut().take();
+    }
+
     /// Handles theme change
     pub fn set_theme(&self, theme: ThemeMode) {
         self.style_preferences.borrow_mut().theme = theme;
@@ -138,14 +568,217 @@ impl GuiDelegate {
         self.update_content_with_new_styles();
     }
 
+    /// Highlights code with the named built-in syntax theme, or the default for the theme
+    pub fn set_syntax_theme(&self, name: Option<String>) {
+        info!("Setting syntax theme to {name:?}");
+        {
+            let mut style_preferences = self.style_preferences.borrow_mut();
+            style_preferences.syntax_theme = name;
+            markdown::set_syntax_theme(style_preferences.syntax_theme.clone());
+            style_preferences.save_to_user_defaults();
+        }
+        self.update_content_with_new_styles();
+    }
+
+    /// Switches to the next theme: Light, then Dark, then System
+    pub fn cycle_theme(&self) {
+        let theme = self.style_preferences.borrow().theme.next();
+        info!("Cycling theme to {theme:?}");
+        self.set_theme(theme);
+    }
+
+    /// Enters or leaves native full screen
+    pub fn toggle_full_screen(&self) {
+        if let Some(window) = self.window.borrow().as_ref() {
+            toggle_full_screen(window);
+        }
+    }
+
+    /// Applies the presentation style while the window is full screen, however it got there,
+    /// and removes it on exit
+    fn follow_full_screen(&self) {
+        let Some(full_screen) = self.window.borrow().as_ref().map(is_full_screen) else {
+            return;
+        };
+        if full_screen != self.view.is_presenting() {
+            info!(
+                "Presentation style {}",
+                if full_screen { "on" } else { "off" }
+            );
+            self.view.set_presentation(full_screen);
+        }
+    }
+
+    /// Re-renders when the system switches between light and dark appearance, so syntax
+    /// highlighting in `ThemeMode::System` follows it
+    fn follow_system_appearance(&self) {
+        let dark = appearance::system_is_dark();
+        if dark == markdown::system_dark_mode() {
+            return;
+        }
+
+        info!(
+            "System appearance changed to {}",
+            if dark { "dark" } else { "light" }
+        );
+        markdown::set_system_dark_mode(dark);
+        if self.style_preferences.borrow().theme == ThemeMode::System {
+            self.update_content_with_new_styles();
+        }
+    }
+
+    /// Reads a file (or fetches a URL) on a background thread and queues its content for display.
+    fn load_file(&self, path: String) {
+        let (sender, receiver) = mpsc::channel();
+        let pending_content = self.pending_content.clone();
+        thread::spawn(move || {
+            debug!("Loading file: {path}");
+            let result = if streaming::is_url(&path) {
+                streaming::read_from_url(sender, &path)
+            } else {
+                streaming::read_from_file(sender, &path, None)
+            };
+            if let Err(e) = result {
+                error!("Failed to load file {path}: {e}");
+                return;
+            }
+            if let Ok(mut pending) = pending_content.lock() {
+                pending.extend(receiver.try_iter());
+            }
+            wake_main_thread();
+        });
+    }
+
+    /// Asks for a markdown or diagram file with the open panel and shows it in this window.
+    fn open_document(&self) {
+        let extensions: Vec<&str> = MARKDOWN_EXTENSIONS
+            .into_iter()
+            .chain(markdown::DIAGRAM_EXTENSIONS.map(|(extension, _)| extension))
+            .collect();
+        if let Some(path) = open_panel::choose_open_path(&extensions) {
+            let path = path.to_string_lossy().into_owned();
+            info!("Opening {path}");
+            self.load_file(path);
+        }
+    }
+
+    /// Reloads the current document from disk, or refetches it from its URL. Does nothing for
+    /// piped input, since stdin cannot be rewound.
+    pub fn reload(&self) {
+        let file_path = self
+            .current_document
+            .borrow()
+            .as_ref()
+            .and_then(|document| document.file_path.clone().or(document.source_url.clone()));
+
+        match file_path {
+            Some(path) => {
+                info!("Reloading {path}");
+                self.load_file(path);
+            }
+            None => debug!("Reload ignored: current document has no backing file"),
+        }
+    }
+
+    /// Re-reads the config file, reconfigures plugins and re-renders the document.
+    /// On a malformed config the previous configuration stays active.
+    pub fn reload_config(&self) {
+        let config = match AppConfig::load() {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Failed to reload config: {e}");
+                self.view.show_banner(&format!(
+                    "Failed to reload config, keeping previous settings. {e}"
+                ));
+                return;
+            }
+        };
+
+        if let Err(e) = PLUGIN_MANAGER.apply_config(&config) {
+            error!("Failed to apply config: {e}");
+            self.view
+                .show_banner(&format!("Failed to apply config: {e}"));
+            return;
+        }
+
+        info!("Config reloaded");
+        self.update_content_with_new_styles();
+    }
+
+    /// Checks or unchecks the task on a source line. In file mode the change is written back to
+    /// the file; piped input only updates the in-memory document.
+    pub fn set_task_checked(&self, line: usize, checked: bool) {
+        let mut current_document = self.current_document.borrow_mut();
+        let Some(document) = current_document.as_mut() else {
+            return;
+        };
+
+        let Some(updated_markdown) = markdown::set_task_checked(&document.markdown, line, checked)
+        else {
+            warn!("No task found on line {line}");
+            return;
+        };
+        document.markdown = updated_markdown;
+        document.regenerate_html();
+        self.view.sync_content(document);
+
+        if let Some(path) = &document.file_path {
+            match write_task_to_file(path, line, checked) {
+                Ok(()) => info!("Updated task on line {} of {path}", line + 1),
+                Err(e) => {
+                    error!("Failed to update {path}: {e}");
+                    self.view
+                        .show_banner(&format!("Failed to save task to {path}: {e}"));
+                }
+            }
+        }
+    }
+
+    /// Reloads the whole page after it gave up on a streamed chunk. The current document
+    /// already holds every chunk, so nothing is lost.
+    fn reload_after_failed_append(&self) {
+        if let Some(current_document) = self.current_document.borrow().as_ref() {
+            let scroll_behavior = ScrollBehavior::Restore(self.view.scroll_ratio());
+            self.view
+                .update_content_with_scroll(current_document, scroll_behavior);
+        }
+    }
+
+    /// Empties the document and the page, and forgets the input rate so the next appends are
+    /// shown one by one again.
+    fn clear_content(&self) {
+        self.update_timestamps.borrow_mut().clear();
+        *self.current_rate_category.borrow_mut() = InputRateCategory::Slow;
+
+        if let Some(current_document) = self.current_document.borrow_mut().as_mut() {
+            info!("Clearing content");
+            current_document.markdown.clear();
+            current_document.html.clear();
+            self.view.clear_content(current_document);
+        }
+    }
+
     /// Updates the content with new styling preferences
     fn update_content_with_new_styles(&self) {
+        // Keep the Settings window in step with changes made from the menus
+        if let Some(settings) = self
+            .settings_window
+            .borrow()
+            .as_ref()
+            .and_then(|window| window.delegate.as_deref())
+        {
+            settings.show_preferences(&self.style_preferences.borrow());
+        }
+
         let mut current_document_option = self.current_document.borrow_mut();
         if let Some(current_document) = current_document_option.as_mut() {
             current_document.style_preferences = self.style_preferences.borrow().clone();
             // Regenerate HTML with new theme for syntax highlighting
             current_document.regenerate_html();
-            self.view.update_content(current_document);
+            // Keep the reader's place instead of jumping back to the top
+            let scroll_behavior = ScrollBehavior::Restore(self.view.scroll_ratio());
+            self.view
+                .update_content_with_scroll(current_document, scroll_behavior);
         }
     }
 
@@ -205,32 +838,74 @@ impl GuiDelegate {
         }
     }
 
+    /// Returns whether the background polling thread runs. Streamed input polls so batches
+    /// flush on time and appearance changes show while the window sits untouched; a file, a URL
+    /// or an empty window is only updated on events and queued content, as is `--low-power`.
+    fn polls_continuously(&self) -> bool {
+        self.is_pipe_mode && !self.low_power
+    }
+
     /// Set up background polling that properly dispatches to main thread  
     fn start_background_polling(&self) {
         thread::spawn(|| {
             loop {
                 thread::sleep(Duration::from_millis(100));
+                wake_main_thread();
+            }
+        });
+    }
 
-                // Use performSelectorOnMainThread to safely call updateWindows from background thread
-                // SAFETY: performSelectorOnMainThread is designed for cross-thread communication
-                unsafe {
-                    use cocoa::appkit::NSApp;
-                    use cocoa::base::{NO, id, nil};
-                    use core_foundation::runloop::{CFRunLoopGetMain, CFRunLoopWakeUp};
-                    use objc::{msg_send, sel, sel_impl};
-
-                    let app: id = NSApp();
-                    if app != nil {
-                        // Use performSelectorOnMainThread to safely execute on main thread
-                        let _: () = msg_send![app,  performSelectorOnMainThread:sel!(updateWindows) withObject:nil waitUntilDone:NO];
-                    }
+    /// Without polling, asks for another update once the current processing window has passed
+    /// if content is still queued, replacing any update asked for earlier.
+    fn schedule_pending_update(&self) {
+        if self.polls_continuously() {
+            return;
+        }
+        let queued = !self.pending_batch.borrow().is_empty()
+            || self
+                .pending_content
+                .lock()
+                .is_ok_and(|pending| !pending.is_empty());
+        if !queued {
+            return;
+        }
 
-                    // Also wake up the main run loop
-                    let main_loop = CFRunLoopGetMain();
-                    CFRunLoopWakeUp(main_loop);
-                }
+        let delay = self.get_processing_window().as_secs_f64();
+        // SAFETY: called on the main thread, which owns the shared application
+        unsafe {
+            use cocoa::appkit::NSApp;
+            use cocoa::base::{id, nil};
+            use objc::{class, msg_send, sel, sel_impl};
+
+            let app: id = NSApp();
+            if app != nil {
+                let _: () = msg_send![class!(NSObject), cancelPreviousPerformRequestsWithTarget:app selector:sel!(updateWindows) object:nil];
+                let _: () = msg_send![app, performSelector:sel!(updateWindows) withObject:nil afterDelay:delay];
             }
-        });
+        }
+    }
+}
+
+/// Has the main thread call `did_update` soon, e.g. after content is queued or a menu message
+/// sent from elsewhere. Safe to call from any thread.
+pub fn wake_main_thread() {
+    // Use performSelectorOnMainThread to safely call updateWindows from background thread
+    // SAFETY: performSelectorOnMainThread is designed for cross-thread communication
+    unsafe {
+        use cocoa::appkit::NSApp;
+        use cocoa::base::{NO, id, nil};
+        use core_foundation::runloop::{CFRunLoopGetMain, CFRunLoopWakeUp};
+        use objc::{msg_send, sel, sel_impl};
+
+        let app: id = NSApp();
+        if app != nil {
+            // Use performSelectorOnMainThread to safely execute on main thread
+            let _: () = msg_send![app,  performSelectorOnMainThread:sel!(updateWindows) withObject:nil waitUntilDone:NO];
+        }
+
+        // Also wake up the main run loop
+        let main_loop = CFRunLoopGetMain();
+        CFRunLoopWakeUp(main_loop);
     }
 }
 
@@ -238,12 +913,29 @@ impl AppDelegate for GuiDelegate {
     /// Called when the application finishes launching.
     fn did_finish_launching(&self) {
         // Menu setup is now handled when the first window is created
-        // Set up background polling to ensure updates continue when window is not focused
-        self.start_background_polling();
+        // Set up background polling to ensure streamed updates continue when window is not focused
+        if self.polls_continuously() {
+            self.start_background_polling();
+        }
+
+        // Show the window right away rather than only once a large file has been read
+        if self.window.borrow().is_none() {
+            self.setup_menu();
+            let window = create_main_window(&self.view, self.title_override.as_deref());
+            *self.window.borrow_mut() = Some(window);
+            if self.has_input {
+                self.view.show_placeholder(LOADING_MESSAGE);
+            } else {
+                self.view.show_drop_zone(EMPTY_WINDOW_MESSAGE);
+            }
+        }
     }
 
     /// Called when forced by background thread - handles all updates
     fn did_update(&self) {
+        self.follow_system_appearance();
+        self.follow_full_screen();
+
         // Handle menu messages
         if let Some(menu_receiver) = self.menu_receiver.borrow().as_ref() {
             while let Ok(menu_message) = menu_receiver.try_recv() {
@@ -255,6 +947,12 @@ impl AppDelegate for GuiDelegate {
                     MenuMessage::Copy => {
                         self.view.copy_selected_text();
                     }
+                    MenuMessage::CopyMarkdown => {
+                        self.copy_markdown();
+                    }
+                    MenuMessage::CopyHtml => {
+                        self.copy_html();
+                    }
                     MenuMessage::SelectAll => {
                         self.view.select_all_text();
                     }
@@ -270,9 +968,130 @@ impl AppDelegate for GuiDelegate {
                     MenuMessage::ResetFontSize => {
                         self.reset_font_size();
                     }
+                    MenuMessage::ZoomIn => {
+                        self.zoom(StylePreferences::zoom_in);
+                    }
+                    MenuMessage::ZoomOut => {
+                        self.zoom(StylePreferences::zoom_out);
+                    }
+                    MenuMessage::ZoomReset => {
+                        self.zoom(StylePreferences::reset_zoom);
+                    }
+                    MenuMessage::EnlargeDiagrams => {
+                        self.scale_diagrams(StylePreferences::enlarge_diagrams);
+                    }
+                    MenuMessage::ShrinkDiagrams => {
+                        self.scale_diagrams(StylePreferences::shrink_diagrams);
+                    }
+                    MenuMessage::ResetDiagramSize => {
+                        self.scale_diagrams(StylePreferences::reset_diagram_size);
+                    }
+                    MenuMessage::NextHeading => {
+                        self.view.jump_to_heading(true);
+                    }
+                    MenuMessage::PrevHeading => {
+                        self.view.jump_to_heading(false);
+                    }
+                    MenuMessage::CycleContentWidth => {
+                        self.cycle_content_width();
+                    }
+                    MenuMessage::CycleLineHeight => {
+                        self.cycle_line_height();
+                    }
+                    MenuMessage::CycleTabWidth => {
+                        self.cycle_tab_width();
+                    }
+                    MenuMessage::ToggleClientSideHighlighting => {
+                        self.toggle_client_side_highlighting();
+                    }
+                    MenuMessage::ToggleStats => {
+                        self.toggle_stats();
+                    }
+                    MenuMessage::ToggleSmartPunctuation => {
+                        self.toggle_smart_punctuation();
+                    }
+                    MenuMessage::ToggleAutolinks => {
+                        self.toggle_autolinks();
+                    }
+                    MenuMessage::ToggleEmojiShortcodes => {
+                        self.toggle_emoji_shortcodes();
+                    }
+                    MenuMessage::ToggleAutoScroll => {
+                        self.toggle_auto_scroll();
+                    }
+                    MenuMessage::ToggleTypewriter => {
+                        self.toggle_typewriter();
+                    }
+                    MenuMessage::ToggleLineNumbers => {
+                        self.toggle_line_numbers();
+                    }
+                    MenuMessage::ToggleWhitespace => {
+                        self.toggle_whitespace();
+                    }
                     MenuMessage::SetTheme(theme) => {
                         self.set_theme(theme);
                     }
+                    MenuMessage::CycleTheme => {
+                        self.cycle_theme();
+                    }
+                    MenuMessage::SetSyntaxTheme(name) => {
+                        self.set_syntax_theme(name);
+                    }
+                    MenuMessage::ToggleFullScreen => {
+                        self.toggle_full_screen();
+                    }
+                    MenuMessage::ReloadConfig => {
+                        self.reload_config();
+                    }
+                    MenuMessage::Reload => {
+                        self.reload();
+                    }
+                    MenuMessage::SetTaskChecked { line, checked } => {
+                        self.set_task_checked(line, checked);
+                    }
+                    MenuMessage::Print => {
+                        self.view.print();
+                    }
+                    MenuMessage::SaveAs => {
+                        self.save_as();
+                    }
+                    MenuMessage::ResyncContent => {
+                        self.view.resync_content();
+                    }
+                    MenuMessage::Clear => {
+                        self.clear_content();
+                    }
+                    MenuMessage::AppendFailed => {
+                        self.reload_after_failed_append();
+                    }
+                    MenuMessage::OpenLink(url) => {
+                        self.open_link(&url);
+                    }
+                    MenuMessage::ToggleInternalLinks => {
+                        self.toggle_internal_links();
+                    }
+                    MenuMessage::ToggleConfirmLinks => {
+                        self.toggle_confirm_links();
+                    }
+                    MenuMessage::ShowSettings => {
+                        self.show_settings();
+                    }
+                    MenuMessage::SettingsChanged => {
+                        self.apply_settings();
+                    }
+                    MenuMessage::SettingsClosed => {
+                        self.close_settings();
+                    }
+                    MenuMessage::Open => {
+                        self.open_document();
+                    }
+                    MenuMessage::OpenFile(path) => {
+                        info!("Opening {path}");
+                        self.load_file(path);
+                    }
+                    MenuMessage::ClearRecentFiles => {
+                        self.clear_recent_files();
+                    }
                 }
             }
         }
@@ -354,6 +1173,7 @@ impl AppDelegate for GuiDelegate {
 
             *last_update = now;
         }
+        self.schedule_pending_update();
     }
 
     /// Prevents the framework from opening an automatic "Untitled" window.
@@ -365,6 +1185,13 @@ impl AppDelegate for GuiDelegate {
     fn should_terminate_after_last_window_closed(&self) -> bool {
         true
     }
+
+    /// Saves settings still open in the Settings window, which would otherwise be saved on close.
+    fn will_terminate(&self) {
+        if self.settings_window.borrow().is_some() {
+            self.style_preferences.borrow().save_to_user_defaults();
+        }
+    }
 }
 
 impl GuiDelegate {
@@ -374,25 +1201,41 @@ impl GuiDelegate {
         let mut combined_updates = Vec::new();
         let mut current_markdown = String::new();
         let mut current_html = String::new();
+        let mut current_standalone = true;
+        let mut latest_progress = None;
+        let mut stream_ended = false;
 
         for update in batched_updates {
             match update {
-                ContentUpdate::FullReplace(content) => {
-                    // Flush any pending appends before the full replace
+                ContentUpdate::FullReplace(_) | ContentUpdate::Clear | ContentUpdate::Error(_) => {
+                    // Flush any pending appends before the full replace, clear or error
                     if !current_markdown.is_empty() {
                         combined_updates.push(ContentUpdate::Append {
                             markdown: current_markdown.clone(),
                             html: current_html.clone(),
+                            standalone: current_standalone,
                         });
                         current_markdown.clear();
                         current_html.clear();
+                        current_standalone = true;
                     }
-                    combined_updates.push(ContentUpdate::FullReplace(content));
+                    combined_updates.push(update);
                 }
-                ContentUpdate::Append { markdown, html } => {
+                ContentUpdate::Append {
+                    markdown,
+                    html,
+                    standalone,
+                } => {
                     current_markdown.push_str(&markdown);
                     current_html.push_str(&html);
+                    // The combined chunk is only standalone if every part of it is
+                    current_standalone &= standalone;
+                }
+                ContentUpdate::Progress(fraction) => {
+                    // Only the most recent progress value matters
+                    latest_progress = Some(fraction);
                 }
+                ContentUpdate::StreamEnd => stream_ended = true,
             }
         }
 
@@ -401,9 +1244,18 @@ impl GuiDelegate {
             combined_updates.push(ContentUpdate::Append {
                 markdown: current_markdown,
                 html: current_html,
+                standalone: current_standalone,
             });
         }
 
+        if let Some(fraction) = latest_progress {
+            combined_updates.push(ContentUpdate::Progress(fraction));
+        }
+
+        if stream_ended {
+            combined_updates.push(ContentUpdate::StreamEnd);
+        }
+
         // Process the combined updates normally
         for update in combined_updates {
             self.process_content_update(update);
@@ -415,7 +1267,11 @@ impl GuiDelegate {
         // For high-speed input, skip incremental appends and do full rebuilds
         let mut final_markdown = String::new();
         let mut found_full_replace = false;
-        let mut base_content: Option<DocumentContent> = None;
+        let mut base_content: Option<Box<DocumentContent>> = None;
+        let mut latest_progress = None;
+        let mut stream_ended = false;
+        let mut cleared = false;
+        let mut errors = Vec::new();
 
         // Accumulate all content changes
         for update in batched_updates {
@@ -428,13 +1284,30 @@ impl GuiDelegate {
                 ContentUpdate::Append { markdown, .. } => {
                     final_markdown.push_str(&markdown);
                 }
+                ContentUpdate::Progress(fraction) => {
+                    latest_progress = Some(fraction);
+                }
+                ContentUpdate::StreamEnd => stream_ended = true,
+                ContentUpdate::Clear => {
+                    // Everything before the clear is dropped
+                    cleared = true;
+                    base_content = None;
+                    found_full_replace = false;
+                    final_markdown.clear();
+                }
+                ContentUpdate::Error(message) => errors.push(message),
             }
         }
 
+        if cleared {
+            self.process_content_update(ContentUpdate::Clear);
+        }
+
         if found_full_replace {
             // We have a base document, append all accumulated content
             if let Some(mut content) = base_content {
                 content.markdown.push_str(&final_markdown);
+                streaming::trim_to_max_buffer(&mut content.markdown, &content.format);
                 content.regenerate_html();
 
                 debug!(
@@ -447,6 +1320,7 @@ impl GuiDelegate {
             // Only appends, update the current document directly
             if let Some(ref mut current_doc) = *self.current_document.borrow_mut() {
                 current_doc.markdown.push_str(&final_markdown);
+                streaming::trim_to_max_buffer(&mut current_doc.markdown, &current_doc.format);
                 current_doc.regenerate_html();
 
                 // Force a full reload instead of incremental append for extreme speeds
@@ -455,6 +1329,18 @@ impl GuiDelegate {
                     .update_content_with_scroll(current_doc, ScrollBehavior::Bottom);
             }
         }
+
+        if let Some(fraction) = latest_progress {
+            self.process_content_update(ContentUpdate::Progress(fraction));
+        }
+
+        for message in errors {
+            self.process_content_update(ContentUpdate::Error(message));
+        }
+
+        if stream_ended {
+            self.process_content_update(ContentUpdate::StreamEnd);
+        }
     }
 
     /// Process a single content update
@@ -463,6 +1349,12 @@ impl GuiDelegate {
             ContentUpdate::FullReplace(mut content) => {
                 // Apply current style preferences to the content
                 content.style_preferences = self.style_preferences.borrow().clone();
+                if content.title_override.is_none() {
+                    content.title_override = self.title_override.clone();
+                }
+                if let Some(mode) = self.initial_mode.borrow_mut().take() {
+                    content.mode = mode;
+                }
 
                 // Create window if needed
                 if self.window.borrow().is_none() {
@@ -471,21 +1363,43 @@ impl GuiDelegate {
                     let window =
                         create_main_window_with_content(&self.view, &content, self.is_pipe_mode);
                     *self.window.borrow_mut() = Some(window);
+                } else if self.current_document.borrow().is_none() {
+                    // The window was showing the loading placeholder
+                    if let Some(window) = self.window.borrow().as_ref() {
+                        fit_window_to_content(window, &content, self.is_pipe_mode);
+                    }
+                } else if content.file_path.is_some() {
+                    // Opening the file moved it to the top of File → Open Recent
+                    self.refresh_menu();
                 }
 
                 // Update content
                 let scroll_behavior = if self.is_pipe_mode {
                     ScrollBehavior::Bottom
+                } else if let Some(fragment) = content.fragment.take() {
+                    if content.html.contains(&format!(" id=\"{fragment}\"")) {
+                        ScrollBehavior::Anchor(fragment)
+                    } else {
+                        warn!("No heading with the anchor #{fragment}, showing the top");
+                        ScrollBehavior::Top
+                    }
                 } else {
                     ScrollBehavior::Top
                 };
 
                 self.view
                     .update_content_with_scroll(&content, scroll_behavior);
-                *self.current_document.borrow_mut() = Some(content);
+                if let Some(window) = self.window.borrow().as_ref() {
+                    window.set_title(&content.preferred_title());
+                }
+                *self.current_document.borrow_mut() = Some(*content);
                 debug!("Content updated (full replace)");
             }
-            ContentUpdate::Append { markdown, html } => {
+            ContentUpdate::Append {
+                markdown,
+                html,
+                standalone,
+            } => {
                 // Only append if we have a window
                 if self.window.borrow().is_some() {
                     let style_preferences = self.style_preferences.borrow().clone();
@@ -496,7 +1410,9 @@ impl GuiDelegate {
                             "Before append - current doc markdown length: {}",
                             current_doc.markdown.len()
                         );
-                        current_doc.markdown.push_str(&markdown);
+                        // A standalone chunk grows the document's HTML by the HTML the page
+                        // appends; any other chunk has the whole document rendered again
+                        let rendered_again = current_doc.append_chunk(&markdown, &html, standalone);
                         debug!(
                             "After append - current doc markdown length: {}",
                             current_doc.markdown.len()
@@ -506,27 +1422,66 @@ impl GuiDelegate {
                             current_doc.markdown.chars().take(200).collect::<String>()
                         );
 
-                        // Regenerate HTML to ensure consistency with accumulated content
-                        current_doc.regenerate_html();
-                        debug!(
-                            "After regenerate - current doc HTML length: {}",
-                            current_doc.html.len()
-                        );
-
-                        // Try to append the individual chunk first
-                        self.view
-                            .append_content(&markdown, &html, &style_preferences);
+                        if rendered_again {
+                            if streaming::trim_to_max_buffer(
+                                &mut current_doc.markdown,
+                                &current_doc.format,
+                            ) {
+                                current_doc.regenerate_html();
+                            }
+                            self.view.replace_streamed_content(current_doc);
+                        } else {
+                            let trimmed =
+                                self.view
+                                    .append_content(&markdown, &html, &style_preferences);
+                            if trimmed {
+                                // The view dropped the oldest chunks to stay within --max-buffer
+                                current_doc.markdown = self.view.accumulated_markdown();
+                                current_doc.html = self.view.accumulated_content();
+                            }
+                        }
+                        self.view.update_stats(current_doc);
                         debug!("Content appended (chunk: {} bytes)", markdown.len());
                     }
                 }
             }
+            ContentUpdate::Progress(fraction) => {
+                // Progress is only meaningful once there is content to show it over
+                if self.window.borrow().is_none() {
+                    return;
+                }
+                self.view.set_progress(fraction);
+                debug!("Progress updated: {:.1}%", fraction * 100.0);
+            }
+            ContentUpdate::Clear => self.clear_content(),
+            ContentUpdate::Error(message) => {
+                *self.input_failed.borrow_mut() = true;
+                // Keep whatever was read so far on screen, with the error above it
+                if self.current_document.borrow().is_some() {
+                    self.view.show_banner(&message);
+                } else {
+                    self.view.show_placeholder(&message);
+                }
+            }
+            ContentUpdate::StreamEnd => {
+                if self.is_pipe_mode {
+                    self.view.set_stream_state(StreamState::Closed);
+                }
+                if self.window.borrow().is_some()
+                    && self.current_document.borrow().is_none()
+                    && !*self.input_failed.borrow()
+                {
+                    self.view.show_placeholder("No content to display");
+                }
+                debug!("Stream ended");
+            }
         }
 
         // Create empty window if needed
         if self.window.borrow().is_none() {
             info!("Creating empty window...");
             self.setup_menu();
-            let window = create_main_window(&self.view);
+            let window = create_main_window(&self.view, self.title_override.as_deref());
             *self.window.borrow_mut() = Some(window);
         }
     }
@@ -0,0 +1,176 @@
+//! Opening markdown files dropped onto the window.
+//!
+//! The web view fills the window, so it is the view that receives drags. Its class gets
+//! dragging methods that take file drops; anything else, such as text dragged within the
+//! page, is handed on to `WKWebView`.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::Once;
+
+use cocoa::base::{BOOL, NO, YES, id, nil};
+use cocoa::foundation::{NSArray, NSString, NSUInteger};
+use objc::runtime::{Class, Imp, Object, Sel, class_addMethod};
+use objc::{Encode, class, msg_send, sel, sel_impl};
+
+use crate::gui::delegate::is_markdown_path;
+use crate::menu::{MenuMessage, dispatch_menu_message};
+
+/// `NSDragOperationNone`, shown as the "not allowed" cursor
+const DRAG_OPERATION_NONE: NSUInteger = 0;
+
+/// `NSDragOperationCopy`
+const DRAG_OPERATION_COPY: NSUInteger = 1;
+
+/// A drag is either a file drop, which this module handles, or something for the web view.
+enum Drag {
+    File(Option<String>),
+    Other,
+}
+
+/// Returns what is being dragged: the path of the first dropped file if it is markdown,
+/// `File(None)` for other files.
+fn dragged(info: id) -> Drag {
+    unsafe {
+        let pasteboard: id = msg_send![info, draggingPasteboard];
+        let classes = NSArray::arrayWithObjects(nil, &[class!(NSURL) as *const Class as id]);
+        let urls: id = msg_send![pasteboard, readObjectsForClasses: classes options: nil];
+        if urls == nil {
+            return Drag::Other;
+        }
+        let count: NSUInteger = msg_send![urls, count];
+        if count == 0 {
+            return Drag::Other;
+        }
+        let url: id = msg_send![urls, objectAtIndex: 0 as NSUInteger];
+        let is_file: BOOL = msg_send![url, isFileURL];
+        if is_file == NO {
+            return Drag::Other;
+        }
+
+        let path: id = msg_send![url, path];
+        let bytes: *const c_char = msg_send![path, UTF8String];
+        if bytes.is_null() {
+            return Drag::File(None);
+        }
+        let path = CStr::from_ptr(bytes).to_string_lossy().into_owned();
+        Drag::File(Some(path).filter(|path| is_markdown_path(path)))
+    }
+}
+
+extern "C" fn dragging_entered(this: &Object, _: Sel, info: id) -> NSUInteger {
+    unsafe {
+        match dragged(info) {
+            Drag::File(Some(_)) => DRAG_OPERATION_COPY,
+            Drag::File(None) => DRAG_OPERATION_NONE,
+            Drag::Other => msg_send![super(this, class!(WKWebView)), draggingEntered: info],
+        }
+    }
+}
+
+extern "C" fn dragging_updated(this: &Object, _: Sel, info: id) -> NSUInteger {
+    unsafe {
+        match dragged(info) {
+            Drag::File(Some(_)) => DRAG_OPERATION_COPY,
+            Drag::File(None) => DRAG_OPERATION_NONE,
+            Drag::Other => msg_send![super(this, class!(WKWebView)), draggingUpdated: info],
+        }
+    }
+}
+
+extern "C" fn prepare_for_drag_operation(this: &Object, _: Sel, info: id) -> BOOL {
+    unsafe {
+        match dragged(info) {
+            Drag::File(Some(_)) => YES,
+            Drag::File(None) => NO,
+            Drag::Other => {
+                msg_send![super(this, class!(WKWebView)), prepareForDragOperation: info]
+            }
+        }
+    }
+}
+
+extern "C" fn perform_drag_operation(this: &Object, _: Sel, info: id) -> BOOL {
+    unsafe {
+        match dragged(info) {
+            Drag::File(Some(path)) => {
+                dispatch_menu_message(MenuMessage::OpenFile(path));
+                YES
+            }
+            Drag::File(None) => NO,
+            Drag::Other => msg_send![super(this, class!(WKWebView)), performDragOperation: info],
+        }
+    }
+}
+
+extern "C" fn conclude_drag_operation(this: &Object, _: Sel, info: id) {
+    unsafe {
+        if let Drag::Other = dragged(info) {
+            let _: () = msg_send![super(this, class!(WKWebView)), concludeDragOperation: info];
+        }
+    }
+}
+
+/// Adds a method taking an `NSDraggingInfo` and returning `R` to `class`.
+unsafe fn add_dragging_method<R: Encode>(class: *mut Class, selector: Sel, imp: Imp) {
+    let types = CString::new(format!(
+        "{}{}{}{}",
+        R::encode().as_str(),
+        <*mut Object>::encode().as_str(),
+        Sel::encode().as_str(),
+        <*mut Object>::encode().as_str()
+    ))
+    .expect("type encodings contain no NUL");
+    unsafe {
+        class_addMethod(class, selector, imp, types.as_ptr());
+    }
+}
+
+/// Lets markdown files be dropped onto `webview` to open them in its window. Files of other
+/// types are refused with the "not allowed" cursor.
+pub fn accept_file_drops(webview: id) {
+    static REGISTER: Once = Once::new();
+    unsafe {
+        // Every web view shares the class, so its methods are added only once
+        let class: *mut Class = msg_send![webview, class];
+        REGISTER.call_once(|| {
+            type DragOperation = extern "C" fn(&Object, Sel, id) -> NSUInteger;
+            type DragResult = extern "C" fn(&Object, Sel, id) -> BOOL;
+            type DragEnd = extern "C" fn(&Object, Sel, id);
+
+            add_dragging_method::<NSUInteger>(
+                class,
+                sel!(draggingEntered:),
+                std::mem::transmute::<DragOperation, Imp>(dragging_entered),
+            );
+            add_dragging_method::<NSUInteger>(
+                class,
+                sel!(draggingUpdated:),
+                std::mem::transmute::<DragOperation, Imp>(dragging_updated),
+            );
+            add_dragging_method::<BOOL>(
+                class,
+                sel!(prepareForDragOperation:),
+                std::mem::transmute::<DragResult, Imp>(prepare_for_drag_operation),
+            );
+            add_dragging_method::<BOOL>(
+                class,
+                sel!(performDragOperation:),
+                std::mem::transmute::<DragResult, Imp>(perform_drag_operation),
+            );
+            add_dragging_method::<()>(
+                class,
+                sel!(concludeDragOperation:),
+                std::mem::transmute::<DragEnd, Imp>(conclude_drag_operation),
+            );
+        });
+
+        // WKWebView already accepts file URLs; make sure they reach the methods above
+        let file_url_type = NSString::alloc(nil).init_str("public.file-url");
+        let types = NSArray::arrayWithObjects(nil, &[file_url_type]);
+        let _: () = msg_send![webview, registerForDraggedTypes: types];
+    }
+}
@@ -0,0 +1,30 @@
+//! The alert asking before a clicked web link is opened, with `--confirm-links`.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use objc::{class, msg_send, sel, sel_impl};
+
+/// `NSAlertFirstButtonReturn`
+const FIRST_BUTTON_RETURN: isize = 1000;
+
+/// Runs a modal alert showing `url`, returning whether the user chose to open it. Must be
+/// called on the main thread.
+pub fn confirm_open_link(url: &str) -> bool {
+    unsafe {
+        let alert: id = msg_send![class!(NSAlert), new];
+        let message = NSString::alloc(nil).init_str("Open this link?");
+        let _: () = msg_send![alert, setMessageText: message];
+        let informative = NSString::alloc(nil).init_str(url);
+        let _: () = msg_send![alert, setInformativeText: informative];
+        // The first button is the default; Cancel also answers Escape
+        let _: id = msg_send![alert, addButtonWithTitle: NSString::alloc(nil).init_str("Open")];
+        let _: id = msg_send![alert, addButtonWithTitle: NSString::alloc(nil).init_str("Cancel")];
+
+        let response: isize = msg_send![alert, runModal];
+        let _: () = msg_send![alert, release];
+        response == FIRST_BUTTON_RETURN
+    }
+}
@@ -1,21 +1,45 @@
 //! GUI module: sets up and runs the application window.
 
-use crate::content::ContentUpdate;
+use crate::content::{ContentUpdate, ViewMode};
 use cacao::appkit::App; // AppDelegate is not used directly here.
 use std::sync::mpsc;
 
+pub mod appearance;
+mod crash_alert;
 mod delegate;
-pub mod types;
+mod file_drop;
+mod link_alert;
+mod open_panel;
+mod save_panel;
+mod settings_window;
 mod view;
 mod window;
 
-pub use delegate::GuiDelegate;
+pub use crash_alert::install_panic_hook;
+pub use delegate::{GuiDelegate, wake_main_thread};
+pub use homo::gui::{types, user_defaults};
 
 /// Runs the GUI application, optionally with a receiver for streamed ContentUpdate.
-pub fn run_app(receiver: Option<mpsc::Receiver<ContentUpdate>>, is_pipe_mode: bool) {
+///
+/// `title_override` replaces the window title that would otherwise come from the content,
+/// `low_power` turns off continuous polling and page animations, and `initial_mode` is the view
+/// the first document opens in.
+pub fn run_app(
+    receiver: Option<mpsc::Receiver<ContentUpdate>>,
+    is_pipe_mode: bool,
+    title_override: Option<String>,
+    low_power: bool,
+    initial_mode: ViewMode,
+) {
     App::new(
         "com.rust-gui.homo",
-        GuiDelegate::new(receiver, is_pipe_mode),
+        GuiDelegate::new(
+            receiver,
+            is_pipe_mode,
+            title_override,
+            low_power,
+            initial_mode,
+        ),
     )
     .run();
 }
@@ -0,0 +1,53 @@
+//! The "Open…" panel for choosing a markdown file to show.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+use cocoa::base::{NO, YES, id, nil};
+use cocoa::foundation::{NSArray, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+
+/// `NSModalResponseOK`
+const MODAL_RESPONSE_OK: isize = 1;
+
+/// Runs a modal open panel limited to files with the given extensions, returning the chosen
+/// path or `None` if the user cancelled.
+pub fn choose_open_path(extensions: &[&str]) -> Option<PathBuf> {
+    unsafe {
+        let panel: id = msg_send![class!(NSOpenPanel), openPanel];
+        let _: () = msg_send![panel, setCanChooseFiles: YES];
+        let _: () = msg_send![panel, setCanChooseDirectories: NO];
+        let _: () = msg_send![panel, setAllowsMultipleSelection: NO];
+
+        let extensions: Vec<id> = extensions
+            .iter()
+            .map(|extension| -> id {
+                msg_send![NSString::alloc(nil).init_str(extension), autorelease]
+            })
+            .collect();
+        let allowed = NSArray::arrayWithObjects(nil, &extensions);
+        let _: () = msg_send![panel, setAllowedFileTypes: allowed];
+
+        let response: isize = msg_send![panel, runModal];
+        if response != MODAL_RESPONSE_OK {
+            return None;
+        }
+
+        let url: id = msg_send![panel, URL];
+        if url == nil {
+            return None;
+        }
+        let path: id = msg_send![url, path];
+        let bytes: *const c_char = msg_send![path, UTF8String];
+        if bytes.is_null() {
+            return None;
+        }
+        Some(PathBuf::from(
+            CStr::from_ptr(bytes).to_string_lossy().into_owned(),
+        ))
+    }
+}
@@ -0,0 +1,67 @@
+//! The "Save As…" panel for choosing where to write the current document.
+
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)] // cocoa crate
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+use cocoa::base::{YES, id, nil};
+use cocoa::foundation::{NSArray, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+
+/// Extensions offered in the save panel. The chosen one decides the format written.
+const SAVE_EXTENSIONS: [&str; 4] = ["md", "markdown", "html", "htm"];
+
+/// `NSModalResponseOK`
+const MODAL_RESPONSE_OK: isize = 1;
+
+/// Returns whether a chosen save path should get standalone HTML rather than markdown.
+pub fn is_html_path(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| matches!(extension.to_ascii_lowercase().as_str(), "html" | "htm"))
+}
+
+/// Runs a modal save panel suggesting `default_name`, returning the chosen path or `None` if
+/// the user cancelled.
+pub fn choose_save_path(default_name: &str) -> Option<PathBuf> {
+    unsafe {
+        let panel: id = msg_send![class!(NSSavePanel), savePanel];
+        let name = NSString::alloc(nil).init_str(default_name);
+        let _: () = msg_send![panel, setNameFieldStringValue: name];
+        let message = NSString::alloc(nil)
+            .init_str("Name the file .md for markdown or .html for a web page.");
+        let _: () = msg_send![panel, setMessage: message];
+
+        let extensions: Vec<id> = SAVE_EXTENSIONS
+            .iter()
+            .map(|extension| -> id {
+                msg_send![NSString::alloc(nil).init_str(extension), autorelease]
+            })
+            .collect();
+        let allowed = NSArray::arrayWithObjects(nil, &extensions);
+        let _: () = msg_send![panel, setAllowedFileTypes: allowed];
+        let _: () = msg_send![panel, setAllowsOtherFileTypes: YES];
+        let _: () = msg_send![panel, setCanCreateDirectories: YES];
+
+        let response: isize = msg_send![panel, runModal];
+        if response != MODAL_RESPONSE_OK {
+            return None;
+        }
+
+        let url: id = msg_send![panel, URL];
+        if url == nil {
+            return None;
+        }
+        let path: id = msg_send![url, path];
+        let bytes: *const c_char = msg_send![path, UTF8String];
+        if bytes.is_null() {
+            return None;
+        }
+        Some(PathBuf::from(
+            CStr::from_ptr(bytes).to_string_lossy().into_owned(),
+        ))
+    }
+}
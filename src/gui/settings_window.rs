@@ -0,0 +1,261 @@
+//! The Settings window (⌘,): popups for the style preferences otherwise spread across the menus.
+//!
+//! Each change is applied to the open document right away; the preferences are saved when the
+//! window closes.
+
+use cacao::appkit::window::{Window, WindowConfig, WindowDelegate, WindowStyle};
+use cacao::foundation::NSInteger;
+use cacao::layout::{Layout, LayoutConstraint};
+use cacao::select::Select;
+use cacao::text::{Label, TextAlign};
+use cacao::view::View;
+
+use crate::gui::types::{
+    ContentWidth, FontFamily, LINE_HEIGHT_STEPS, MAX_FONT_SIZE, MIN_FONT_SIZE, StylePreferences,
+    TAB_WIDTH_STEPS, ThemeMode,
+};
+use crate::menu::{MenuMessage, dispatch_menu_message};
+
+/// Themes in the order the Theme popup lists them
+const THEMES: [(ThemeMode, &str); 3] = [
+    (ThemeMode::Light, "Light"),
+    (ThemeMode::Dark, "Dark"),
+    (ThemeMode::System, "Match System"),
+];
+
+/// Built-in font families in the order the Font popup lists them
+const FONT_FAMILIES: [(FontFamily, &str); 4] = [
+    (FontFamily::System, "System"),
+    (FontFamily::Menlo, "Menlo"),
+    (FontFamily::Monaco, "Monaco"),
+    (FontFamily::Helvetica, "Helvetica"),
+];
+
+/// Column widths in the order the Content Width popup lists them
+const CONTENT_WIDTHS: [(ContentWidth, &str); 4] = [
+    (ContentWidth::Full, "Full Window"),
+    (ContentWidth::Narrow, "Narrow (680px)"),
+    (ContentWidth::Medium, "Medium (860px)"),
+    (ContentWidth::Wide, "Wide (1080px)"),
+];
+
+/// Width of the label column, in points
+const LABEL_WIDTH: f64 = 110.0;
+
+/// Space around the form and between its rows, in points
+const MARGIN: f64 = 20.0;
+const ROW_SPACING: f64 = 10.0;
+
+/// Window delegate holding the Settings form.
+pub struct SettingsWindow {
+    content: View,
+    labels: Vec<Label>,
+    theme: Select,
+    font_family: Select,
+    font_size: Select,
+    line_height: Select,
+    content_width: Select,
+    tab_width: Select,
+    /// Installed font chosen with `--font`, listed after the built-in families
+    custom_font: Option<String>,
+}
+
+impl SettingsWindow {
+    fn new(preferences: &StylePreferences) -> Self {
+        let custom_font = match &preferences.font_family {
+            FontFamily::Custom(name) => Some(name.clone()),
+            _ => None,
+        };
+        let font_family = popup(FONT_FAMILIES.iter().map(|(_, name)| name.to_string()));
+        if let Some(name) = &custom_font {
+            font_family.add_item(name);
+        }
+
+        let settings = SettingsWindow {
+            content: View::new(),
+            labels: Vec::new(),
+            theme: popup(THEMES.iter().map(|(_, name)| name.to_string())),
+            font_family,
+            font_size: popup(font_sizes().map(|size| format!("{size} px"))),
+            line_height: popup(
+                LINE_HEIGHT_STEPS
+                    .iter()
+                    .map(|height| format!("{height:.1}")),
+            ),
+            content_width: popup(CONTENT_WIDTHS.iter().map(|(_, name)| name.to_string())),
+            tab_width: popup(
+                TAB_WIDTH_STEPS
+                    .iter()
+                    .map(|width| format!("{width} spaces")),
+            ),
+            custom_font,
+        };
+        settings.show_preferences(preferences);
+        settings
+    }
+
+    /// Selects the popup items matching `preferences`, e.g. after a change from the menus.
+    pub fn show_preferences(&self, preferences: &StylePreferences) {
+        let theme = THEMES
+            .iter()
+            .position(|(theme, _)| *theme == preferences.theme);
+        let font_family = match &preferences.font_family {
+            FontFamily::Custom(_) => Some(FONT_FAMILIES.len()),
+            family => FONT_FAMILIES.iter().position(|(font, _)| font == family),
+        };
+        let font_size = font_sizes().position(|size| size as f32 == preferences.font_size.round());
+        let content_width = CONTENT_WIDTHS
+            .iter()
+            .position(|(width, _)| *width == preferences.content_width);
+
+        select(&self.theme, theme);
+        select(&self.font_family, font_family);
+        select(&self.font_size, font_size);
+        select(
+            &self.line_height,
+            nearest(&LINE_HEIGHT_STEPS, |height| {
+                (height - preferences.line_height).abs()
+            }),
+        );
+        select(&self.content_width, content_width);
+        select(
+            &self.tab_width,
+            nearest(&TAB_WIDTH_STEPS, |width| {
+                width.abs_diff(preferences.tab_width) as f32
+            }),
+        );
+    }
+
+    /// Writes the chosen settings into `preferences`.
+    pub fn apply_to(&self, preferences: &mut StylePreferences) {
+        if let Some((theme, _)) = THEMES.get(self.theme.get_selected_index()) {
+            preferences.theme = theme.clone();
+        }
+        let font_index = self.font_family.get_selected_index();
+        match (FONT_FAMILIES.get(font_index), &self.custom_font) {
+            (Some((family, _)), _) => preferences.font_family = family.clone(),
+            (None, Some(name)) => preferences.font_family = FontFamily::Custom(name.clone()),
+            (None, None) => {}
+        }
+        if let Some(size) = font_sizes().nth(self.font_size.get_selected_index()) {
+            preferences.font_size = size as f32;
+        }
+        if let Some(height) = LINE_HEIGHT_STEPS.get(self.line_height.get_selected_index()) {
+            preferences.line_height = *height;
+        }
+        if let Some((width, _)) = CONTENT_WIDTHS.get(self.content_width.get_selected_index()) {
+            preferences.content_width = *width;
+        }
+        if let Some(width) = TAB_WIDTH_STEPS.get(self.tab_width.get_selected_index()) {
+            preferences.tab_width = *width;
+        }
+    }
+}
+
+impl WindowDelegate for SettingsWindow {
+    const NAME: &'static str = "HomoSettingsWindow";
+
+    fn did_load(&mut self, window: Window) {
+        window.set_title("Settings");
+
+        let rows = [
+            ("Theme:", &self.theme),
+            ("Font:", &self.font_family),
+            ("Font Size:", &self.font_size),
+            ("Line Height:", &self.line_height),
+            ("Content Width:", &self.content_width),
+            ("Tab Width:", &self.tab_width),
+        ];
+        let mut above: Option<&Select> = None;
+        for (title, control) in rows {
+            self.labels
+                .push(add_row(&self.content, title, control, above));
+            above = Some(control);
+        }
+        if let Some(last) = above {
+            LayoutConstraint::activate(&[last
+                .bottom
+                .constraint_equal_to(&self.content.bottom)
+                .offset(-MARGIN)]);
+        }
+
+        window.set_content_view(&self.content);
+    }
+
+    fn will_close(&self) {
+        dispatch_menu_message(MenuMessage::SettingsClosed);
+    }
+}
+
+/// Opens the Settings window showing `preferences`.
+pub fn create_settings_window(preferences: &StylePreferences) -> Window<SettingsWindow> {
+    let mut config = WindowConfig::default();
+    config.set_styles(&[WindowStyle::Titled, WindowStyle::Closable]);
+
+    let window = Window::with(config, SettingsWindow::new(preferences));
+    window.set_content_size(360., 280.);
+    window.show();
+    window
+}
+
+/// Adds a labelled popup to the form, below the popup `above` or at the top, returning the label.
+fn add_row(content: &View, title: &str, control: &Select, above: Option<&Select>) -> Label {
+    let label = Label::new();
+    label.set_text(title);
+    label.set_text_alignment(TextAlign::Right);
+    content.add_subview(&label);
+    content.add_subview(control);
+
+    let top = match above {
+        Some(above) => control
+            .top
+            .constraint_equal_to(&above.bottom)
+            .offset(ROW_SPACING),
+        None => control.top.constraint_equal_to(&content.top).offset(MARGIN),
+    };
+    LayoutConstraint::activate(&[
+        top,
+        label
+            .leading
+            .constraint_equal_to(&content.leading)
+            .offset(MARGIN),
+        label.width.constraint_equal_to_constant(LABEL_WIDTH),
+        label.center_y.constraint_equal_to(&control.center_y),
+        control
+            .leading
+            .constraint_equal_to(&label.trailing)
+            .offset(8.0),
+        control
+            .trailing
+            .constraint_equal_to(&content.trailing)
+            .offset(-MARGIN),
+    ]);
+    label
+}
+
+/// Returns a popup listing `items`, which reports changes as a settings change.
+fn popup(items: impl Iterator<Item = String>) -> Select {
+    let mut popup = Select::new();
+    for item in items {
+        popup.add_item(&item);
+    }
+    popup.set_action(|| dispatch_menu_message(MenuMessage::SettingsChanged));
+    popup
+}
+
+/// Selects the item at `index` of a popup, if there is one.
+fn select(popup: &Select, index: Option<usize>) {
+    if let Some(index) = index {
+        popup.set_selected_index(index as NSInteger);
+    }
+}
+
+/// Returns the index of the step closest to the current value, by `distance`.
+fn nearest<T: Copy>(steps: &[T], distance: impl Fn(T) -> f32) -> Option<usize> {
+    (0..steps.len()).min_by(|&a, &b| distance(steps[a]).total_cmp(&distance(steps[b])))
+}
+
+/// Font sizes the Font Size popup lists, in whole pixels.
+fn font_sizes() -> impl Iterator<Item = u32> {
+    MIN_FONT_SIZE as u32..=MAX_FONT_SIZE as u32
+}
@@ -1,11 +1,9 @@
-#![allow(unexpected_cfgs)]
-
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use objc::runtime::Object;
-use objc::{class, msg_send, sel, sel_impl};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "gui")]
+use crate::gui::user_defaults;
+use crate::markdown;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum FontFamily {
     #[default]
@@ -13,17 +11,34 @@ pub enum FontFamily {
     Menlo,     // SF Mono, Menlo
     Monaco,    // Monaco
     Helvetica, // Helvetica Neue
+    /// Any installed font, by family name (`--font "JetBrains Mono"`)
+    Custom(String),
 }
 
 impl FontFamily {
-    pub fn css_value(&self) -> &'static str {
+    pub fn css_value(&self) -> String {
         match self {
             FontFamily::System => {
                 "-apple-system, BlinkMacSystemFont, \"Segoe UI\", Roboto, Helvetica, Arial, sans-serif"
+                    .to_string()
+            }
+            FontFamily::Menlo => "\"SF Mono\", \"Menlo\", \"Monaco\", monospace".to_string(),
+            FontFamily::Monaco => "\"Monaco\", \"SF Mono\", \"Menlo\", monospace".to_string(),
+            FontFamily::Helvetica => "\"Helvetica Neue\", Helvetica, Arial, sans-serif".to_string(),
+            FontFamily::Custom(name) => {
+                // Quote the name as a CSS string; it ends up inside a <style> element
+                let quoted = name
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace(['<', '>'], "");
+                // Fall back to a stack of the same kind if the font isn't installed
+                let fallback = if name.to_lowercase().contains("mono") {
+                    "\"SF Mono\", \"Menlo\", monospace"
+                } else {
+                    "-apple-system, BlinkMacSystemFont, \"Helvetica Neue\", sans-serif"
+                };
+                format!("\"{quoted}\", {fallback}")
             }
-            FontFamily::Menlo => "\"SF Mono\", \"Menlo\", \"Monaco\", monospace",
-            FontFamily::Monaco => "\"Monaco\", \"SF Mono\", \"Menlo\", monospace",
-            FontFamily::Helvetica => "\"Helvetica Neue\", Helvetica, Arial, sans-serif",
         }
     }
 }
@@ -44,6 +59,94 @@ impl ThemeMode {
             ThemeMode::System => "light dark",
         }
     }
+
+    /// Returns the theme after this one in the Light, Dark, System cycle.
+    pub fn next(&self) -> ThemeMode {
+        match self {
+            ThemeMode::Light => ThemeMode::Dark,
+            ThemeMode::Dark => ThemeMode::System,
+            ThemeMode::System => ThemeMode::Light,
+        }
+    }
+}
+
+/// Maximum width of the document column. Wide screens read better with a narrower column.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ContentWidth {
+    #[default]
+    Full,
+    Narrow, // 680px
+    Medium, // 860px
+    Wide,   // 1080px
+}
+
+impl ContentWidth {
+    pub fn max_width_px(&self) -> Option<u32> {
+        match self {
+            ContentWidth::Full => None,
+            ContentWidth::Narrow => Some(680),
+            ContentWidth::Medium => Some(860),
+            ContentWidth::Wide => Some(1080),
+        }
+    }
+
+    /// Returns the next preset, wrapping around to `Full`.
+    pub fn next(&self) -> Self {
+        match self {
+            ContentWidth::Full => ContentWidth::Narrow,
+            ContentWidth::Narrow => ContentWidth::Medium,
+            ContentWidth::Medium => ContentWidth::Wide,
+            ContentWidth::Wide => ContentWidth::Full,
+        }
+    }
+}
+
+/// Body font size, in pixels, that Reset Font Size returns to.
+const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+/// Pixels added or removed by each Increase / Decrease Font Size.
+const FONT_SIZE_STEP: f32 = 1.0;
+
+/// Smallest and largest body font sizes, in pixels.
+pub const MIN_FONT_SIZE: f32 = 8.0;
+pub const MAX_FONT_SIZE: f32 = 72.0;
+
+/// Page zoom levels stepped through by Zoom In / Zoom Out, matching Safari's.
+const ZOOM_STEPS: [f32; 13] = [
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+
+fn default_zoom_level() -> f32 {
+    1.0
+}
+
+/// Mermaid diagram scales stepped through by Enlarge Diagrams / Shrink Diagrams.
+const MERMAID_SCALE_STEPS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 2.5, 3.0];
+
+fn default_mermaid_scale() -> f32 {
+    1.0
+}
+
+/// Body line heights stepped through by Cycle Line Height, from dense to airy.
+pub const LINE_HEIGHT_STEPS: [f32; 3] = [1.3, 1.6, 2.0];
+
+fn default_line_height() -> f32 {
+    1.6
+}
+
+/// Tab stops stepped through by Cycle Tab Width, in spaces.
+pub const TAB_WIDTH_STEPS: [usize; 3] = [2, 4, 8];
+
+fn default_tab_width() -> usize {
+    4
+}
+
+fn default_code_fold_lines() -> usize {
+    30
+}
+
+fn default_true() -> bool {
+    true
 }
 
 // Simplified style preferences without toolbar-specific state
@@ -52,107 +155,206 @@ pub struct StylePreferences {
     pub font_family: FontFamily,
     pub font_size: f32,
     pub theme: ThemeMode,
+    /// Whole-page zoom applied by the WebView, independent of `font_size`
+    #[serde(default = "default_zoom_level")]
+    pub zoom_level: f32,
+    /// Scale of Mermaid diagrams on top of the page zoom
+    #[serde(default = "default_mermaid_scale")]
+    pub mermaid_scale: f32,
+    #[serde(default)]
+    pub content_width: ContentWidth,
+    /// Line height of body text, as a multiple of the font size
+    #[serde(default = "default_line_height")]
+    pub line_height: f32,
+    /// Width of a tab stop in code blocks and the Source view, in spaces
+    #[serde(default = "default_tab_width")]
+    pub tab_width: usize,
+    /// Highlight code in the page with highlight.js instead of syntect, so code colors
+    /// follow live system theme changes
+    #[serde(default)]
+    pub client_side_highlighting: bool,
+    /// Show the word count and reading time overlay
+    #[serde(default)]
+    pub show_stats: bool,
+    /// Convert straight quotes and dashes to typographic punctuation
+    #[serde(default)]
+    pub smart_punctuation: bool,
+    /// Turn bare URLs into links
+    #[serde(default = "default_true")]
+    pub autolinks: bool,
+    /// Expand `:shortcode:` emoji
+    #[serde(default = "default_true")]
+    pub emoji_shortcodes: bool,
+    /// Follow appended content and offer the scroll-to-bottom button
+    #[serde(default = "default_true")]
+    pub auto_scroll: bool,
+    /// Fade appended prose in word by word, like a typewriter
+    #[serde(default)]
+    pub typewriter: bool,
+    /// Number the lines of the Source view
+    #[serde(default = "default_true")]
+    pub line_numbers: bool,
+    /// Mark spaces, tabs and trailing whitespace in the Source view
+    #[serde(default)]
+    pub show_whitespace: bool,
+    /// Code blocks longer than this many lines start folded; 0 never folds
+    #[serde(default = "default_code_fold_lines")]
+    pub code_fold_lines: usize,
+    /// Open links to remote markdown documents in a new homo window instead of the browser
+    #[serde(default)]
+    pub internal_links: bool,
+    /// Ask before a clicked web link is opened
+    #[serde(default)]
+    pub confirm_links: bool,
+    /// Built-in syntect theme for code, e.g. `Solarized (dark)`; `None` picks one for the theme
+    #[serde(default)]
+    pub syntax_theme: Option<String>,
+    /// Background of code blocks as `#rrggbb`, used in every theme; `None` follows the theme
+    #[serde(default)]
+    pub code_background: Option<String>,
 }
 
 impl Default for StylePreferences {
     fn default() -> Self {
         Self {
             font_family: FontFamily::default(),
-            font_size: 14.0,
+            font_size: DEFAULT_FONT_SIZE,
             theme: ThemeMode::default(),
+            zoom_level: default_zoom_level(),
+            mermaid_scale: default_mermaid_scale(),
+            content_width: ContentWidth::default(),
+            line_height: default_line_height(),
+            tab_width: default_tab_width(),
+            client_side_highlighting: false,
+            show_stats: false,
+            smart_punctuation: false,
+            autolinks: true,
+            emoji_shortcodes: true,
+            auto_scroll: true,
+            typewriter: false,
+            line_numbers: true,
+            show_whitespace: false,
+            code_fold_lines: default_code_fold_lines(),
+            internal_links: false,
+            confirm_links: false,
+            syntax_theme: None,
+            code_background: None,
         }
     }
 }
 
 impl StylePreferences {
+    #[cfg(feature = "gui")]
     const PREFERENCES_KEY: &'static str = "StylePreferences";
 
     /// Load preferences from macOS UserDefaults
+    #[cfg(feature = "gui")]
     pub fn load_from_user_defaults() -> Self {
-        unsafe {
-            let user_defaults: *mut Object =
-                msg_send![class!(NSUserDefaults), standardUserDefaults];
-            let key = CFString::new(Self::PREFERENCES_KEY);
-            let key_ptr = key.as_concrete_TypeRef();
-
-            let data: *mut Object = msg_send![user_defaults, dataForKey: key_ptr];
-
-            if !data.is_null() {
-                let length: usize = msg_send![data, length];
-                let bytes: *const u8 = msg_send![data, bytes];
-                let slice = std::slice::from_raw_parts(bytes, length);
-
-                if let Ok(prefs) = serde_json::from_slice::<StylePreferences>(slice) {
-                    return prefs;
-                }
-            }
-        }
-
         // Return default preferences if loading fails
-        Self::default()
+        user_defaults::load_json(Self::PREFERENCES_KEY).unwrap_or_default()
     }
 
     /// Save preferences to macOS UserDefaults
+    #[cfg(feature = "gui")]
     pub fn save_to_user_defaults(&self) {
-        if let Ok(json_data) = serde_json::to_vec(self) {
-            unsafe {
-                let user_defaults: *mut Object =
-                    msg_send![class!(NSUserDefaults), standardUserDefaults];
-                let key = CFString::new(Self::PREFERENCES_KEY);
-                let key_ptr = key.as_concrete_TypeRef();
-
-                let data: *mut Object = msg_send![class!(NSData), dataWithBytes: json_data.as_ptr() length: json_data.len()];
-                let _: () = msg_send![user_defaults, setObject: data forKey: key_ptr];
-                let _: () = msg_send![user_defaults, synchronize];
-            }
-        }
+        user_defaults::save_json(Self::PREFERENCES_KEY, self);
     }
 
+    /// Makes the body text one step larger, up to the largest size.
     pub fn increase_font_size(&mut self) {
-        let new_size = match self.font_size as i32 {
-            8..=9 => 10.0,
-            10..=11 => 12.0,
-            12..=13 => 14.0,
-            14..=15 => 16.0,
-            16..=17 => 18.0,
-            18..=21 => 22.0,
-            22..=27 => 28.0,
-            28..=35 => 36.0,
-            36..=47 => 48.0,
-            48..=71 => 72.0,
-            _ => self.font_size,
-        };
-        self.font_size = new_size;
+        self.step_font_size(FONT_SIZE_STEP);
     }
 
+    /// Makes the body text one step smaller, down to the smallest size.
     pub fn decrease_font_size(&mut self) {
-        let new_size = match self.font_size as i32 {
-            9..=10 => 8.0,
-            11..=12 => 10.0,
-            13..=14 => 12.0,
-            15..=16 => 14.0,
-            17..=18 => 16.0,
-            19..=22 => 18.0,
-            23..=28 => 22.0,
-            29..=36 => 28.0,
-            37..=48 => 36.0,
-            49..=72 => 48.0,
-            _ => self.font_size,
-        };
-        self.font_size = new_size;
+        self.step_font_size(-FONT_SIZE_STEP);
+    }
+
+    /// Changes the font size by `step` pixels from the nearest whole size, within the limits.
+    fn step_font_size(&mut self, step: f32) {
+        self.font_size = (self.font_size.round() + step).clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
     }
 
     pub fn reset_font_size(&mut self) {
-        self.font_size = 14.0; // Reset to default size
+        self.font_size = DEFAULT_FONT_SIZE;
+    }
+
+    pub fn zoom_in(&mut self) {
+        if let Some(&level) = ZOOM_STEPS
+            .iter()
+            .find(|&&level| level > self.zoom_level + 0.001)
+        {
+            self.zoom_level = level;
+        }
+    }
+
+    pub fn zoom_out(&mut self) {
+        if let Some(&level) = ZOOM_STEPS
+            .iter()
+            .rev()
+            .find(|&&level| level < self.zoom_level - 0.001)
+        {
+            self.zoom_level = level;
+        }
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.zoom_level = default_zoom_level();
+    }
+
+    pub fn enlarge_diagrams(&mut self) {
+        if let Some(&scale) = MERMAID_SCALE_STEPS
+            .iter()
+            .find(|&&scale| scale > self.mermaid_scale + 0.001)
+        {
+            self.mermaid_scale = scale;
+        }
+    }
+
+    pub fn shrink_diagrams(&mut self) {
+        if let Some(&scale) = MERMAID_SCALE_STEPS
+            .iter()
+            .rev()
+            .find(|&&scale| scale < self.mermaid_scale - 0.001)
+        {
+            self.mermaid_scale = scale;
+        }
+    }
+
+    pub fn reset_diagram_size(&mut self) {
+        self.mermaid_scale = default_mermaid_scale();
+    }
+
+    /// Switches to the next line height preset, wrapping around to the densest.
+    pub fn cycle_line_height(&mut self) {
+        self.line_height = LINE_HEIGHT_STEPS
+            .iter()
+            .copied()
+            .find(|&height| height > self.line_height + 0.001)
+            .unwrap_or(LINE_HEIGHT_STEPS[0]);
+    }
+
+    /// Switches to the next tab width preset, wrapping around to the narrowest.
+    pub fn cycle_tab_width(&mut self) {
+        self.tab_width = TAB_WIDTH_STEPS
+            .iter()
+            .copied()
+            .find(|&width| width > self.tab_width)
+            .unwrap_or(TAB_WIDTH_STEPS[0]);
     }
 
     pub fn generate_css(&self) -> String {
         let font_family = self.font_family.css_value();
         let font_size = self.font_size;
+        let line_height = self.line_height;
+        let tab_width = self.tab_width;
+        let mermaid_scale = self.mermaid_scale;
         let color_scheme = self.theme.css_color_scheme();
 
         // Start with theme-specific CSS variables first
-        let mut css = format!(":root {{\n    color-scheme: {color_scheme};\n");
+        let mut css = format!(
+            ":root {{\n    color-scheme: {color_scheme};\n    --mermaid-scale: {mermaid_scale};\n"
+        );
 
         // Add theme-specific variables based on current theme
         match self.theme {
@@ -167,6 +369,12 @@ impl StylePreferences {
     --table-header-bg: #f6f8fa;
     --table-row-hover-bg: #f5f8ff;
     --table-row-alt-hover-bg: #eef4ff;
+    --admonition-note: #0969da;
+    --admonition-tip: #1a7f37;
+    --admonition-important: #8250df;
+    --admonition-warning: #9a6700;
+    --admonition-caution: #d1242f;
+    --mark-bg-color: #fff8c5;
 "#,
                 );
             }
@@ -181,6 +389,12 @@ impl StylePreferences {
     --table-header-bg: #21262d;
     --table-row-hover-bg: #1c2128;
     --table-row-alt-hover-bg: #262c36;
+    --admonition-note: #4493f8;
+    --admonition-tip: #3fb950;
+    --admonition-important: #ab7df8;
+    --admonition-warning: #d29922;
+    --admonition-caution: #f85149;
+    --mark-bg-color: rgba(187, 128, 9, 0.4);
 "#,
                 );
             }
@@ -195,6 +409,12 @@ impl StylePreferences {
     --table-header-bg: #f6f8fa;
     --table-row-hover-bg: #f5f8ff;
     --table-row-alt-hover-bg: #eef4ff;
+    --admonition-note: #0969da;
+    --admonition-tip: #1a7f37;
+    --admonition-important: #8250df;
+    --admonition-warning: #9a6700;
+    --admonition-caution: #d1242f;
+    --mark-bg-color: #fff8c5;
 "#,
                 );
             }
@@ -208,7 +428,7 @@ impl StylePreferences {
     font-family: {font_family};
     font-size: {font_size}px;
     font-weight: normal;
-    line-height: 1.6;
+    line-height: {line_height};
     padding: 20px;
     margin: 0;
 }}
@@ -225,6 +445,7 @@ code {{
     margin: 0;
     font-size: 85%;
     border-radius: 6px;
+    tab-size: {tab_width};
 }}
 pre {{
     font-family: "SF Mono", "Menlo", "Monaco", monospace;
@@ -232,6 +453,231 @@ pre {{
     padding: 16px;
     border-radius: 6px;
     overflow: auto;
+    tab-size: {tab_width};
+}}
+#homo-stats {{
+    position: fixed;
+    bottom: 12px;
+    left: 12px;
+    padding: 4px 10px;
+    font-size: 12px;
+    color: var(--muted-text-color);
+    background: var(--pre-bg-color);
+    border: 1px solid var(--border-color);
+    border-radius: 12px;
+    z-index: 1000;
+}}
+.scroll-shadow-left {{
+    -webkit-mask-image: linear-gradient(to right, transparent, #000 32px);
+}}
+.scroll-shadow-right {{
+    -webkit-mask-image: linear-gradient(to left, transparent, #000 32px);
+}}
+.scroll-shadow-left.scroll-shadow-right {{
+    -webkit-mask-image: linear-gradient(to right, transparent, #000 32px, #000 calc(100% - 32px), transparent);
+}}
+details {{
+    margin: 0 0 16px;
+    padding: 0 16px;
+    border: 1px solid var(--border-color);
+    border-radius: 6px;
+}}
+details details {{
+    margin: 8px 0;
+}}
+details[open] {{
+    padding-bottom: 4px;
+}}
+details > summary {{
+    padding: 8px 0;
+    font-weight: 600;
+    cursor: pointer;
+    list-style: none;
+    user-select: none;
+}}
+details > summary::-webkit-details-marker {{
+    display: none;
+}}
+details > summary::before {{
+    content: "";
+    display: inline-block;
+    margin-right: 8px;
+    vertical-align: middle;
+    border-style: solid;
+    border-width: 5px 0 5px 7px;
+    border-color: transparent transparent transparent var(--muted-text-color);
+    transition: transform 0.15s ease;
+}}
+details[open] > summary {{
+    margin-bottom: 12px;
+    border-bottom: 1px solid var(--border-color);
+}}
+details[open] > summary::before {{
+    transform: rotate(90deg);
+}}
+details[open] > :not(summary) {{
+    animation: homo-details-open 0.15s ease-out;
+}}
+@keyframes homo-details-open {{
+    from {{ opacity: 0; transform: translateY(-4px); }}
+    to {{ opacity: 1; transform: none; }}
+}}
+.homo-type-in {{
+    animation: homo-type-in 0.2s ease-out both;
+}}
+@keyframes homo-type-in {{
+    from {{ opacity: 0; }}
+    to {{ opacity: 1; }}
+}}
+.lightbox-image {{
+    cursor: zoom-in;
+}}
+#homo-lightbox {{
+    position: fixed;
+    inset: 0;
+    display: flex;
+    align-items: center;
+    justify-content: center;
+    background: rgba(0, 0, 0, 0.85);
+    cursor: zoom-out;
+    z-index: 1003;
+}}
+#homo-lightbox img {{
+    max-width: 95vw;
+    max-height: 95vh;
+    box-shadow: 0 4px 24px rgba(0, 0, 0, 0.5);
+}}
+#homo-math-preview {{
+    position: fixed;
+    max-width: 80vw;
+    padding: 8px 12px;
+    overflow: hidden;
+    color: CanvasText;
+    background: Canvas;
+    border: 1px solid var(--border-color);
+    border-radius: 6px;
+    box-shadow: 0 4px 12px rgba(0, 0, 0, 0.15);
+    pointer-events: none;
+    z-index: 1002;
+}}
+#homo-streaming {{
+    position: fixed;
+    top: 12px;
+    right: 12px;
+    display: flex;
+    align-items: center;
+    gap: 6px;
+    padding: 4px 10px;
+    font-size: 12px;
+    color: var(--muted-text-color);
+    background: var(--pre-bg-color);
+    border: 1px solid var(--border-color);
+    border-radius: 12px;
+    z-index: 1000;
+}}
+.homo-streaming-dot {{
+    width: 8px;
+    height: 8px;
+    border-radius: 50%;
+    background: #2da44e;
+    animation: homo-pulse 1.2s ease-in-out infinite;
+}}
+#homo-streaming.done .homo-streaming-dot {{
+    background: var(--muted-text-color);
+    animation: none;
+}}
+@keyframes homo-pulse {{
+    50% {{ opacity: 0.3; }}
+}}
+.code-block {{
+    position: relative;
+}}
+.code-block-buttons {{
+    position: absolute;
+    top: 8px;
+    right: 8px;
+    opacity: 0;
+    transition: opacity 0.15s;
+}}
+.code-block:hover .code-block-buttons {{
+    opacity: 1;
+}}
+.code-block.folded .code-fold-overflow {{
+    display: none;
+}}
+.code-fold-btn {{
+    display: block;
+    width: 100%;
+    margin-top: -16px;
+    margin-bottom: 16px;
+    padding: 4px 8px;
+    font-size: 12px;
+    color: var(--muted-text-color);
+    background: var(--pre-bg-color);
+    border: 1px solid var(--border-color);
+    border-radius: 0 0 6px 6px;
+    cursor: pointer;
+}}
+.code-copy-btn {{
+    padding: 4px 8px;
+    font-size: 12px;
+    color: inherit;
+    background: var(--pre-bg-color);
+    border: 1px solid var(--border-color);
+    border-radius: 4px;
+    cursor: pointer;
+}}
+pre.ansi-output,
+pre.plain-output {{
+    margin: 0;
+    padding-top: 0;
+    padding-bottom: 0;
+    border-radius: 0;
+}}
+.trimmed-marker,
+.split-line-marker {{
+    text-align: center;
+    font-style: italic;
+    color: var(--muted-text-color);
+}}
+pre.source-lines {{
+    counter-reset: source-line;
+}}
+.source-line {{
+    display: block;
+    position: relative;
+    padding-left: 4em;
+}}
+.source-line::before {{
+    counter-increment: source-line;
+    content: counter(source-line);
+    position: absolute;
+    left: 0;
+    width: 3em;
+    text-align: right;
+    color: var(--muted-text-color);
+    user-select: none;
+}}
+.ws-space,
+.ws-tab {{
+    position: relative;
+}}
+.ws-space::before,
+.ws-tab::before {{
+    position: absolute;
+    left: 0;
+    color: var(--muted-text-color);
+    opacity: .6;
+    pointer-events: none;
+}}
+.ws-space::before {{
+    content: "·";
+}}
+.ws-tab::before {{
+    content: "→";
+}}
+.ws-trailing {{
+    background-color: rgba(248, 81, 73, 0.25);
 }}
 pre > code {{
     padding: 0;
@@ -240,11 +686,84 @@ pre > code {{
     background-color: transparent;
     border: none;
 }}
+mark {{
+    color: inherit;
+    background-color: var(--mark-bg-color);
+    padding: 0 .1em;
+    border-radius: 2px;
+}}
+.front-matter {{
+    color: var(--muted-text-color);
+    font-size: 90%;
+    padding-bottom: 8px;
+    margin-bottom: 16px;
+    border-bottom: 1px solid var(--border-color);
+}}
 blockquote {{
     border-left: .25em solid var(--border-color);
     padding: 0 1em;
     color: var(--muted-text-color);
 }}
+.admonition {{
+    --admonition-color: var(--admonition-note);
+    border-left: .25em solid var(--admonition-color);
+    padding: .5em 1em;
+    margin: 16px 0;
+}}
+.admonition > :last-child {{
+    margin-bottom: 0;
+}}
+.admonition-title {{
+    color: var(--admonition-color);
+    font-weight: 600;
+    margin-top: 0;
+}}
+.admonition-tip {{ --admonition-color: var(--admonition-tip); }}
+.admonition-important {{ --admonition-color: var(--admonition-important); }}
+.admonition-warning {{ --admonition-color: var(--admonition-warning); }}
+.admonition-caution {{ --admonition-color: var(--admonition-caution); }}
+dl {{
+    margin: 16px 0;
+}}
+dl dt {{
+    font-weight: 600;
+    margin-top: 16px;
+}}
+dl dt:first-child {{
+    margin-top: 0;
+}}
+dl dd {{
+    margin: 4px 0 0 2em;
+}}
+dl dd > p {{
+    margin: 0;
+}}
+.footnote-ref a {{
+    text-decoration: none;
+}}
+.footnote-ref a::before {{
+    content: "[";
+}}
+.footnote-ref a::after {{
+    content: "]";
+}}
+section.footnotes {{
+    margin-top: 32px;
+    font-size: 0.875em;
+    color: var(--muted-text-color);
+}}
+section.footnotes hr {{
+    width: 33%;
+    margin: 0 0 16px 0;
+}}
+section.footnotes li:target {{
+    background: var(--code-bg-color);
+    border-radius: 4px;
+}}
+.footnote-backref {{
+    text-decoration: none;
+    font-family: -apple-system, BlinkMacSystemFont, sans-serif;
+}}
 table {{
     border-collapse: collapse;
     border-spacing: 0;
@@ -277,7 +796,6 @@ table td {{
     padding: 8px 12px;
     border-right: 1px solid var(--border-color);
     display: table-cell;
-    text-align: left;
     vertical-align: top;
     line-height: 1.5;
 }}
@@ -287,6 +805,7 @@ table td:last-child {{
 }}
 table th {{
     font-weight: 600;
+    text-align: left;
     background-color: var(--table-header-bg);
     border-bottom: 1px solid var(--border-color);
 }}
@@ -297,6 +816,38 @@ table td {{
 table tbody tr:hover {{
     background-color: var(--table-row-hover-bg);
 }}
+/* Numbered figures and equations (--crossref) */
+.crossref-figure {{
+    margin: 16px 0;
+    text-align: center;
+}}
+.crossref-figure figcaption,
+.crossref-caption {{
+    font-size: 0.9em;
+    color: var(--muted-text-color);
+    text-align: center;
+}}
+.crossref-figure figcaption {{
+    margin-top: 8px;
+}}
+.crossref-number {{
+    font-weight: 600;
+}}
+.crossref-equation {{
+    display: flex;
+    align-items: center;
+}}
+.crossref-equation > .latex-math {{
+    flex: 1;
+}}
+.crossref-equation > .crossref-number {{
+    margin-left: 16px;
+    font-weight: normal;
+}}
+.crossref-missing {{
+    color: var(--muted-text-color);
+    text-decoration: underline dotted;
+}}
 /* Mermaid diagram styling */
 .mermaid-container {{
     position: relative;
@@ -352,6 +903,13 @@ table tbody tr:hover {{
 .mermaid svg {{
     max-width: 100%;
     height: auto;
+    /* zoom rather than a transform, so the scroll area grows with the diagram */
+    zoom: var(--mermaid-scale, 1);
+}}
+/* Sequence diagrams, Gantt charts and the like scroll sideways rather than shrink to fit;
+   Mermaid sets their max-width inline */
+.mermaid.mermaid-wide svg {{
+    max-width: none !important;
 }}
 /* Ensure mermaid diagrams are visible in both themes */
 .mermaid .node rect,
@@ -407,6 +965,12 @@ pre code span {
         --table-header-bg: #21262d;
         --table-row-hover-bg: #1c2128;
         --table-row-alt-hover-bg: #262c36;
+        --admonition-note: #4493f8;
+        --admonition-tip: #3fb950;
+        --admonition-important: #ab7df8;
+        --admonition-warning: #d29922;
+        --admonition-caution: #f85149;
+        --mark-bg-color: rgba(187, 128, 9, 0.4);
     }
     body {
         background-color: #0d1117;
@@ -426,6 +990,142 @@ pre code span {
             _ => {}
         }
 
+        if let Some(max_width) = self.content_width.max_width_px() {
+            // Center a fixed-width column; wide code blocks and tables scroll inside it
+            css.push_str(&format!(
+                r#"
+body {{
+    max-width: {max_width}px;
+    margin: 0 auto;
+}}
+table {{
+    display: block;
+    width: max-content;
+    max-width: 100%;
+    overflow-x: auto;
+}}
+"#
+            ));
+        }
+
+        // A chosen code background overrides the theme's, with plain code text kept readable
+        if let Some(background) = &self.code_background
+            && let Some(color) = markdown::parse_hex_color(background)
+        {
+            let text_color = if markdown::is_dark_color(color) {
+                "#f0f6fc"
+            } else {
+                "#1f2328"
+            };
+            css.push_str(&format!(
+                r#"pre, .mermaid-raw, .latex-raw {{
+    background-color: {background} !important;
+}}
+pre, pre code {{
+    color: {text_color} !important;
+}}
+"#
+            ));
+        }
+
+        css.push_str(PRINT_CSS);
         css
     }
 }
+
+/// Styles applied only when printing or saving as PDF: a white page, no interactive
+/// controls, and code blocks wrapped instead of scrolled.
+const PRINT_CSS: &str = r#"
+@media print {
+    :root {
+        color-scheme: light;
+        --border-color: #d0d7de;
+        --code-bg-color: rgba(175, 184, 193, 0.2);
+        --pre-bg-color: #f6f8fa;
+        --muted-text-color: #57606a;
+        --table-row-bg: #ffffff;
+        --table-row-alt-bg: #f6f8fa;
+        --table-header-bg: #f6f8fa;
+        --mark-bg-color: #fff8c5;
+    }
+    body {
+        background-color: #ffffff !important;
+        color: #1f2328 !important;
+    }
+    #scroll-to-bottom-btn,
+    #homo-banner,
+    #homo-stats,
+    #homo-streaming,
+    #homo-lightbox,
+    #homo-math-preview,
+    #stream-progress-bar,
+    .code-block-buttons,
+    .code-fold-btn,
+    .mermaid-buttons,
+    .plantuml-buttons,
+    .latex-buttons {
+        display: none !important;
+    }
+    .scroll-shadow-left,
+    .scroll-shadow-right {
+        -webkit-mask-image: none !important;
+    }
+    .code-block.folded .code-fold-overflow {
+        display: inline;
+    }
+    pre {
+        white-space: pre-wrap;
+        word-wrap: break-word;
+        overflow: visible;
+        page-break-inside: avoid;
+    }
+    img, svg, table {
+        page-break-inside: avoid;
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_size_steps_by_one_pixel() {
+        let mut preferences = StylePreferences::default();
+        preferences.increase_font_size();
+        assert_eq!(preferences.font_size, DEFAULT_FONT_SIZE + 1.0);
+        preferences.decrease_font_size();
+        preferences.decrease_font_size();
+        assert_eq!(preferences.font_size, DEFAULT_FONT_SIZE - 1.0);
+        preferences.reset_font_size();
+        assert_eq!(preferences.font_size, DEFAULT_FONT_SIZE);
+    }
+
+    #[test]
+    fn font_size_stays_between_8_and_72() {
+        let mut preferences = StylePreferences {
+            font_size: 9.0,
+            ..StylePreferences::default()
+        };
+        for _ in 0..5 {
+            preferences.decrease_font_size();
+        }
+        assert_eq!(preferences.font_size, MIN_FONT_SIZE);
+
+        preferences.font_size = 71.0;
+        for _ in 0..5 {
+            preferences.increase_font_size();
+        }
+        assert_eq!(preferences.font_size, MAX_FONT_SIZE);
+    }
+
+    #[test]
+    fn fractional_font_size_snaps_to_whole_pixels() {
+        let mut preferences = StylePreferences {
+            font_size: 13.6,
+            ..StylePreferences::default()
+        };
+        preferences.increase_font_size();
+        assert_eq!(preferences.font_size, 15.0);
+    }
+}
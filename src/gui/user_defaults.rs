@@ -0,0 +1,45 @@
+//! JSON values stored in macOS `NSUserDefaults`.
+
+#![allow(unexpected_cfgs)]
+
+use core_foundation::base::TCFType;
+use core_foundation::string::CFString;
+use objc::runtime::Object;
+use objc::{class, msg_send, sel, sel_impl};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Reads the value stored under `key`, or `None` if it is missing or can't be decoded.
+pub fn load_json<T: DeserializeOwned>(key: &str) -> Option<T> {
+    unsafe {
+        let user_defaults: *mut Object = msg_send![class!(NSUserDefaults), standardUserDefaults];
+        let key = CFString::new(key);
+        let key_ptr = key.as_concrete_TypeRef();
+
+        let data: *mut Object = msg_send![user_defaults, dataForKey: key_ptr];
+        if data.is_null() {
+            return None;
+        }
+
+        let length: usize = msg_send![data, length];
+        let bytes: *const u8 = msg_send![data, bytes];
+        let slice = std::slice::from_raw_parts(bytes, length);
+        serde_json::from_slice(slice).ok()
+    }
+}
+
+/// Stores `value` as JSON under `key`.
+pub fn save_json<T: Serialize>(key: &str, value: &T) {
+    if let Ok(json_data) = serde_json::to_vec(value) {
+        unsafe {
+            let user_defaults: *mut Object =
+                msg_send![class!(NSUserDefaults), standardUserDefaults];
+            let key = CFString::new(key);
+            let key_ptr = key.as_concrete_TypeRef();
+
+            let data: *mut Object = msg_send![class!(NSData), dataWithBytes: json_data.as_ptr() length: json_data.len()];
+            let _: () = msg_send![user_defaults, setObject: data forKey: key_ptr];
+            let _: () = msg_send![user_defaults, synchronize];
+        }
+    }
+}
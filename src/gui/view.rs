@@ -1,9 +1,16 @@
-use crate::content::{DocumentContent, ViewMode};
+use crate::content::{ContentFormat, DocumentContent, ViewMode};
+use crate::export::{generate_scripts_html, generate_stylesheet};
+use crate::gui::file_drop;
 use crate::markdown;
-use crate::plugins::{PluginContext, manager::PLUGIN_MANAGER};
+use crate::menu::{MenuMessage, dispatch_menu_message};
+use crate::streaming;
 use cacao::pasteboard::Pasteboard;
 use cacao::webview::{InjectAt, WebView, WebViewConfig, WebViewDelegate};
-use log::{debug, info};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 /// Safely truncate a string at the given byte limit, respecting Unicode character boundaries
 fn safe_truncate(s: &str, max_bytes: usize) -> &str {
@@ -20,23 +27,75 @@ fn safe_truncate(s: &str, max_bytes: usize) -> &str {
     &s[..safe_end]
 }
 
-#[derive(Clone, Copy)]
+/// Minimum time between checks that streamed chunks all reached the page.
+const PERIODIC_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Clone)]
 pub enum ScrollBehavior {
     Top,
     Bottom,
+    /// Scroll to a fraction (0.0 to 1.0) of the page's scroll height
+    Restore(f64),
+    /// Scroll to the element with this `id`, e.g. a heading anchor
+    Anchor(String),
+}
+
+/// Whether the content comes from a stream, and whether that stream is still open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamState {
+    /// Not streamed, e.g. a file loaded whole; no badge is shown
+    None,
+    /// Input is still arriving
+    Open,
+    /// The input has closed
+    Closed,
+}
+
+/// Larger text and roomier margins for presenting in full screen.
+const PRESENTATION_STYLE: &str = r#"<style id="homo-presentation">
+body {
+    zoom: 1.35;
+    padding: 48px 64px;
 }
+</style>"#;
+
+/// Markup added to every page with `--low-power`: no CSS animations or transitions, and
+/// scrolling jumps instead of gliding
+const LOW_POWER_STYLE: &str = r#"<style id="homo-low-power">
+*, *::before, *::after {
+    animation: none !important;
+    transition: none !important;
+}
+</style>
+<script>window.lowPower = true;</script>"#;
 
 const LINK_INTERCEPTOR_JS: &str = r#"
     window.addEventListener('DOMContentLoaded', (event) => {
         document.addEventListener('click', (e) => {
             let target = e.target.closest('a');
             if (target && target.href) {
-                if (target.href.startsWith('http')) {
+                // In-page anchors keep scrolling within the page
+                if ((target.getAttribute('href') || '').startsWith('#')) {
+                    return;
+                }
+                // Web, mail, phone and local file links are opened by the app or the system
+                if (/^(https?|mailto|tel|file):/i.test(target.href)) {
                     e.preventDefault();
                     window.webkit.messageHandlers.linkClicked.postMessage(target.href);
                 }
             }
         });
+
+        // Report task checkbox clicks so the source can be updated
+        document.addEventListener('change', (e) => {
+            const checkbox = e.target;
+            if (checkbox.matches && checkbox.matches('input.task-checkbox')) {
+                window.webkit.messageHandlers.taskToggled.postMessage(JSON.stringify({
+                    line: parseInt(checkbox.dataset.line, 10),
+                    checked: checkbox.checked
+                }));
+            }
+        });
         
         // Function to copy selected text
         window.copySelectedText = function() {
@@ -46,6 +105,173 @@ const LINK_INTERCEPTOR_JS: &str = r#"
             }
         };
         
+        // Copy the raw source of a highlighted code block
+        window.copyCodeBlock = function(button) {
+            const block = button.closest('.code-block');
+            const source = block.getAttribute('data-code-source');
+            window.webkit.messageHandlers.copyText.postMessage(source);
+            button.textContent = 'Copied';
+            setTimeout(() => { button.textContent = 'Copy'; }, 1500);
+        };
+        
+        // Expand or collapse the lines of a long code block past the fold
+        window.toggleCodeFold = function(button) {
+            const block = button.closest('.code-block');
+            const folded = block.classList.toggle('folded');
+            const hidden = button.dataset.hiddenLines;
+            const noun = hidden === '1' ? 'line' : 'lines';
+            button.textContent = folded ? `Show ${hidden} more ${noun}…` : 'Show fewer lines';
+        };
+        
+        // Show an image at full size over a dark backdrop; click or Escape dismisses it
+        window.openImageLightbox = function(image) {
+            window.closeImageLightbox();
+            const overlay = document.createElement('div');
+            overlay.id = 'homo-lightbox';
+            const full = document.createElement('img');
+            full.src = image.currentSrc || image.src;
+            full.alt = image.alt;
+            overlay.appendChild(full);
+            overlay.addEventListener('click', window.closeImageLightbox);
+            document.body.appendChild(overlay);
+        };
+
+        window.closeImageLightbox = function() {
+            const overlay = document.getElementById('homo-lightbox');
+            if (overlay) overlay.remove();
+        };
+
+        // Make images in `root` open the lightbox; images inside links follow the link instead
+        window.initImageLightbox = function(root) {
+            (root || document).querySelectorAll('img').forEach((image) => {
+                if (image.dataset.lightbox || image.closest('a')) return;
+                image.dataset.lightbox = 'true';
+                image.classList.add('lightbox-image');
+                image.addEventListener('click', () => window.openImageLightbox(image));
+            });
+        };
+
+        document.addEventListener('keydown', (e) => {
+            if (e.key === 'Escape') {
+                window.closeImageLightbox();
+            }
+        });
+
+        window.initImageLightbox(document);
+
+        // Source view: hovering $inline$, $$display$$ or a ```math block previews it with
+        // KaTeX. The math found in a source view is cached until its text changes.
+        const SOURCE_MATH = /```math\n([\s\S]*?)\n```|\$\$([\s\S]+?)\$\$|\$([^\s$](?:[^$\n]*?[^\s$\\])?)\$(?!\d)/g;
+        const sourceMath = new WeakMap();
+
+        window.findSourceMath = function(source) {
+            let found = sourceMath.get(source);
+            if (found) return found;
+            const starts = new Map();
+            let text = '';
+            const walker = document.createTreeWalker(source, NodeFilter.SHOW_TEXT);
+            while (walker.nextNode()) {
+                starts.set(walker.currentNode, text.length);
+                text += walker.currentNode.textContent;
+            }
+            const spans = [];
+            for (const match of text.matchAll(SOURCE_MATH)) {
+                const latex = match[1] ?? match[2] ?? match[3];
+                const display = match[3] === undefined;
+                spans.push({ start: match.index, end: match.index + match[0].length, latex, display });
+            }
+            found = { starts, spans };
+            sourceMath.set(source, found);
+            new MutationObserver((_, observer) => {
+                sourceMath.delete(source);
+                observer.disconnect();
+            }).observe(source, { childList: true, subtree: true, characterData: true });
+            return found;
+        };
+
+        window.hideMathPreview = function() {
+            const preview = document.getElementById('homo-math-preview');
+            if (preview) preview.remove();
+        };
+
+        window.showMathPreview = function(x, y) {
+            const caret = document.caretRangeFromPoint(x, y);
+            const parent = caret && caret.startContainer.parentElement;
+            const source = parent && parent.closest('pre.source-view');
+            if (!source || typeof katex === 'undefined') {
+                window.hideMathPreview();
+                return;
+            }
+            const { starts, spans } = window.findSourceMath(source);
+            const offset = starts.get(caret.startContainer) + caret.startOffset;
+            const span = spans.find((span) => offset >= span.start && offset < span.end);
+            if (!span) {
+                window.hideMathPreview();
+                return;
+            }
+
+            let preview = document.getElementById('homo-math-preview');
+            if (!preview) {
+                preview = document.createElement('div');
+                preview.id = 'homo-math-preview';
+                document.body.appendChild(preview);
+            }
+            if (preview.dataset.start !== String(span.start)) {
+                preview.dataset.start = span.start;
+                preview.innerHTML = katex.renderToString(span.latex, {
+                    ...window.katexOptions,
+                    displayMode: span.display,
+                    throwOnError: false
+                });
+            }
+            // Below the pointer, or above it when there's no room
+            const below = y + 16 + preview.offsetHeight <= window.innerHeight;
+            preview.style.top = (below ? y + 16 : Math.max(0, y - 16 - preview.offsetHeight)) + 'px';
+            preview.style.left = Math.max(0, Math.min(x, window.innerWidth - preview.offsetWidth)) + 'px';
+        };
+
+        let mathPreviewPending = false;
+        document.addEventListener('mousemove', (e) => {
+            if (mathPreviewPending) return;
+            mathPreviewPending = true;
+            requestAnimationFrame(() => {
+                mathPreviewPending = false;
+                window.showMathPreview(e.clientX, e.clientY);
+            });
+        }, { passive: true });
+        document.documentElement.addEventListener('mouseleave', window.hideMathPreview);
+        window.addEventListener('scroll', window.hideMathPreview, { passive: true });
+
+        // Fade the edges of horizontally scrolling code blocks and tables where more content is
+        // hidden, updating as they scroll
+        window.updateScrollShadow = function(element) {
+            const hiddenRight = element.scrollWidth - element.clientWidth - element.scrollLeft;
+            element.classList.toggle('scroll-shadow-left', element.scrollLeft > 1);
+            element.classList.toggle('scroll-shadow-right', hiddenRight > 1);
+        };
+
+        window.initScrollShadows = function(root) {
+            (root || document).querySelectorAll('pre, table').forEach((element) => {
+                if (element.dataset.scrollShadow) return;
+                element.dataset.scrollShadow = 'true';
+                element.addEventListener('scroll', () => window.updateScrollShadow(element), { passive: true });
+                window.updateScrollShadow(element);
+            });
+        };
+
+        window.addEventListener('resize', () => {
+            document.querySelectorAll('[data-scroll-shadow]').forEach(window.updateScrollShadow);
+        });
+
+        // Code and tables inside a closed <details> have no width until it opens
+        document.addEventListener('toggle', (event) => {
+            if (event.target.open) {
+                event.target.querySelectorAll('[data-scroll-shadow]').forEach(window.updateScrollShadow);
+            }
+        }, true);
+
+        window.initScrollShadows(document);
+
         // Function to select all text
         window.selectAllText = function() {
             const range = document.createRange();
@@ -79,14 +305,147 @@ const LINK_INTERCEPTOR_JS: &str = r#"
             }
         });
         
+        // Whether appended content is followed and the scroll-to-bottom button is offered
+        window.autoScroll = true;
+        window.setAutoScroll = function(enabled) {
+            window.autoScroll = enabled;
+            const scrollButton = document.getElementById('scroll-to-bottom-btn');
+            if (scrollButton && !enabled) {
+                scrollButton.style.opacity = '0';
+                scrollButton.style.display = 'none';
+            }
+        };
+
+        // Follow mode: appended content scrolls into view until the user scrolls up more than
+        // FOLLOW_RELEASE_PX from the bottom, and resumes once they are back at the bottom
+        const FOLLOW_RELEASE_PX = 80;
+        const FOLLOW_RESUME_PX = 8;
+        window.followMode = true;
+        let lastScrollY = window.pageYOffset;
+
+        window.distanceFromBottom = function() {
+            return document.body.scrollHeight - (window.innerHeight + window.pageYOffset);
+        };
+
+        window.addEventListener('scroll', () => {
+            const scrollY = window.pageYOffset;
+            const distance = window.distanceFromBottom();
+            if (scrollY < lastScrollY && distance > FOLLOW_RELEASE_PX) {
+                window.followMode = false;
+            } else if (distance <= FOLLOW_RESUME_PX) {
+                window.followMode = true;
+            }
+            lastScrollY = scrollY;
+        }, { passive: true });
+
+        // Typewriter effect: appended prose fades in word by word. Code, math and diagrams
+        // appear whole, and nothing waits on the animation, so appends and scrolling keep pace.
+        const TYPEWRITER_SKIP = 'pre, code, svg, table, .code-block, .mermaid-container, .plantuml-container, .latex-container, .latex-math';
+        const TYPEWRITER_CHUNK_MS = 600;
+        window.typewriter = false;
+        window.setTypewriter = function(enabled) {
+            window.typewriter = enabled && !window.lowPower;
+        };
+
+        window.typeIn = function(container) {
+            const walker = document.createTreeWalker(container, NodeFilter.SHOW_TEXT, {
+                acceptNode: (node) => node.textContent.trim() && !node.parentElement.closest(TYPEWRITER_SKIP)
+                    ? NodeFilter.FILTER_ACCEPT
+                    : NodeFilter.FILTER_REJECT
+            });
+            const textNodes = [];
+            while (walker.nextNode()) textNodes.push(walker.currentNode);
+
+            const words = [];
+            textNodes.forEach((node) => {
+                const fragment = document.createDocumentFragment();
+                node.textContent.split(/(\s+)/).forEach((part) => {
+                    if (!part) return;
+                    if (/^\s+$/.test(part)) {
+                        fragment.appendChild(document.createTextNode(part));
+                        return;
+                    }
+                    const span = document.createElement('span');
+                    span.className = 'homo-type-in';
+                    span.textContent = part;
+                    fragment.appendChild(span);
+                    words.push(span);
+                });
+                node.replaceWith(fragment);
+            });
+            if (words.length === 0) return;
+
+            // Long chunks type faster so each finishes in about the same time
+            const step = Math.min(30, TYPEWRITER_CHUNK_MS / words.length);
+            words.forEach((span, index) => {
+                span.style.animationDelay = `${Math.round(index * step)}ms`;
+            });
+            // Put the plain text back once it has appeared
+            setTimeout(() => {
+                words.forEach((span) => {
+                    if (span.isConnected) span.replaceWith(span.textContent);
+                });
+                container.normalize();
+            }, TYPEWRITER_CHUNK_MS + 400);
+        };
+
         // Simple scroll functions
         window.scrollToBottom = function() {
+            window.followMode = true;
             window.scrollTo(0, document.body.scrollHeight);
         };
         
         window.scrollToTop = function() {
             window.scrollTo(0, 0);
+            window.followMode = window.distanceFromBottom() <= FOLLOW_RELEASE_PX;
         };
+
+        window.scrollToRatio = function(ratio) {
+            window.scrollTo(0, ratio * document.body.scrollHeight);
+            window.followMode = window.distanceFromBottom() <= FOLLOW_RELEASE_PX;
+        };
+
+        window.scrollToAnchor = function(id) {
+            const target = document.getElementById(id);
+            if (target) {
+                target.scrollIntoView();
+            } else {
+                window.scrollTo(0, 0);
+            }
+            window.followMode = window.distanceFromBottom() <= FOLLOW_RELEASE_PX;
+        };
+
+        // Scroll the next (direction 1) or previous (-1) heading to the top of the window.
+        // Headings are looked up on every call, so ones appended while streaming count too.
+        window.jumpToHeading = function(direction) {
+            const headings = Array.from(document.querySelectorAll('h1, h2, h3, h4, h5, h6'))
+                .filter((heading) => heading.getClientRects().length > 0);
+            // A heading just jumped to sits at the top, give or take rounding
+            const tops = headings.map((heading) => heading.getBoundingClientRect().top);
+            let index = tops.findIndex((top) => top > 1);
+            if (direction < 0) {
+                index = (index === -1 ? tops.length : index) - 1;
+                while (index >= 0 && tops[index] >= -1) index--;
+            }
+            if (index === -1) return;
+            headings[index].scrollIntoView({ block: 'start' });
+            window.followMode = window.distanceFromBottom() <= FOLLOW_RELEASE_PX;
+        };
+
+        // Report the scroll position so it can be restored after a style change reloads the page
+        let scrollReportPending = false;
+        window.addEventListener('scroll', () => {
+            if (scrollReportPending) {
+                return;
+            }
+            scrollReportPending = true;
+            requestAnimationFrame(() => {
+                scrollReportPending = false;
+                const height = document.body.scrollHeight;
+                const ratio = height > 0 ? window.pageYOffset / height : 0;
+                window.webkit.messageHandlers.scrollPosition.postMessage(String(ratio));
+            });
+        });
         
         // Create scroll to bottom button
         window.createScrollToBottomButton = function() {
@@ -128,9 +487,10 @@ const LINK_INTERCEPTOR_JS: &str = r#"
             });
             
             button.addEventListener('click', function() {
+                window.followMode = true;
                 window.scrollTo({
                     top: document.body.scrollHeight,
-                    behavior: 'smooth'
+                    behavior: window.lowPower ? 'auto' : 'smooth'
                 });
             });
             
@@ -141,7 +501,7 @@ const LINK_INTERCEPTOR_JS: &str = r#"
         // Function to check if user is near bottom and show/hide button
         window.updateScrollButton = function() {
             const scrollButton = document.getElementById('scroll-to-bottom-btn');
-            if (!scrollButton) return;
+            if (!scrollButton || !window.autoScroll) return;
             
             const isNearBottom = (window.innerHeight + window.pageYOffset) >= (document.body.offsetHeight - 100);
             
@@ -167,7 +527,7 @@ const LINK_INTERCEPTOR_JS: &str = r#"
         
         window.handleScroll = function() {
             const scrollButton = document.getElementById('scroll-to-bottom-btn');
-            if (!scrollButton) return;
+            if (!scrollButton || !window.autoScroll) return;
             
             const isNearBottom = (window.innerHeight + window.pageYOffset) >= (document.body.offsetHeight - 100);
             
@@ -210,6 +570,96 @@ const LINK_INTERCEPTOR_JS: &str = r#"
             }, 150); // 150ms after scroll stops
         };
 
+        // Dismissible banner for errors that should not interrupt reading
+        window.showBanner = function(message) {
+            let banner = document.getElementById('homo-banner');
+            if (!banner) {
+                banner = document.createElement('div');
+                banner.id = 'homo-banner';
+                banner.style.cssText = `
+                    position: fixed;
+                    top: 0;
+                    left: 0;
+                    right: 0;
+                    padding: 8px 36px 8px 16px;
+                    background: #fff8c5;
+                    color: #3b2300;
+                    border-bottom: 1px solid #d4a72c;
+                    font-size: 13px;
+                    z-index: 1002;
+                `;
+                const close = document.createElement('span');
+                close.textContent = '✕';
+                close.style.cssText = 'position: absolute; right: 12px; top: 8px; cursor: pointer;';
+                close.addEventListener('click', function() {
+                    banner.remove();
+                });
+                banner.appendChild(document.createElement('span'));
+                banner.appendChild(close);
+                document.body.appendChild(banner);
+            }
+            banner.firstChild.textContent = message;
+        };
+
+        // Word count / reading time overlay
+        window.updateStats = function(text) {
+            let stats = document.getElementById('homo-stats');
+            if (!stats) {
+                stats = document.createElement('div');
+                stats.id = 'homo-stats';
+                document.body.appendChild(stats);
+            }
+            stats.textContent = text;
+        };
+
+        // Thin progress bar shown while streaming a file of known length
+
+        window.updateProgress = function(fraction) {
+            let bar = document.getElementById('stream-progress-bar');
+            if (fraction >= 1) {
+                if (bar) bar.remove();
+                return;
+            }
+            if (!bar) {
+                bar = document.createElement('div');
+                bar.id = 'stream-progress-bar';
+                bar.style.cssText = `
+                    position: fixed;
+                    top: 0;
+                    left: 0;
+                    height: 3px;
+                    width: 0;
+                    background: #0969da;
+                    z-index: 1001;
+                    transition: width 0.2s ease;
+                `;
+                document.body.appendChild(bar);
+            }
+            bar.style.width = (fraction * 100).toFixed(1) + '%';
+        };
+
+        // Chunks appended since the page loaded, compared against the app's count to spot losses
+        window.appendedChunks = 0;
+        // A rebuild waits until this long after the last scroll, click or key press
+        const USER_IDLE_MS = 1500;
+        window.lastUserActivity = 0;
+        ['wheel', 'mousedown', 'keydown', 'touchstart'].forEach((type) => {
+            window.addEventListener(type, () => { window.lastUserActivity = Date.now(); }, { passive: true });
+        });
+
+        // Requests a rebuild if appended chunks went missing, once the user is idle
+        window.verifyAppendedContent = function(expectedChunks) {
+            if (window.appendedChunks + window.appendQueue.length >= expectedChunks) {
+                return;
+            }
+            const hasSelection = window.getSelection().toString().length > 0;
+            if (hasSelection || Date.now() - window.lastUserActivity < USER_IDLE_MS) {
+                setTimeout(() => window.verifyAppendedContent(expectedChunks), USER_IDLE_MS);
+                return;
+            }
+            window.webkit.messageHandlers.syncRequested.postMessage('');
+        };
+
         // Initialize append queue system for sequential processing with retry mechanism
         window.appendQueue = [];
         window.isProcessingQueue = false;
@@ -257,8 +707,12 @@ const LINK_INTERCEPTOR_JS: &str = r#"
                     window.appendQueue.unshift({ htmlContent, retryCount: retryCount + 1 });
                     setTimeout(window.processNextAppend, 50 * (retryCount + 1));
                 } else {
-                    console.error('Max retries exceeded, skipping content:', htmlContent.substring(0, 100));
-                    setTimeout(window.processNextAppend, 10);
+                    // The app reloads the page with everything it has, queued chunks included
+                    const dropped = 1 + window.appendQueue.length;
+                    console.error('Max retries exceeded, asking for a reload:', htmlContent.substring(0, 100));
+                    window.appendQueue = [];
+                    window.isProcessingQueue = false;
+                    window.webkit.messageHandlers.appendFailed.postMessage(String(dropped));
                 }
             }
         };
@@ -277,18 +731,19 @@ const LINK_INTERCEPTOR_JS: &str = r#"
 
         // Core content appending function (synchronous)
         window.doAppendContent = function(htmlContent) {
-            // Check if user was near the bottom before adding content
-            const wasNearBottom = (window.innerHeight + window.pageYOffset) >= (document.body.offsetHeight - 300);
-            
             const div = document.createElement('div');
             div.innerHTML = htmlContent;
+            if (window.typewriter) {
+                window.typeIn(div);
+            }
             document.body.appendChild(div);
+            window.appendedChunks++;
             
-            // Only scroll to bottom if user was already near the bottom
-            if (wasNearBottom) {
+            // Only scroll to bottom while following; scrolling up to read stops it
+            if (window.autoScroll && window.followMode) {
                 window.scrollTo({
                     top: document.body.scrollHeight,
-                    behavior: 'smooth'
+                    behavior: window.lowPower ? 'auto' : 'smooth'
                 });
             }
             
@@ -299,6 +754,14 @@ const LINK_INTERCEPTOR_JS: &str = r#"
             if (typeof window.renderNewLatexExpressions === 'function') {
                 window.renderNewLatexExpressions(div);
             }
+            if (typeof window.highlightCodeBlocks === 'function') {
+                window.highlightCodeBlocks(div);
+            }
+            if (typeof window.numberCrossReferences === 'function') {
+                window.numberCrossReferences();
+            }
+            window.initImageLightbox(div);
+            window.initScrollShadows(div);
         };
         
         // Initialize everything when DOM is ready
@@ -325,73 +788,84 @@ const LINK_INTERCEPTOR_JS: &str = r#"
     });
 "#;
 
-fn generate_stylesheet(content: &DocumentContent) -> String {
-    let base_css = content.style_preferences.generate_css();
-
-    // Get plugin CSS
-    let context = PluginContext {
-        theme_mode: content.style_preferences.theme.clone(),
-        is_streaming: false,
-        content_id: "main".to_string(),
-    };
-
-    let plugin_css = PLUGIN_MANAGER.get_all_css(&context);
-
-    if plugin_css.is_empty() {
-        base_css
-    } else {
-        format!("{base_css}\n\n/* Plugin Styles */\n{plugin_css}")
-    }
+/// Returns a page showing `message` centered, optionally inside a dashed drop zone outline.
+fn placeholder_page(message: &str, drop_zone: bool) -> String {
+    let message = message
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let class = if drop_zone { "drop-zone" } else { "" };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        :root {{ color-scheme: light dark; }}
+        body {{
+            display: flex;
+            align-items: center;
+            justify-content: center;
+            height: 100vh;
+            margin: 0;
+            font-family: -apple-system, BlinkMacSystemFont, sans-serif;
+            font-size: 13px;
+            color: gray;
+        }}
+        body.drop-zone {{
+            box-sizing: border-box;
+            height: calc(100vh - 32px);
+            margin: 16px;
+            border: 2px dashed rgba(128, 128, 128, 0.4);
+            border-radius: 12px;
+        }}
+    </style>
+</head>
+<body class="{class}">{message}</body>
+</html>"#
+    )
 }
 
-fn generate_scripts_html(content: &DocumentContent) -> String {
-    let context = PluginContext {
-        theme_mode: content.style_preferences.theme.clone(),
-        is_streaming: false,
-        content_id: "main".to_string(),
-    };
-
-    let mut html_parts = Vec::new();
-
-    // Get external CSS URLs
-    let external_css = PLUGIN_MANAGER.get_all_external_css();
-    let external_css_tags: Vec<String> = external_css
-        .iter()
-        .map(|url| format!(r#"<link rel="stylesheet" href="{url}">"#))
-        .collect();
-
-    html_parts.extend(external_css_tags);
-
-    // Get external script URLs
-    let external_scripts = PLUGIN_MANAGER.get_all_external_scripts();
-    let external_script_tags: Vec<String> = external_scripts
-        .iter()
-        .map(|url| format!(r#"<script src="{url}"></script>"#))
-        .collect();
-
-    html_parts.extend(external_script_tags);
-
-    // Get plugin JavaScript
-    let plugin_js = PLUGIN_MANAGER.get_all_javascript(&context);
-
-    if !plugin_js.is_empty() {
-        html_parts.push(format!("<script>\n{plugin_js}\n</script>"));
+/// Text for the word count overlay, e.g. "1,024 words · 6 min read".
+fn stats_text(document_content: &DocumentContent) -> String {
+    let words = document_content.word_count().to_string();
+    // Group digits in thousands for readability
+    let mut grouped = String::new();
+    for (i, digit) in words.chars().enumerate() {
+        if i > 0 && (words.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
     }
 
-    html_parts.join("\n")
+    format!(
+        "{grouped} words · {} min read",
+        document_content.reading_time_minutes()
+    )
+}
+
+/// Body of a `taskToggled` message posted when a task checkbox is clicked.
+#[derive(Deserialize)]
+struct TaskToggle {
+    line: usize,
+    checked: bool,
 }
 
 #[derive(Default)]
-pub struct LinkOpenerDelegate;
+pub struct LinkOpenerDelegate {
+    /// Last reported scroll position, as a fraction of the page's scroll height
+    scroll_ratio: Rc<Cell<f64>>,
+    /// Chunks the page failed to append, each followed by a full reload
+    dropped_chunks: Cell<usize>,
+}
 
 impl WebViewDelegate for LinkOpenerDelegate {
     fn on_message(&self, name: &str, body: &str) {
         debug!("Received message: name='{}', body_len={}", name, body.len());
         match name {
             "linkClicked" => {
-                let url = body;
-                info!("Opening external link: {url}");
-                open::that(url).ok();
+                // The app decides whether the link opens in homo or in another app
+                dispatch_menu_message(MenuMessage::OpenLink(body.to_string()));
             }
             "copyText" => {
                 let text = body;
@@ -407,6 +881,34 @@ impl WebViewDelegate for LinkOpenerDelegate {
 
                 info!("Successfully copied to clipboard");
             }
+            "taskToggled" => match serde_json::from_str::<TaskToggle>(body) {
+                Ok(toggle) => {
+                    debug!("Task on line {} set to {}", toggle.line, toggle.checked);
+                    dispatch_menu_message(MenuMessage::SetTaskChecked {
+                        line: toggle.line,
+                        checked: toggle.checked,
+                    });
+                }
+                Err(e) => error!("Invalid taskToggled message {body:?}: {e}"),
+            },
+            "syncRequested" => {
+                info!("Page is missing appended content, rebuilding");
+                dispatch_menu_message(MenuMessage::ResyncContent);
+            }
+            "appendFailed" => {
+                let dropped = body.parse::<usize>().unwrap_or(1);
+                let total = self.dropped_chunks.get() + dropped;
+                self.dropped_chunks.set(total);
+                warn!(
+                    "Page dropped {dropped} streamed chunk(s) ({total} this session), reloading it"
+                );
+                dispatch_menu_message(MenuMessage::AppendFailed);
+            }
+            "scrollPosition" => {
+                if let Ok(ratio) = body.parse::<f64>() {
+                    self.scroll_ratio.set(ratio.clamp(0.0, 1.0));
+                }
+            }
             _ => {
                 debug!("Unknown message type: {name}");
             }
@@ -419,7 +921,25 @@ pub struct MarkdownView {
     current_mode: std::cell::RefCell<ViewMode>,
     accumulated_content: std::cell::RefCell<String>, // HTML content
     accumulated_markdown: std::cell::RefCell<String>, // Original markdown content
+    /// Markdown and HTML lengths of the loaded document and each chunk appended since, oldest
+    /// first, so `--max-buffer` can trim the oldest whole
+    chunk_lengths: std::cell::RefCell<VecDeque<(usize, usize)>>,
+    /// Format of the current document, which decides how trimmed text is marked
+    format: std::cell::RefCell<ContentFormat>,
     last_sync_time: std::cell::RefCell<std::time::Instant>,
+    /// Set when content is appended, cleared when the page has been checked against it
+    sync_dirty: std::cell::RefCell<bool>,
+    /// Chunks appended to the page since it was last loaded or rebuilt
+    appended_chunks: std::cell::RefCell<usize>,
+    scroll_ratio: Rc<Cell<f64>>,
+    /// Where relative image and link paths of the current document resolve
+    base_url: std::cell::RefCell<Option<String>>,
+    /// Whether the content is streamed and the stream still open, shown as a badge
+    stream_state: Cell<StreamState>,
+    /// Whether the presentation style is applied, while the window is full screen
+    presentation: Cell<bool>,
+    /// Whether pages are built without animations, for `--low-power`
+    low_power: Cell<bool>,
 }
 
 impl MarkdownView {
@@ -460,105 +980,237 @@ impl MarkdownView {
         config.add_handler("linkClicked");
         config.add_handler("copyText");
         config.add_handler("appendHTML");
+        config.add_handler("taskToggled");
+        config.add_handler("scrollPosition");
+        config.add_handler("syncRequested");
+        config.add_handler("appendFailed");
 
         // CORRECTED: Use the correct enum variant `InjectAt::Start`.
         config.add_user_script(LINK_INTERCEPTOR_JS, InjectAt::Start, false);
 
-        let delegate = LinkOpenerDelegate;
+        let scroll_ratio = Rc::new(Cell::new(0.0));
+        let delegate = LinkOpenerDelegate {
+            scroll_ratio: scroll_ratio.clone(),
+            dropped_chunks: Cell::new(0),
+        };
         let webview = WebView::with(config, delegate);
+        webview.objc.with_mut(file_drop::accept_file_drops);
 
         MarkdownView {
             webview,
             current_mode: std::cell::RefCell::new(ViewMode::Preview),
             accumulated_content: std::cell::RefCell::new(String::new()),
             accumulated_markdown: std::cell::RefCell::new(String::new()),
+            chunk_lengths: std::cell::RefCell::new(VecDeque::new()),
+            format: std::cell::RefCell::new(ContentFormat::Markdown),
             last_sync_time: std::cell::RefCell::new(std::time::Instant::now()),
+            sync_dirty: std::cell::RefCell::new(false),
+            appended_chunks: std::cell::RefCell::new(0),
+            scroll_ratio,
+            base_url: std::cell::RefCell::new(None),
+            stream_state: Cell::new(StreamState::None),
+            presentation: Cell::new(false),
+            low_power: Cell::new(false),
         }
     }
 
-    pub fn update_content(&self, document_content: &DocumentContent) {
-        self.update_content_with_scroll(document_content, ScrollBehavior::Top);
+    /// Returns whether the presentation style is applied.
+    pub fn is_presenting(&self) -> bool {
+        self.presentation.get()
     }
 
+    /// Applies or removes the larger presentation style without reloading the page.
+    pub fn set_presentation(&self, presentation: bool) {
+        self.presentation.set(presentation);
+        let script = if presentation {
+            format!(
+                "(function() {{ if (!document.getElementById('homo-presentation')) {{ document.body.insertAdjacentHTML('beforeend', {}); }} }})();",
+                serde_json::to_string(PRESENTATION_STYLE).unwrap_or_default()
+            )
+        } else {
+            "(function() { const style = document.getElementById('homo-presentation'); if (style) style.remove(); })();".to_string()
+        };
+        self.evaluate_javascript(&script);
+    }
+
+    /// Returns the presentation style markup for a new page, if presenting.
+    fn presentation_style(&self) -> &'static str {
+        if self.presentation.get() {
+            PRESENTATION_STYLE
+        } else {
+            ""
+        }
+    }
+
+    /// Leaves animations and smooth scrolling out of pages loaded from now on.
+    pub fn set_low_power(&self, low_power: bool) {
+        self.low_power.set(low_power);
+    }
+
+    /// Returns the markup turning animations off for a new page, in low-power mode.
+    fn low_power_style(&self) -> &'static str {
+        if self.low_power.get() {
+            LOW_POWER_STYLE
+        } else {
+            ""
+        }
+    }
+
+    /// Updates the badge telling whether streamed input is still arriving.
+    pub fn set_stream_state(&self, state: StreamState) {
+        self.stream_state.set(state);
+        let badge = serde_json::to_string(self.streaming_indicator())
+            .unwrap_or_else(|_| "\"\"".to_string());
+        self.evaluate_javascript(&format!(
+            "(function() {{ const badge = document.getElementById('homo-streaming'); if (badge) badge.outerHTML = {badge}; }})();"
+        ));
+    }
+
+    /// Returns the streaming badge markup for a new page, if the content is streamed.
+    fn streaming_indicator(&self) -> &'static str {
+        match self.stream_state.get() {
+            StreamState::None => "",
+            StreamState::Open => {
+                r#"<div id="homo-streaming" title="Input is still arriving"><span class="homo-streaming-dot"></span>streaming…</div>"#
+            }
+            StreamState::Closed => {
+                r#"<div id="homo-streaming" class="done" title="The input has closed"><span class="homo-streaming-dot"></span>done</div>"#
+            }
+        }
+    }
+
+    /// Shows a short centered message in place of a document, e.g. while the first content loads.
+    pub fn show_placeholder(&self, message: &str) {
+        self.load_page(&placeholder_page(message, false));
+    }
+
+    /// Shows an outlined area with a message inviting the user to drop or open a file, in a
+    /// window started without a document.
+    pub fn show_drop_zone(&self, message: &str) {
+        self.load_page(&placeholder_page(message, true));
+    }
+
+    /// Returns the current scroll position as a fraction of the page's scroll height.
+    pub fn scroll_ratio(&self) -> f64 {
+        self.scroll_ratio.get()
+    }
+
+    /// Returns the markdown of everything shown so far, including streamed chunks.
+    pub fn accumulated_markdown(&self) -> String {
+        self.accumulated_markdown.borrow().clone()
+    }
+
+    /// Returns the rendered HTML of everything shown so far, including streamed chunks.
+    pub fn accumulated_content(&self) -> String {
+        self.accumulated_content.borrow().clone()
+    }
+
+    /// Appends a streamed chunk to the page. Returns whether older content was trimmed to stay
+    /// within `--max-buffer`, in which case the page was rebuilt from what is left.
     pub fn append_content(
         &self,
         markdown_chunk: &str,
         html_chunk: &str,
         _style_preferences: &crate::gui::types::StylePreferences,
-    ) {
+    ) -> bool {
         // Accumulate both markdown and HTML content
         self.accumulated_content.borrow_mut().push_str(html_chunk);
         self.accumulated_markdown
             .borrow_mut()
             .push_str(markdown_chunk);
+        self.chunk_lengths
+            .borrow_mut()
+            .push_back((markdown_chunk.len(), html_chunk.len()));
 
-        // Check if we need to do a periodic sync to ensure content integrity
-        let now = std::time::Instant::now();
-        let mut last_sync = self.last_sync_time.borrow_mut();
-        let should_sync = now.duration_since(*last_sync) >= std::time::Duration::from_secs(5);
-
-        // Only append to DOM if we're in preview mode
-        if *self.current_mode.borrow() == ViewMode::Preview {
-            if should_sync {
-                // Periodic full refresh to ensure integrity
-                debug!("Performing periodic content sync to ensure integrity");
-                let full_content = self.accumulated_content.borrow().clone();
-                let sync_script = format!(
-                    r#"
-                    try {{
-                        // Clear and rebuild content to ensure integrity
-                        document.body.innerHTML = {};
-                        console.log('Periodic sync completed, content length:', document.body.innerHTML.length);
-                        
-                        // Re-initialize scroll button and plugins
-                        if (typeof window.createScrollToBottomButton === 'function') {{
-                            window.createScrollToBottomButton();
-                            window.addEventListener('scroll', window.handleScroll);
-                        }}
-                        
-                        if (typeof window.renderMermaidDiagrams === 'function') {{
-                            window.renderMermaidDiagrams();
-                        }}
-                        if (typeof window.renderLatexExpressions === 'function') {{
-                            window.renderLatexExpressions();
-                        }}
-                    }} catch(e) {{
-                        console.error('Sync error:', e);
-                    }}
-                    "#,
-                    serde_json::to_string(&full_content)
-                        .unwrap_or_else(|_| "\"Sync error\"".to_string())
-                );
-                self.evaluate_javascript(&sync_script);
-                *last_sync = now;
-            } else {
-                // Normal incremental append
-                let json_escaped_html = serde_json::to_string(html_chunk)
-                    .unwrap_or_else(|_| "\"Error: Could not escape HTML content\"".to_string());
-
-                // Simplified append script that uses the queue system
-                let append_script = format!(
-                    r#"
-                    try {{
-                        if (typeof window.appendContent === 'function') {{
-                            window.appendContent({json_escaped_html});
-                        }} else {{
-                            console.error('appendContent function not available');
-                        }}
-                    }} catch(e) {{
-                        console.error('JavaScript append error:', e);
+        *self.sync_dirty.borrow_mut() = true;
+
+        let trimmed = self.trim_accumulated();
+        if trimmed && *self.current_mode.borrow() == ViewMode::Preview {
+            // The rebuilt page includes the new chunk
+            self.resync_content();
+            self.evaluate_javascript(
+                "if (window.autoScroll && window.followMode) { window.scrollTo(0, document.body.scrollHeight); }",
+            );
+        } else if *self.current_mode.borrow() == ViewMode::Preview {
+            // Only append to DOM if we're in preview mode
+            *self.appended_chunks.borrow_mut() += 1;
+
+            // Normal incremental append
+            let json_escaped_html = serde_json::to_string(html_chunk)
+                .unwrap_or_else(|_| "\"Error: Could not escape HTML content\"".to_string());
+
+            // Simplified append script that uses the queue system
+            let append_script = format!(
+                r#"
+                try {{
+                    if (typeof window.appendContent === 'function') {{
+                        window.appendContent({json_escaped_html});
+                    }} else {{
+                        console.error('appendContent function not available');
                     }}
-                    "#
-                );
+                }} catch(e) {{
+                    console.error('JavaScript append error:', e);
+                }}
+                "#
+            );
 
-                debug!(
-                    "Queuing content append with {} characters of HTML",
-                    html_chunk.len()
-                );
-                self.evaluate_javascript(&append_script);
-            }
+            debug!(
+                "Queuing content append with {} characters of HTML",
+                html_chunk.len()
+            );
+            self.evaluate_javascript(&append_script);
+
+            self.verify_appended_content();
         }
         // If we're in source mode, we'll regenerate the full content when toggling
+        trimmed
+    }
+
+    /// Drops the oldest chunks once the accumulated markdown outgrows `--max-buffer`, always
+    /// keeping the newest chunk. Chunks are only appended when they start a new block, so no
+    /// block is cut in half. Returns whether anything was dropped.
+    fn trim_accumulated(&self) -> bool {
+        let Some(max_bytes) = streaming::max_buffer() else {
+            return false;
+        };
+        let mut markdown = self.accumulated_markdown.borrow_mut();
+        if markdown.len() <= max_bytes {
+            return false;
+        }
+
+        let target = streaming::trim_target(max_bytes);
+        let mut chunk_lengths = self.chunk_lengths.borrow_mut();
+        let (mut markdown_cut, mut html_cut) = (0, 0);
+        while chunk_lengths.len() > 1 && markdown.len() - markdown_cut > target {
+            let Some((markdown_len, html_len)) = chunk_lengths.pop_front() else {
+                break;
+            };
+            markdown_cut += markdown_len;
+            html_cut += html_len;
+        }
+        if markdown_cut == 0 {
+            return false;
+        }
+
+        let markdown_marker = streaming::trimmed_marker(&self.format.borrow());
+        let html_marker = streaming::TRIMMED_MARKER_HTML;
+        markdown.replace_range(..markdown_cut, markdown_marker);
+        self.accumulated_content
+            .borrow_mut()
+            .replace_range(..html_cut, html_marker);
+        if let Some((markdown_len, html_len)) = chunk_lengths.front_mut() {
+            *markdown_len += markdown_marker.len();
+            *html_len += html_marker.len();
+        }
+        info!("Trimmed {markdown_cut} bytes of the oldest streamed content");
+        true
+    }
+
+    /// Loads an emptied document, resetting the accumulated content and the sync clock as if
+    /// nothing had been streamed yet.
+    pub fn clear_content(&self, document_content: &DocumentContent) {
+        *self.last_sync_time.borrow_mut() = std::time::Instant::now();
+        self.update_content_with_scroll(document_content, ScrollBehavior::Top);
     }
 
     pub fn update_content_with_scroll(
@@ -569,20 +1221,54 @@ impl MarkdownView {
         // Set accumulated content to match the new content (for streaming mode)
         *self.accumulated_content.borrow_mut() = document_content.html.clone();
         *self.accumulated_markdown.borrow_mut() = document_content.markdown.clone();
+        self.reset_chunk_lengths(document_content);
         *self.current_mode.borrow_mut() = document_content.mode.clone();
+        *self.base_url.borrow_mut() = document_content.base_url();
+        // The new page holds everything so far
+        *self.appended_chunks.borrow_mut() = 0;
+        *self.sync_dirty.borrow_mut() = false;
 
         let content = match document_content.mode {
             ViewMode::Preview => &document_content.html,
             ViewMode::Source => &markdown::highlight_markdown_with_theme(
                 &document_content.markdown,
                 &document_content.style_preferences.theme,
+                document_content.style_preferences.line_numbers,
+                document_content.style_preferences.show_whitespace,
             ),
         };
 
-        let onload_script = match scroll_behavior {
-            ScrollBehavior::Bottom => "window.scrollToBottom();",
-            ScrollBehavior::Top => "window.scrollToTop();",
+        let onload_script = match &scroll_behavior {
+            ScrollBehavior::Bottom => "window.scrollToBottom();".to_string(),
+            ScrollBehavior::Top => "window.scrollToTop();".to_string(),
+            ScrollBehavior::Restore(ratio) => format!("window.scrollToRatio({ratio});"),
+            // The script sits in a double-quoted attribute
+            ScrollBehavior::Anchor(id) => format!(
+                "window.scrollToAnchor({});",
+                serde_json::to_string(id)
+                    .unwrap_or_default()
+                    .replace('&', "&amp;")
+                    .replace('"', "&quot;")
+            ),
+        };
+
+        let auto_scroll = document_content.style_preferences.auto_scroll;
+        let typewriter = document_content.style_preferences.typewriter;
+        let streaming_indicator = self.streaming_indicator();
+        let presentation_style = self.presentation_style();
+        let low_power_style = self.low_power_style();
+        let stats = if document_content.style_preferences.show_stats {
+            format!(
+                r#"<div id="homo-stats">{}</div>"#,
+                stats_text(document_content)
+            )
+        } else {
+            String::new()
         };
+        // A fresh page starts at the top until it reports otherwise
+        if !matches!(scroll_behavior, ScrollBehavior::Restore(_)) {
+            self.scroll_ratio.set(0.0);
+        }
 
         let stylesheet = generate_stylesheet(document_content);
         let scripts = generate_scripts_html(document_content);
@@ -596,7 +1282,13 @@ impl MarkdownView {
 </head>
 <body onload="{onload_script}">
 {content}
+{stats}
+{streaming_indicator}
+{presentation_style}
+{low_power_style}
 <script>
+window.setAutoScroll({auto_scroll});
+window.setTypewriter({typewriter});
 // Initialize scroll to bottom button for regular content updates
 setTimeout(function() {{
     console.log('Trying to create scroll button...');
@@ -617,7 +1309,232 @@ setTimeout(function() {{
 </body>
 </html>"#
         );
-        self.webview.load_html(&full_html);
+        self.load_page(&full_html);
+        self.set_page_zoom(document_content.style_preferences.zoom_level);
+    }
+
+    /// Loads a full page, resolving relative paths against the current document's base URL.
+    #[allow(deprecated)]
+    #[allow(unexpected_cfgs)]
+    fn load_page(&self, html: &str) {
+        let Some(base_url) = self.base_url.borrow().clone() else {
+            // Piped input has nothing to resolve relative paths against
+            self.webview.load_html(html);
+            return;
+        };
+
+        self.webview.objc.with_mut(|obj| unsafe {
+            use cocoa::base::{id, nil};
+            use cocoa::foundation::NSString;
+            use objc::{class, msg_send, sel, sel_impl};
+
+            let ns_html = NSString::alloc(nil).init_str(html);
+            let ns_base_url = NSString::alloc(nil).init_str(&base_url);
+            let url: id = msg_send![class!(NSURL), URLWithString: ns_base_url];
+            let _: () = msg_send![obj, loadHTMLString: ns_html baseURL: url];
+        });
+    }
+
+    /// Scales the whole page (text, code, diagrams and images) by `zoom_level`.
+    #[allow(deprecated)]
+    #[allow(unexpected_cfgs)]
+    pub fn set_page_zoom(&self, zoom_level: f32) {
+        self.webview.objc.with_mut(|obj| unsafe {
+            use objc::{msg_send, sel, sel_impl};
+
+            let zoom_level = zoom_level as f64; // CGFloat
+            let _: () = msg_send![obj, setPageZoom: zoom_level];
+        });
+    }
+
+    /// Periodically asks the page whether every appended chunk made it into the DOM. The page
+    /// requests a rebuild (see [`Self::resync_content`]) only if chunks are missing, and waits
+    /// until the user stops scrolling or selecting, so steady streaming never flashes.
+    fn verify_appended_content(&self) {
+        let now = std::time::Instant::now();
+        let mut last_sync = self.last_sync_time.borrow_mut();
+        if !*self.sync_dirty.borrow() || now.duration_since(*last_sync) < PERIODIC_SYNC_INTERVAL {
+            return;
+        }
+
+        let expected_chunks = *self.appended_chunks.borrow();
+        self.evaluate_javascript(&format!(
+            "if (typeof window.verifyAppendedContent === 'function') {{ window.verifyAppendedContent({expected_chunks}); }}"
+        ));
+        *self.sync_dirty.borrow_mut() = false;
+        *last_sync = now;
+    }
+
+    /// Rebuilds the page body from the accumulated HTML, restoring chunks that failed to append.
+    pub fn resync_content(&self) {
+        if *self.current_mode.borrow() != ViewMode::Preview {
+            return;
+        }
+
+        debug!("Rebuilding page content to restore missing chunks");
+        let full_content = self.accumulated_content.borrow().clone();
+        let appended_chunks = *self.appended_chunks.borrow();
+        let sync_script = format!(
+            r#"
+            try {{
+                // Clear and rebuild content to ensure integrity
+                window.appendQueue = [];
+                document.body.innerHTML = {};
+                document.body.insertAdjacentHTML('beforeend', {});
+                window.appendedChunks = {appended_chunks};
+                console.log('Content sync completed, content length:', document.body.innerHTML.length);
+
+                // Re-initialize scroll button and plugins
+                if (typeof window.createScrollToBottomButton === 'function') {{
+                    window.createScrollToBottomButton();
+                    window.addEventListener('scroll', window.handleScroll);
+                }}
+
+                if (typeof window.renderMermaidDiagrams === 'function') {{
+                    window.renderMermaidDiagrams();
+                }}
+                if (typeof window.renderLatexExpressions === 'function') {{
+                    window.renderLatexExpressions();
+                }}
+                if (typeof window.highlightCodeBlocks === 'function') {{
+                    window.highlightCodeBlocks();
+                }}
+                if (typeof window.numberCrossReferences === 'function') {{
+                    window.numberCrossReferences();
+                }}
+                window.initImageLightbox(document);
+                window.initScrollShadows(document);
+            }} catch(e) {{
+                console.error('Sync error:', e);
+            }}
+            "#,
+            serde_json::to_string(&full_content).unwrap_or_else(|_| "\"Sync error\"".to_string()),
+            serde_json::to_string(self.streaming_indicator())
+                .unwrap_or_else(|_| "\"\"".to_string())
+        );
+        self.evaluate_javascript(&sync_script);
+    }
+
+    /// Shows a streamed document rendered again as a whole, in place of the chunks appended so
+    /// far, and keeps following the end of the stream.
+    pub fn replace_streamed_content(&self, document_content: &DocumentContent) {
+        self.sync_content(document_content);
+        self.resync_content();
+        self.evaluate_javascript(
+            "if (window.autoScroll && window.followMode) { window.scrollTo(0, document.body.scrollHeight); }",
+        );
+    }
+
+    /// Replaces the stored document content without reloading the page, so later
+    /// syncs and mode toggles reflect edits already visible in the DOM.
+    pub fn sync_content(&self, document_content: &DocumentContent) {
+        *self.accumulated_content.borrow_mut() = document_content.html.clone();
+        *self.accumulated_markdown.borrow_mut() = document_content.markdown.clone();
+        self.reset_chunk_lengths(document_content);
+    }
+
+    /// Starts tracking chunks afresh, with the whole document as the oldest.
+    fn reset_chunk_lengths(&self, document_content: &DocumentContent) {
+        *self.chunk_lengths.borrow_mut() =
+            VecDeque::from([(document_content.markdown.len(), document_content.html.len())]);
+        *self.format.borrow_mut() = document_content.format.clone();
+    }
+
+    /// Opens the print panel for the rendered page, which also offers "Save as PDF".
+    ///
+    /// Printing goes through the WebView's own print operation, so inline SVGs such as
+    /// rendered Mermaid diagrams are captured as they appear on screen.
+    #[allow(deprecated)]
+    #[allow(unexpected_cfgs)]
+    pub fn print(&self) {
+        self.webview.objc.with_mut(|obj| unsafe {
+            use cocoa::base::{id, nil};
+            use cocoa::foundation::NSRect;
+            use objc::{class, msg_send, sel, sel_impl};
+
+            let print_info: id = msg_send![class!(NSPrintInfo), sharedPrintInfo];
+            // Scale wide content (tables, diagrams) to the page width instead of clipping it
+            let _: () = msg_send![print_info, setHorizontalPagination: 1isize]; // NSFitPagination
+
+            let operation: id = msg_send![obj, printOperationWithPrintInfo: print_info];
+            if operation == nil {
+                error!("WebView did not provide a print operation");
+                return;
+            }
+
+            // The WebView's print view starts with an empty frame, which prints blank pages
+            let bounds: NSRect = msg_send![obj, bounds];
+            let print_view: id = msg_send![operation, view];
+            let _: () = msg_send![print_view, setFrame: bounds];
+
+            let window: id = msg_send![obj, window];
+            let no_selector: *const std::ffi::c_void = std::ptr::null();
+            let no_context: *mut std::ffi::c_void = std::ptr::null_mut();
+            let _: () = msg_send![operation, runOperationModalForWindow: window delegate: nil didRunSelector: no_selector contextInfo: no_context];
+            info!("Print panel opened");
+        });
+    }
+
+    /// Refreshes the word count overlay, if enabled, after content was appended.
+    pub fn update_stats(&self, document_content: &DocumentContent) {
+        if !document_content.style_preferences.show_stats {
+            return;
+        }
+        let json_text = serde_json::to_string(&stats_text(document_content))
+            .unwrap_or_else(|_| "\"\"".to_string());
+        let script = format!(
+            "if (typeof window.updateStats === 'function') {{ window.updateStats({json_text}); }}"
+        );
+        self.evaluate_javascript(&script);
+    }
+
+    /// Turns the typewriter effect for appended content on or off.
+    pub fn set_typewriter(&self, enabled: bool) {
+        let script = format!(
+            "if (typeof window.setTypewriter === 'function') {{ window.setTypewriter({enabled}); }}"
+        );
+        self.evaluate_javascript(&script);
+    }
+
+    /// Scales Mermaid diagrams by `scale` without reloading the page.
+    pub fn set_mermaid_scale(&self, scale: f32) {
+        let script =
+            format!("document.documentElement.style.setProperty('--mermaid-scale', '{scale}');");
+        self.evaluate_javascript(&script);
+    }
+
+    /// Scrolls to the next heading below the top of the window, or the previous one above it.
+    pub fn jump_to_heading(&self, forward: bool) {
+        let direction = if forward { 1 } else { -1 };
+        let script = format!(
+            "if (typeof window.jumpToHeading === 'function') {{ window.jumpToHeading({direction}); }}"
+        );
+        self.evaluate_javascript(&script);
+    }
+
+    /// Turns following appended content and the scroll-to-bottom button on or off.
+    pub fn set_auto_scroll(&self, enabled: bool) {
+        let script = format!(
+            "if (typeof window.setAutoScroll === 'function') {{ window.setAutoScroll({enabled}); }}"
+        );
+        self.evaluate_javascript(&script);
+    }
+
+    /// Shows a dismissible banner at the top of the page.
+    pub fn show_banner(&self, message: &str) {
+        let json_message = serde_json::to_string(message).unwrap_or_else(|_| "\"\"".to_string());
+        let script = format!(
+            "if (typeof window.showBanner === 'function') {{ window.showBanner({json_message}); }}"
+        );
+        self.evaluate_javascript(&script);
+    }
+
+    /// Updates the streaming progress bar; a fraction of 1.0 removes it.
+    pub fn set_progress(&self, fraction: f64) {
+        let script = format!(
+            "if (typeof window.updateProgress === 'function') {{ window.updateProgress({fraction}); }}"
+        );
+        self.evaluate_javascript(&script);
     }
 
     pub fn copy_selected_text(&self) {
@@ -634,7 +1551,14 @@ setTimeout(function() {{
         info!("Select All triggered via menu - use Cmd+A to select all text");
     }
 
-    pub fn toggle_mode(&self, style_preferences: &crate::gui::types::StylePreferences) {
+    /// Sets the view mode without redrawing, e.g. before the first document arrives, so that
+    /// content appended meanwhile waits for a full page in that mode.
+    pub fn set_mode(&self, mode: ViewMode) {
+        *self.current_mode.borrow_mut() = mode;
+    }
+
+    /// Switches between the rendered and the highlighted source view, returning the new mode.
+    pub fn toggle_mode(&self, style_preferences: &crate::gui::types::StylePreferences) -> ViewMode {
         // Toggle the current mode
         let new_mode = match *self.current_mode.borrow() {
             ViewMode::Preview => ViewMode::Source,
@@ -653,35 +1577,34 @@ setTimeout(function() {{
                 markdown::highlight_markdown_with_theme(
                     &self.accumulated_markdown.borrow(),
                     &style_preferences.theme,
+                    style_preferences.line_numbers,
+                    style_preferences.show_whitespace,
                 )
             }
         };
 
         // Do a full reload for mode toggle (this is acceptable since it's user-initiated)
-        let base_css = style_preferences.generate_css();
-        let context = PluginContext {
-            theme_mode: style_preferences.theme.clone(),
-            is_streaming: false,
-            content_id: "toggle".to_string(),
-        };
-
-        let plugin_css = PLUGIN_MANAGER.get_all_css(&context);
-        let stylesheet = if plugin_css.is_empty() {
-            base_css
-        } else {
-            format!("{base_css}\n\n/* Plugin Styles */\n{plugin_css}")
-        };
-
-        let scripts = generate_scripts_html(&DocumentContent {
+        let toggle_document = DocumentContent {
             markdown: self.accumulated_markdown.borrow().clone(),
             html: content.clone(),
             mode: new_mode.clone(),
+            format: ContentFormat::default(),
             title: "Toggle Mode".to_string(),
             file_path: None,
+            source_url: None,
+            title_override: None,
+            fragment: None,
             style_preferences: style_preferences.clone(),
-        });
+        };
+        let stylesheet = generate_stylesheet(&toggle_document);
+        let scripts = generate_scripts_html(&toggle_document);
 
         let onload_script = "window.scrollToTop();";
+        let auto_scroll = style_preferences.auto_scroll;
+        let typewriter = style_preferences.typewriter;
+        let streaming_indicator = self.streaming_indicator();
+        let presentation_style = self.presentation_style();
+        let low_power_style = self.low_power_style();
         let full_html = format!(
             r#"<!DOCTYPE html>
 <html>
@@ -692,7 +1615,12 @@ setTimeout(function() {{
 </head>
 <body onload="{onload_script}">
 {content}
+{streaming_indicator}
+{presentation_style}
+{low_power_style}
 <script>
+window.setAutoScroll({auto_scroll});
+window.setTypewriter({typewriter});
 // Initialize scroll to bottom button for mode toggle
 setTimeout(function() {{
     console.log('Trying to create scroll button in mode toggle...');
@@ -713,6 +1641,8 @@ setTimeout(function() {{
 </body>
 </html>"#
         );
-        self.webview.load_html(&full_html);
+        self.load_page(&full_html);
+        self.set_page_zoom(style_preferences.zoom_level);
+        new_mode
     }
 }
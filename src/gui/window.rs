@@ -1,7 +1,22 @@
-use crate::content::DocumentContent;
+#![allow(unexpected_cfgs)] // Suppress objc crate cfg warnings
+
+use crate::content::{DEFAULT_TITLE, DocumentContent};
 use crate::gui::view::MarkdownView;
 use cacao::appkit::App;
 use cacao::appkit::window::{Window, WindowConfig, WindowStyle};
+use objc::{msg_send, sel, sel_impl};
+
+/// `NSWindowStyleMaskFullScreen`
+const FULL_SCREEN_STYLE_MASK: usize = 1 << 14;
+
+/// `NSWindowCollectionBehaviorFullScreenPrimary`
+const FULL_SCREEN_PRIMARY: usize = 1 << 7;
+
+/// Placeholder shown until the first content arrives.
+pub const LOADING_MESSAGE: &str = "Loading…";
+
+/// Placeholder of a window started without a document (`--stay-open`).
+pub const EMPTY_WINDOW_MESSAGE: &str = "Drop a markdown file here, or choose File → Open…";
 
 /// Calculates optimal window size based on content characteristics
 fn calculate_window_size(content: &DocumentContent, is_pipe_mode: bool) -> (f64, f64) {
@@ -35,8 +50,9 @@ fn calculate_window_size(content: &DocumentContent, is_pipe_mode: bool) -> (f64,
     }
 }
 
-/// Creates and configures the main application window for the markdown viewer.
-pub fn create_main_window(content_view: &MarkdownView) -> Window {
+/// Creates and configures the main application window for the markdown viewer, titled `title`
+/// or [`DEFAULT_TITLE`].
+pub fn create_main_window(content_view: &MarkdownView, title: Option<&str>) -> Window {
     let mut config = WindowConfig::default();
     config.set_styles(&[
         WindowStyle::Titled,
@@ -47,7 +63,7 @@ pub fn create_main_window(content_view: &MarkdownView) -> Window {
 
     let window = Window::new(config);
 
-    window.set_title("Hoss' Opinionated Markdown Output");
+    window.set_title(title.unwrap_or(DEFAULT_TITLE));
     window.set_minimum_content_size(400., 300.);
 
     window.set_content_view(&content_view.webview);
@@ -76,12 +92,9 @@ pub fn create_main_window_with_content(
 
     let window = Window::new(config);
 
-    window.set_title("Hoss' Opinionated Markdown Output");
+    window.set_title(&content.preferred_title());
     window.set_minimum_content_size(400., 300.);
-
-    // Calculate and set content-aware window size
-    let (width, height) = calculate_window_size(content, is_pipe_mode);
-    window.set_content_size(width, height);
+    fit_window_to_content(&window, content, is_pipe_mode);
 
     window.set_content_view(&content_view.webview);
 
@@ -92,3 +105,24 @@ pub fn create_main_window_with_content(
 
     window
 }
+
+/// Resizes a window for its content, e.g. once the first content replaces the loading placeholder.
+pub fn fit_window_to_content(window: &Window, content: &DocumentContent, is_pipe_mode: bool) {
+    let (width, height) = calculate_window_size(content, is_pipe_mode);
+    window.set_content_size(width, height);
+}
+
+/// Returns whether the window is in native full screen.
+pub fn is_full_screen(window: &Window) -> bool {
+    let style_mask: usize = unsafe { msg_send![&*window.objc, styleMask] };
+    style_mask & FULL_SCREEN_STYLE_MASK != 0
+}
+
+/// Enters or leaves native full screen, making sure the window is allowed to go full screen.
+pub fn toggle_full_screen(window: &Window) {
+    unsafe {
+        let behavior: usize = msg_send![&*window.objc, collectionBehavior];
+        let _: () = msg_send![&*window.objc, setCollectionBehavior: behavior | FULL_SCREEN_PRIMARY];
+    }
+    window.toggle_full_screen();
+}
@@ -0,0 +1,28 @@
+//! Hoss' Opinionated Markdown Output: markdown rendered to themed, standalone HTML with syntax
+//! highlighting, diagrams and math.
+//!
+//! [`render_document`] renders a whole document without the macOS viewer, so it works from
+//! other programs and headless tests. Build with `default-features = false` to leave out the
+//! GUI dependencies.
+
+pub mod ansi;
+pub mod config;
+pub mod content;
+pub mod error;
+pub mod export;
+pub mod markdown;
+pub mod plugins;
+/// Serves a document over HTTP with live reload, for viewing from another machine
+#[cfg(feature = "server")]
+pub mod server;
+
+/// Style preferences shared by the renderer and the viewer.
+pub mod gui {
+    pub mod types;
+    /// Where the viewer keeps its preferences
+    #[cfg(feature = "gui")]
+    pub mod user_defaults;
+}
+
+pub use export::{RenderOptions, render_document};
+pub use gui::types::{StylePreferences, ThemeMode};
@@ -1,18 +1,17 @@
 //! Entry point for the Markdown Viewer application.
 //! Handles both GUI and streaming (pipe) modes.
 
-use content::ContentUpdate;
-use log::{debug, error, info};
+use homo::content::{ContentUpdate, ViewMode};
+use homo::{ansi, config, content, error, export, markdown, plugins};
+use log::{debug, error, info, warn};
 use std::env;
 use std::sync::mpsc;
 use std::thread;
 
-mod content;
-mod error;
+mod cli;
 mod gui;
-mod markdown;
 mod menu;
-mod plugins;
+mod recent_files;
 mod streaming;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -21,47 +20,224 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .format_timestamp_secs()
         .init();
 
+    debug!("Application starting...");
+    let args: Vec<String> = env::args().collect();
+    debug!("Command line args: {args:?}");
+    let options = cli::CliOptions::parse(&args);
+
+    if options.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if options.version {
+        println!("homo {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
     // Initialize plugin system
     if let Err(e) = plugins::manager::initialize_plugins() {
         error!("Failed to initialize plugin system: {e}");
     }
 
-    debug!("Application starting...");
-    let args: Vec<String> = env::args().collect();
-    debug!("Command line args: {args:?}");
+    // Apply user configuration to the registered plugins
+    match config::AppConfig::load() {
+        Ok(config) => {
+            if let Err(e) = plugins::manager::PLUGIN_MANAGER.apply_config(&config) {
+                error!("Failed to apply plugin configuration: {e}");
+            }
+        }
+        Err(e) => error!("Failed to load configuration: {e}"),
+    }
+
+    plugins::set_offline(options.offline);
+    if let Some(base) = options.cdn_base.or_else(|| env::var("HOMO_CDN_BASE").ok()) {
+        plugins::set_cdn_base(&base);
+    }
+
+    if let Some(theme_path) = &options.syntax_theme {
+        markdown::load_custom_theme(theme_path);
+    }
+
+    if let Some(css_path) = &options.css {
+        export::set_custom_css_path(css_path);
+    }
+
+    if let Some(wiki_base) = &options.wiki_base {
+        markdown::set_wiki_base(wiki_base);
+    }
 
-    // If a filename is provided as an argument, use file mode.
-    if args.len() > 1 {
-        let filename = &args[1];
+    markdown::set_inline_code_language(options.inline_lang.clone());
+
+    markdown::set_dialect(options.dialect);
+    streaming::set_max_buffer(options.max_buffer);
+    markdown::set_sanitize_html(options.sanitize_html);
+
+    // Set before any parsing starts, since content is parsed on the reader threads
+    let mut style_preferences = gui::types::StylePreferences::load_from_user_defaults();
+    if let Some(font) = options.font {
+        info!("Using font family: {font}");
+        style_preferences.font_family = gui::types::FontFamily::Custom(font);
+        // The window reads its preferences back from user defaults
+        style_preferences.save_to_user_defaults();
+    }
+    if let Some(color) = options.code_background {
+        if color.eq_ignore_ascii_case("none") {
+            style_preferences.code_background = None;
+            style_preferences.save_to_user_defaults();
+        } else if markdown::parse_hex_color(&color).is_some() {
+            info!("Using code background: {color}");
+            style_preferences.code_background = Some(color);
+            style_preferences.save_to_user_defaults();
+        } else {
+            warn!("Ignoring --code-background {color}: expected a color such as #fdf6e3");
+        }
+    }
+    if options.confirm_links {
+        style_preferences.confirm_links = true;
+        style_preferences.save_to_user_defaults();
+    }
+    markdown::set_client_side_highlighting(style_preferences.client_side_highlighting);
+    markdown::set_smart_punctuation(style_preferences.smart_punctuation || options.smart);
+    markdown::set_autolinks(style_preferences.autolinks);
+    markdown::set_emoji_shortcodes(style_preferences.emoji_shortcodes);
+    markdown::set_syntax_theme(style_preferences.syntax_theme.clone());
+    markdown::set_code_background(style_preferences.code_background.as_deref());
+
+    // Outline for other tools: print it as JSON and exit without starting the GUI.
+    if options.dump_json {
+        export::dump_json(options.file.as_deref())?;
+        return Ok(());
+    }
+
+    // Headless export: render to a file and exit without starting the GUI.
+    if let Some(output) = &options.export_html {
+        info!("Exporting HTML to {output}");
+        export::export_html(options.file.as_deref(), output, &style_preferences)?;
+        return Ok(());
+    }
+
+    // Remote viewing: serve the file over HTTP without starting the GUI.
+    if let Some(port) = options.serve {
+        #[cfg(feature = "server")]
+        {
+            let Some(filename) = options.file.as_deref() else {
+                return Err("--serve requires a markdown file".into());
+            };
+            let mut preferences = style_preferences;
+            preferences.smart_punctuation |= options.smart;
+            homo::server::serve(filename, port, preferences)?;
+            return Ok(());
+        }
+        // Opening the window instead would hide that nothing is being served
+        #[cfg(not(feature = "server"))]
+        return Err(format!(
+            "--serve {port} needs a build with the server feature (cargo build --features server)"
+        )
+        .into());
+    }
+
+    // From here on a panic is shown in an alert instead of the window silently disappearing
+    gui::install_panic_hook();
+
+    // Exports keep the light code theme; the window resolves System mode from the appearance
+    markdown::set_system_dark_mode(gui::appearance::system_is_dark());
+    // Exports have no script to unfold long code blocks, so only the window folds them
+    markdown::set_code_fold_lines(style_preferences.code_fold_lines);
+
+    // How much streamed markdown to collect per update, from --first-lines/--flush-lines
+    let defaults = streaming::FlushThresholds::default();
+    let thresholds = streaming::FlushThresholds {
+        first_lines: options.first_lines.unwrap_or(defaults.first_lines),
+        flush_lines: options.flush_lines.unwrap_or(defaults.flush_lines),
+    };
+    // `--source` opens the first document in the Source view
+    let initial_mode = if options.source {
+        ViewMode::Source
+    } else {
+        ViewMode::Preview
+    };
+
+    // A URL argument is fetched in the background and rendered like a file.
+    if let Some(url) = options.file.clone().filter(|file| streaming::is_url(file)) {
+        info!("URL argument detected: {url}. Fetching document.");
+        let (sender, receiver) = mpsc::channel::<ContentUpdate>();
+        thread::spawn(move || {
+            if let Err(e) = streaming::read_from_url(sender.clone(), &url) {
+                // Replace the loading placeholder with the error
+                error!("Failed to load {url}: {e}");
+                let _ = sender.send(ContentUpdate::Error(format!("Failed to load {url}: {e}")));
+            }
+        });
+        gui::run_app(Some(receiver), false, None, options.low_power, initial_mode);
+    } else if let Some(filename) = options.file {
+        // If a filename is provided as an argument, use file mode.
         info!("File argument detected: {filename}. Setting up file mode.");
+        // `file.md#section` opens the file scrolled to that heading
+        let (filename, fragment) = streaming::split_fragment(&filename);
         let (sender, receiver) = mpsc::channel::<ContentUpdate>();
-        let filename = filename.clone();
+        let stream_files = options.stream_files;
+        let fifo = streaming::is_fifo(&filename);
         thread::spawn(move || {
             debug!("File streaming thread started for: {filename}");
-            if let Err(e) = streaming::read_from_file(sender, &filename) {
+            let result = if fifo {
+                streaming::read_from_fifo(sender.clone(), &filename, thresholds)
+            } else if stream_files {
+                streaming::read_from_file_streaming(sender.clone(), &filename, thresholds)
+            } else {
+                streaming::read_from_file(sender.clone(), &filename, fragment.as_deref())
+            };
+            if let Err(e) = result {
                 error!("File streaming thread failed: {e}");
+                let _ = sender.send(ContentUpdate::Error(format!(
+                    "Couldn't read {filename}: {e}"
+                )));
             } else {
                 debug!("File streaming thread completed successfully");
             }
         });
-        gui::run_app(Some(receiver), false); // File mode
-    } else if atty::is(atty::Stream::Stdin) {
-        info!(
-            "No pipe or file argument detected. Please provide a markdown file as an argument or pipe input. Exiting."
+        // Streamed files and named pipes behave like pipes: follow the tail as content arrives
+        gui::run_app(
+            Some(receiver),
+            stream_files || fifo,
+            None,
+            options.low_power,
+            initial_mode,
         );
-        return Ok(());
+    } else if atty::is(atty::Stream::Stdin) {
+        if !options.stay_open {
+            info!(
+                "No pipe or file argument detected. Please provide a markdown file as an argument or pipe input, or pass --stay-open for an empty window. Exiting."
+            );
+            return Ok(());
+        }
+        info!("No input given. Opening an empty window.");
+        gui::run_app(None, false, options.title, options.low_power, initial_mode);
     } else {
         info!("Pipe detected. Setting up streaming mode.");
         let (sender, receiver) = mpsc::channel::<ContentUpdate>();
+        let plain = options.plain;
+        let title = options.title.clone();
         thread::spawn(move || {
             debug!("Pipe streaming thread started");
-            if let Err(e) = streaming::read_from_pipe(sender) {
+            let result =
+                streaming::read_from_pipe(sender.clone(), plain, thresholds, title.as_deref());
+            if let Err(e) = result {
                 error!("Streaming thread failed: {e}");
+                let _ = sender.send(ContentUpdate::Error(format!(
+                    "Couldn't read the input: {e}"
+                )));
             } else {
                 debug!("Pipe streaming thread completed successfully");
             }
         });
-        gui::run_app(Some(receiver), true); // Pipe mode
+        // Pipe mode
+        gui::run_app(
+            Some(receiver),
+            true,
+            options.title,
+            options.low_power,
+            initial_mode,
+        );
     }
     debug!("Application exiting");
     Ok(())
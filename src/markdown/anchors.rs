@@ -0,0 +1,69 @@
+//! `id` anchors on headings, so `#section` links and `file.md#section` can reach them.
+
+use std::collections::HashMap;
+
+use pulldown_cmark::HeadingLevel;
+
+/// Returns the GitHub-style anchor for a heading: lowercase, spaces as hyphens, and
+/// punctuation other than `-` and `_` removed, e.g. `Getting Started!` → `getting-started`.
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Gives each heading of a document a unique anchor as it is rendered.
+#[derive(Debug, Default)]
+pub struct HeadingAnchors {
+    /// How many headings have used each slug so far
+    used: HashMap<String, usize>,
+    /// Level, output offset and text so far of the heading being rendered
+    open: Option<(HeadingLevel, usize, String)>,
+}
+
+impl HeadingAnchors {
+    /// Marks the start of a heading, whose content is rendered from `output_len` onwards.
+    pub fn start(&mut self, level: HeadingLevel, output_len: usize) {
+        self.open = Some((level, output_len, String::new()));
+    }
+
+    /// Adds text of the heading being rendered, if any.
+    pub fn push_text(&mut self, text: &str) {
+        if let Some((_, _, heading_text)) = &mut self.open {
+            heading_text.push_str(text);
+        }
+    }
+
+    /// Returns the anchor for the next heading with this text, unique within the document.
+    /// Repeated headings get `-1`, `-2` and so on appended.
+    pub fn anchor(&mut self, text: &str) -> String {
+        let mut anchor = slug(text);
+        if anchor.is_empty() {
+            anchor = "section".to_string();
+        }
+        let count = self.used.entry(anchor.clone()).or_insert(0);
+        if *count > 0 {
+            anchor = format!("{anchor}-{count}");
+        }
+        *count += 1;
+        anchor
+    }
+
+    /// Wraps the heading content rendered since [`Self::start`] in its tags, the opening one
+    /// carrying the anchor.
+    pub fn end(&mut self, output: &mut String) {
+        let Some((level, start, text)) = self.open.take() else {
+            return;
+        };
+        let anchor = self.anchor(&text);
+        output.insert_str(start, &format!("<{level} id=\"{anchor}\">"));
+        output.push_str(&format!("</{level}>\n"));
+    }
+}
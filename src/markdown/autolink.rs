@@ -0,0 +1,99 @@
+//! GFM-style autolinking of bare URLs (`https://...`, `www....`) in text.
+
+use pulldown_cmark::{Event, LinkType, Tag, TagEnd, html};
+use std::ops::Range;
+
+const URL_PREFIXES: [&str; 3] = ["https://", "http://", "www."];
+
+/// Renders text as HTML, turning bare URLs into links.
+pub fn autolink_html(text: &str) -> String {
+    let mut html_output = String::new();
+    let mut rest = text;
+
+    while let Some(range) = find_url(rest) {
+        let url = &rest[range.clone()];
+        // Give `www.` links a scheme so they open externally like any other link
+        let dest_url = if url.starts_with("www.") {
+            format!("http://{url}")
+        } else {
+            url.to_string()
+        };
+
+        html::push_html(
+            &mut html_output,
+            [
+                Event::Text(rest[..range.start].into()),
+                Event::Start(Tag::Link {
+                    link_type: LinkType::Autolink,
+                    dest_url: dest_url.into(),
+                    title: "".into(),
+                    id: "".into(),
+                }),
+                Event::Text(url.into()),
+                Event::End(TagEnd::Link),
+            ]
+            .into_iter(),
+        );
+        rest = &rest[range.end..];
+    }
+
+    html::push_html(&mut html_output, std::iter::once(Event::Text(rest.into())));
+    html_output
+}
+
+/// Finds the first bare URL in the text, returning its byte range.
+fn find_url(text: &str) -> Option<Range<usize>> {
+    let mut search_from = 0;
+
+    while search_from < text.len() {
+        let (start, prefix) = URL_PREFIXES
+            .iter()
+            .filter_map(|prefix| {
+                text[search_from..]
+                    .find(prefix)
+                    .map(|index| (search_from + index, *prefix))
+            })
+            .min_by_key(|(index, _)| *index)?;
+
+        // A URL starts a word, or follows an opening parenthesis or emphasis delimiter
+        let at_boundary = text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '*' | '_' | '~'));
+
+        let candidate_end = text[start..]
+            .find(|c: char| c.is_whitespace() || c == '<')
+            .map_or(text.len(), |index| start + index);
+        let end = start + trim_url_end(&text[start..candidate_end]);
+
+        if at_boundary && end > start + prefix.len() {
+            return Some(start..end);
+        }
+        search_from = start + prefix.len();
+    }
+
+    None
+}
+
+/// Returns the length of a URL candidate without trailing punctuation, which usually belongs
+/// to the surrounding sentence. A closing parenthesis is kept while it balances an opening one.
+fn trim_url_end(candidate: &str) -> usize {
+    let mut end = candidate.len();
+
+    while let Some(last) = candidate[..end].chars().next_back() {
+        let trailing = match last {
+            '?' | '!' | '.' | ',' | ':' | ';' | '*' | '_' | '~' | '\'' | '"' => true,
+            ')' => {
+                let url = &candidate[..end];
+                url.matches(')').count() > url.matches('(').count()
+            }
+            _ => false,
+        };
+        if !trailing {
+            break;
+        }
+        end -= last.len_utf8();
+    }
+
+    end
+}
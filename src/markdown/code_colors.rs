@@ -0,0 +1,183 @@
+//! Foreground colors for highlighted code, fitted to the page's code block background.
+//!
+//! Syntect themes pick their colors for their own background, which the page replaces with
+//! `--pre-bg-color` or a chosen code background. Colors that end up too faint against it are
+//! pushed towards black or white.
+
+use std::sync::RwLock;
+
+use syntect::highlighting::Color;
+
+/// `--pre-bg-color` of the light stylesheet
+const LIGHT_PRE_BACKGROUND: Color = Color {
+    r: 0xf6,
+    g: 0xf8,
+    b: 0xfa,
+    a: 0xff,
+};
+
+/// `--pre-bg-color` of the dark stylesheet
+const DARK_PRE_BACKGROUND: Color = Color {
+    r: 0x16,
+    g: 0x1b,
+    b: 0x22,
+    a: 0xff,
+};
+
+/// Contrast ratio WCAG asks of body text
+const MIN_CONTRAST: f64 = 4.5;
+
+/// Code block background chosen with `--code-background`, replacing the theme's.
+static CODE_BACKGROUND: RwLock<Option<Color>> = RwLock::new(None);
+
+/// Sets the background code is drawn on, as a `#rgb` or `#rrggbb` color, or the theme's
+/// background when `None`. An invalid color is ignored.
+pub fn set_code_background(background: Option<&str>) {
+    let color = background.and_then(parse_hex_color);
+    *CODE_BACKGROUND.write().unwrap_or_else(|e| e.into_inner()) = color;
+}
+
+/// Returns the chosen code background as `#rrggbb`, if any.
+pub fn code_background() -> Option<String> {
+    let color = (*CODE_BACKGROUND.read().unwrap_or_else(|e| e.into_inner()))?;
+    Some(hex(color))
+}
+
+/// Parses a `#rgb` or `#rrggbb` color.
+pub fn parse_hex_color(value: &str) -> Option<Color> {
+    let digits = value.trim().strip_prefix('#')?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    let (r, g, b) = match digits.len() {
+        3 => {
+            let short = |index: usize| channel(&digits[index..index + 1]).map(|value| value * 17);
+            (short(0)?, short(1)?, short(2)?)
+        }
+        6 => (
+            channel(&digits[0..2])?,
+            channel(&digits[2..4])?,
+            channel(&digits[4..6])?,
+        ),
+        _ => return None,
+    };
+    Some(Color { r, g, b, a: 0xff })
+}
+
+/// Returns whether light text reads better than dark text on `background`.
+pub fn is_dark_color(background: Color) -> bool {
+    contrast(Color::WHITE, background) > contrast(Color::BLACK, background)
+}
+
+/// Returns the CSS color for a token, readable on the light or dark code block background.
+pub fn span_color(foreground: Color, dark: bool) -> String {
+    let chosen = *CODE_BACKGROUND.read().unwrap_or_else(|e| e.into_inner());
+    let background = match chosen {
+        Some(background) => background,
+        None if dark => DARK_PRE_BACKGROUND,
+        None => LIGHT_PRE_BACKGROUND,
+    };
+    // Translucent theme colors are drawn over the page background, not the theme's own
+    let mut color = blend(foreground, background, f64::from(foreground.a) / 255.0);
+
+    let target = if is_dark_color(background) {
+        Color::WHITE
+    } else {
+        Color::BLACK
+    };
+    let mut step = 0;
+    while contrast(color, background) < MIN_CONTRAST && step < 20 {
+        color = blend(target, color, 0.1);
+        step += 1;
+    }
+    hex(color)
+}
+
+/// Formats a color as `#rrggbb`.
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Mixes `amount` of `top` into `bottom`.
+fn blend(top: Color, bottom: Color, amount: f64) -> Color {
+    let mix = |top: u8, bottom: u8| {
+        (f64::from(top) * amount + f64::from(bottom) * (1.0 - amount)).round() as u8
+    };
+    Color {
+        r: mix(top.r, bottom.r),
+        g: mix(top.g, bottom.g),
+        b: mix(top.b, bottom.b),
+        a: 0xff,
+    }
+}
+
+/// WCAG contrast ratio between two opaque colors, from 1 to 21.
+fn contrast(a: Color, b: Color) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// WCAG relative luminance of an opaque color.
+fn luminance(color: Color) -> f64 {
+    let channel = |value: u8| {
+        let value = f64::from(value) / 255.0;
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(color.r) + 0.7152 * channel(color.g) + 0.0722 * channel(color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::RenderSettings;
+
+    fn color(value: &str) -> Color {
+        parse_hex_color(value).unwrap()
+    }
+
+    #[test]
+    fn parses_short_and_long_hex_colors() {
+        assert_eq!(parse_hex_color("#abc"), parse_hex_color("#aabbcc"));
+        assert_eq!(color(" #102030 ").g, 0x20);
+        assert_eq!(parse_hex_color("102030"), None);
+        assert_eq!(parse_hex_color("#12345"), None);
+        assert_eq!(parse_hex_color("#ggg"), None);
+    }
+
+    #[test]
+    fn readable_colors_are_kept() {
+        let _settings = RenderSettings::default().scoped();
+        assert_eq!(span_color(color("#000000"), false), "#000000");
+        assert_eq!(span_color(color("#ffffff"), true), "#ffffff");
+    }
+
+    #[test]
+    fn faint_colors_are_pushed_until_readable() {
+        let _settings = RenderSettings::default().scoped();
+        let light = color(&span_color(color("#e0e0e0"), false));
+        assert!(contrast(light, LIGHT_PRE_BACKGROUND) >= MIN_CONTRAST);
+        assert!(luminance(light) < luminance(color("#e0e0e0")));
+
+        let dark = color(&span_color(color("#202020"), true));
+        assert!(contrast(dark, DARK_PRE_BACKGROUND) >= MIN_CONTRAST);
+        assert!(luminance(dark) > luminance(color("#202020")));
+    }
+
+    #[test]
+    fn chosen_background_decides_the_direction() {
+        let _settings = RenderSettings {
+            code_background: Some("#000".into()),
+            ..RenderSettings::default()
+        }
+        .scoped();
+        // Dark text on a black background gets lighter even in light mode
+        let fitted = color(&span_color(color("#333333"), false));
+        assert!(contrast(fitted, Color::BLACK) >= MIN_CONTRAST);
+        assert_eq!(code_background().as_deref(), Some("#000000"));
+    }
+}
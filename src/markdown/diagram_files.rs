@@ -0,0 +1,36 @@
+//! Diagram files such as `flow.mmd`, opened as a document holding just that diagram.
+
+use std::path::Path;
+
+/// File extensions of diagram sources, with the fenced code block language that renders them
+pub const DIAGRAM_EXTENSIONS: [(&str, &str); 6] = [
+    ("mmd", "mermaid"),
+    ("mermaid", "mermaid"),
+    ("dot", "dot"),
+    ("gv", "dot"),
+    ("puml", "plantuml"),
+    ("plantuml", "plantuml"),
+];
+
+/// Returns the code block language of a diagram file, or `None` for any other file, which is
+/// read as markdown.
+pub fn diagram_language(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    DIAGRAM_EXTENSIONS
+        .iter()
+        .find(|(diagram, _)| extension.eq_ignore_ascii_case(diagram))
+        .map(|(_, language)| *language)
+}
+
+/// Returns the markdown for a file's contents: a diagram file becomes a single fenced code block
+/// in its diagram language, and anything else is returned unchanged.
+pub fn file_markdown(path: &str, source: String) -> String {
+    let Some(language) = diagram_language(path) else {
+        return source;
+    };
+    // The fence must be longer than any run of backticks inside the diagram
+    let longest_run = source.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    let newline = if source.ends_with('\n') { "" } else { "\n" };
+    format!("{fence}{language}\n{source}{newline}{fence}\n")
+}
@@ -0,0 +1,143 @@
+//! Which markdown extensions are parsed, chosen at startup from command-line flags.
+
+use pulldown_cmark::Options;
+use std::sync::RwLock;
+
+/// Markdown extensions enabled on top of CommonMark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    /// Pipe tables
+    pub tables: bool,
+    /// `[^note]` footnotes
+    pub footnotes: bool,
+    /// `~~strikethrough~~`
+    pub strikethrough: bool,
+    /// `- [ ]` task lists
+    pub tasklists: bool,
+    /// `==highlighted==` text
+    pub highlight: bool,
+    /// Pandoc-style `H~2~O` subscripts and `x^2^` superscripts. Off by default, since a single
+    /// `~` otherwise strikes text through.
+    pub sub_sup: bool,
+    /// `$inline$` and `$$display$$` math, rendered with KaTeX. Off by default, since prose
+    /// often uses dollar signs for prices.
+    pub math: bool,
+    /// Numbered `{#fig:label}` figures and `{#eq:label}` equations, with `@fig:label`
+    /// references. Off by default, since `@` also starts handles and addresses.
+    pub crossref: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            highlight: true,
+            sub_sup: false,
+            math: false,
+            crossref: false,
+        }
+    }
+}
+
+impl Dialect {
+    /// Returns the parser options for this dialect.
+    pub fn options(&self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_MATH, self.math);
+        // The parser takes `~sub~` and `^sup^` between words; `marks` handles them within words
+        options.set(Options::ENABLE_SUBSCRIPT, self.sub_sup);
+        options.set(Options::ENABLE_SUPERSCRIPT, self.sub_sup);
+        options.insert(Options::ENABLE_GFM); // `> [!NOTE]` style alerts
+        options.insert(Options::ENABLE_DEFINITION_LIST); // `Term` followed by `: Definition`
+        options.insert(Options::ENABLE_WIKILINKS); // `[[Page Name]]` and `[[Page Name|alias]]`
+        options
+    }
+}
+
+/// Dialect given on the command line; the default dialect applies until it is set.
+static DIALECT: RwLock<Option<Dialect>> = RwLock::new(None);
+
+/// Sets the dialect used for every document rendered from now on, streamed or not.
+pub fn set_dialect(dialect: Dialect) {
+    *DIALECT.write().unwrap_or_else(|e| e.into_inner()) = Some(dialect);
+}
+
+/// Returns the dialect documents are rendered with.
+pub fn dialect() -> Dialect {
+    DIALECT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{RenderSettings, parse_markdown};
+
+    fn render(dialect: Dialect, markdown: &str) -> String {
+        let _settings = RenderSettings {
+            dialect,
+            ..RenderSettings::default()
+        }
+        .scoped();
+        parse_markdown(markdown)
+    }
+
+    #[test]
+    fn no_tables_leaves_pipes_as_text() {
+        let table = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+        assert!(render(Dialect::default(), table).contains("<table>"));
+
+        let no_tables = Dialect {
+            tables: false,
+            ..Dialect::default()
+        };
+        let html = render(no_tables, table);
+        assert!(!html.contains("<table>"), "{html}");
+        assert!(html.contains("| a | b |"), "{html}");
+    }
+
+    #[test]
+    fn math_is_parsed_only_when_enabled() {
+        let markdown = "Costs $5 and $x^2$ here";
+        let html = render(Dialect::default(), markdown);
+        assert!(!html.contains("latex-math"), "{html}");
+
+        let math = Dialect {
+            math: true,
+            ..Dialect::default()
+        };
+        let html = render(math, markdown);
+        assert!(html.contains("latex-math"), "{html}");
+    }
+
+    #[test]
+    fn no_strikethrough_leaves_tildes() {
+        let no_strikethrough = Dialect {
+            strikethrough: false,
+            ..Dialect::default()
+        };
+        assert!(render(Dialect::default(), "~~gone~~").contains("<del>gone</del>"));
+        assert!(render(no_strikethrough, "~~gone~~").contains("~~gone~~"));
+    }
+
+    #[test]
+    fn no_tasklists_leaves_brackets() {
+        let no_tasklists = Dialect {
+            tasklists: false,
+            ..Dialect::default()
+        };
+        assert!(render(Dialect::default(), "- [ ] todo").contains("task-checkbox"));
+        let html = render(no_tasklists, "- [ ] todo");
+        assert!(!html.contains("task-checkbox"), "{html}");
+        assert!(html.contains("[ ] todo"), "{html}");
+    }
+}
@@ -0,0 +1,84 @@
+//! GitHub-style emoji shortcodes (`:rocket:` → 🚀).
+
+use std::borrow::Cow;
+
+/// Returns whether a character can appear in a shortcode name.
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '-')
+}
+
+/// Replaces known `:name:` shortcodes with their emoji. Unknown names are left as written.
+pub fn expand_shortcodes(text: &str) -> Cow<'_, str> {
+    if !text.contains(':') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(':') {
+        expanded.push_str(&rest[..start]);
+        let after_colon = &rest[start + 1..];
+
+        let name_len = after_colon
+            .find(|c: char| !is_shortcode_char(c))
+            .unwrap_or(after_colon.len());
+        let emoji = (name_len > 0 && after_colon[name_len..].starts_with(':'))
+            .then(|| emojis::get_by_shortcode(&after_colon[..name_len]))
+            .flatten();
+
+        match emoji {
+            Some(emoji) => {
+                expanded.push_str(emoji.as_str());
+                rest = &after_colon[name_len + 1..];
+            }
+            None => {
+                // The closing colon may open the next shortcode, so resume right after this one
+                expanded.push(':');
+                rest = after_colon;
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    Cow::Owned(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{RenderSettings, parse_markdown};
+
+    #[test]
+    fn expands_known_shortcodes() {
+        assert_eq!(expand_shortcodes("Ship it :rocket::+1:"), "Ship it 🚀👍");
+    }
+
+    #[test]
+    fn leaves_unknown_shortcodes_and_times_alone() {
+        assert_eq!(expand_shortcodes(":not_an_emoji:"), ":not_an_emoji:");
+        assert_eq!(expand_shortcodes("at 10:30:45"), "at 10:30:45");
+        assert_eq!(expand_shortcodes(":nope::tada:"), ":nope:🎉");
+    }
+
+    #[test]
+    fn code_is_not_expanded() {
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown(":tada: `:tada:`\n\n```\n:tada:\n```\n");
+        // Only the shortcode in text is expanded
+        assert_eq!(html.matches('🎉').count(), 1, "{html}");
+        assert!(html.contains("<code>:tada:</code>"), "{html}");
+    }
+
+    #[test]
+    fn autolinks_are_not_expanded() {
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown("<https://example.com/:tada:> and <me:tada:@example.com> :tada:");
+        assert!(
+            html.contains(r#"<a href="https://example.com/:tada:">https://example.com/:tada:</a>"#),
+            "{html}"
+        );
+        assert!(html.contains(">me:tada:@example.com</a>"), "{html}");
+        assert_eq!(html.matches('🎉').count(), 1, "{html}");
+    }
+}
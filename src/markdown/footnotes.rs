@@ -0,0 +1,158 @@
+//! Footnote references and the numbered footnote section rendered at the end of a document.
+
+use std::collections::HashMap;
+
+/// Collects footnotes while a document renders, numbering them in order of first use.
+#[derive(Debug, Default)]
+pub struct Footnotes {
+    numbers: HashMap<String, usize>,
+    /// How many times each footnote has been referenced so far
+    reference_counts: HashMap<String, usize>,
+    /// Rendered definitions as `(label, body HTML)`
+    definitions: Vec<(String, String)>,
+    /// Label and output offset of the definition being rendered
+    open_definition: Option<(String, usize)>,
+}
+
+impl Footnotes {
+    fn number(&mut self, label: &str) -> usize {
+        let next = self.numbers.len() + 1;
+        *self.numbers.entry(label.to_string()).or_insert(next)
+    }
+
+    /// Returns the superscript link for a reference. Each reference gets its own anchor, so the
+    /// footnote can link back to every place that cites it.
+    pub fn reference_html(&mut self, label: &str) -> String {
+        let number = self.number(label);
+        let count = self.reference_counts.entry(label.to_string()).or_insert(0);
+        *count += 1;
+        let id = element_id(label, number);
+        format!(
+            "<sup class=\"footnote-ref\"><a href=\"#fn-{id}\" id=\"{}\">{number}</a></sup>",
+            reference_id(&id, *count)
+        )
+    }
+
+    /// Marks the start of a definition, whose body is rendered from `output_len` onwards.
+    pub fn start_definition(&mut self, label: &str, output_len: usize) {
+        self.number(label);
+        self.open_definition = Some((label.to_string(), output_len));
+    }
+
+    /// Moves the body of the finished definition out of the document into the footnote section.
+    pub fn end_definition(&mut self, html_output: &mut String) {
+        if let Some((label, start)) = self.open_definition.take() {
+            let body = html_output.split_off(start);
+            self.definitions.push((label, body));
+        }
+    }
+
+    /// Renders the collected definitions as a `<section class="footnotes">`, or nothing when the
+    /// document has none.
+    pub fn section_html(&self) -> String {
+        if self.definitions.is_empty() {
+            return String::new();
+        }
+
+        let mut definitions: Vec<_> = self
+            .definitions
+            .iter()
+            .map(|(label, body)| (self.numbers[label], label, body))
+            .collect();
+        definitions.sort_by_key(|(number, ..)| *number);
+
+        let mut html = String::from("<section class=\"footnotes\">\n<hr>\n<ol>\n");
+        for (number, label, body) in definitions {
+            let id = element_id(label, number);
+            let references = self.reference_counts.get(label).copied().unwrap_or(0);
+            let backrefs: Vec<String> = (1..=references)
+                .map(|count| {
+                    let marker = if count == 1 {
+                        String::new()
+                    } else {
+                        format!("<sup>{count}</sup>")
+                    };
+                    format!(
+                        "<a href=\"#{}\" class=\"footnote-backref\" title=\"Back to reference\">↩{marker}</a>",
+                        reference_id(&id, count)
+                    )
+                })
+                .collect();
+            let backrefs = backrefs.join(" ");
+
+            // Keep the back-references on the last line of the footnote text
+            let body = body.trim_end();
+            let body = match body.strip_suffix("</p>") {
+                Some(text) if !backrefs.is_empty() => format!("{text} {backrefs}</p>"),
+                _ if !backrefs.is_empty() => format!("{body}\n<p>{backrefs}</p>"),
+                _ => body.to_string(),
+            };
+            html.push_str(&format!(
+                "<li id=\"fn-{id}\" value=\"{number}\">\n{body}\n</li>\n"
+            ));
+        }
+        html.push_str("</ol>\n</section>\n");
+        html
+    }
+}
+
+/// Returns the anchor name for a footnote label: `[^My Note]` becomes `my-note`. Labels
+/// without letters or digits fall back to the footnote number.
+fn element_id(label: &str, number: usize) -> String {
+    let id = label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    if id.is_empty() {
+        number.to_string()
+    } else {
+        id
+    }
+}
+
+/// Returns the anchor of the `count`th reference to a footnote.
+fn reference_id(id: &str, count: usize) -> String {
+    if count == 1 {
+        format!("fnref-{id}")
+    } else {
+        format!("fnref-{id}-{count}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{RenderSettings, parse_markdown};
+
+    #[test]
+    fn footnotes_are_numbered_by_first_reference_with_back_references() {
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown(
+            "Second[^b], first[^a] and again[^b].\n\n[^a]: Note A.\n\n[^b]: Note B.\n",
+        );
+
+        assert!(
+            html.contains(r##"<a href="#fn-b" id="fnref-b">1</a>"##),
+            "{html}"
+        );
+        assert!(
+            html.contains(r##"<a href="#fn-a" id="fnref-a">2</a>"##),
+            "{html}"
+        );
+        assert!(
+            html.contains(r##"<a href="#fn-b" id="fnref-b-2">1</a>"##),
+            "{html}"
+        );
+
+        // Definitions move to a section at the end, in number order
+        let section = &html[html.find(r#"<section class="footnotes">"#).unwrap()..];
+        let note_b = section.find(r#"<li id="fn-b" value="1">"#).unwrap();
+        let note_a = section.find(r#"<li id="fn-a" value="2">"#).unwrap();
+        assert!(note_b < note_a);
+        assert!(section.contains(r##"href="#fnref-b" class="footnote-backref""##));
+        assert!(section.contains(r##"href="#fnref-b-2" class="footnote-backref""##));
+        assert!(section.contains(r##"href="#fnref-a" class="footnote-backref""##));
+        assert!(!html[..html.len() - section.len()].contains("Note A"));
+    }
+}
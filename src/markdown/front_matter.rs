@@ -0,0 +1,98 @@
+//! YAML front matter detection for documents that start with a `---` block.
+
+/// Metadata parsed from a leading YAML front matter block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+}
+
+/// Returns whether a line opens (or closes) a front matter block.
+pub fn is_fence(line: &str) -> bool {
+    line.trim_end() == "---"
+}
+
+/// Returns whether a line closes a front matter block (`---` or YAML's `...`).
+pub fn is_closing_fence(line: &str) -> bool {
+    is_fence(line) || line.trim_end() == "..."
+}
+
+/// Splits a leading front matter block from the markdown body.
+///
+/// Returns `None` when the document has no front matter or the block is not a valid YAML
+/// mapping, in which case the input should be rendered untouched.
+pub fn split_front_matter(input: &str) -> Option<(FrontMatter, &str)> {
+    let mut lines = input.split_inclusive('\n');
+    let opening = lines.next()?;
+    if !is_fence(opening) {
+        return None;
+    }
+
+    let yaml_start = opening.len();
+    let mut offset = yaml_start;
+    for line in lines {
+        if is_closing_fence(line) {
+            let yaml = &input[yaml_start..offset];
+            let body = &input[offset + line.len()..];
+            return parse_yaml(yaml).map(|front_matter| (front_matter, body));
+        }
+        offset += line.len();
+    }
+
+    None // Unterminated block
+}
+
+fn parse_yaml(yaml: &str) -> Option<FrontMatter> {
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml).ok()?;
+    let mapping = value.as_mapping()?;
+
+    let field = |key: &str| -> Option<String> {
+        match mapping.get(key)? {
+            serde_yaml::Value::String(s) => Some(s.clone()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            serde_yaml::Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    };
+
+    Some(FrontMatter {
+        title: field("title"),
+        author: field("author"),
+        date: field("date"),
+    })
+}
+
+impl FrontMatter {
+    /// Renders the metadata as a compact header shown above the document body.
+    pub fn to_html(&self) -> String {
+        let parts: Vec<String> = [&self.title, &self.author, &self.date]
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                value
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            })
+            .collect();
+
+        if parts.is_empty() {
+            return String::new();
+        }
+
+        format!(
+            "<div class=\"front-matter\">{}</div>\n",
+            parts
+                .iter()
+                .map(|part| format!("<span>{part}</span>"))
+                .collect::<Vec<_>>()
+                .join(" · ")
+        )
+    }
+}
+
+/// Returns the `title` from a document's front matter, if any.
+pub fn front_matter_title(input: &str) -> Option<String> {
+    split_front_matter(input).and_then(|(front_matter, _)| front_matter.title)
+}
@@ -0,0 +1,231 @@
+//! Inline marks pulldown-cmark leaves as text: `==highlighted==` text, rendered as `<mark>`,
+//! and Pandoc-style `H~2~O` subscripts and `x^2^` superscripts.
+//!
+//! A highlight opens with `==` followed by a non-space character and closes at the next `==`
+//! on the same line that follows one, so `a == b` stays as written. Runs of three or more `=`
+//! never open or close a highlight, and `\==` is a literal `==`. A highlight lies within one
+//! run of text, so it can't contain emphasis, links or inline code.
+//!
+//! A subscript or superscript is the text between two `~` or two `^`, which may sit inside a
+//! word but can't contain spaces; `~~` is left to strikethrough.
+
+use std::ops::Range;
+
+use pulldown_cmark::{CowStr, Event};
+
+/// Splits text into plain and highlighted parts, in order, without the `==` markers. Each part
+/// is paired with whether it is highlighted.
+pub fn highlight_parts(text: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let mut plain_start = 0;
+    let mut search = 0;
+
+    while let Some(found) = text[search..].find("==") {
+        let open = search + found;
+        search = open + 1;
+        if !is_marker(text, open) {
+            continue;
+        }
+        let content_start = open + 2;
+        if !text[content_start..].starts_with(|c: char| !c.is_whitespace()) {
+            continue;
+        }
+        let Some(close) = find_close(text, content_start) else {
+            continue;
+        };
+
+        if plain_start < open {
+            parts.push((false, &text[plain_start..open]));
+        }
+        parts.push((true, &text[content_start..close]));
+        plain_start = close + 2;
+        search = plain_start;
+    }
+
+    if plain_start < text.len() {
+        parts.push((false, &text[plain_start..]));
+    }
+    parts
+}
+
+/// Returns the offset of the `==` closing a highlight whose text starts at `start`, if it
+/// closes on the same line.
+fn find_close(text: &str, start: usize) -> Option<usize> {
+    let line_end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |end| start + end);
+    let mut search = start + 1;
+    while search < line_end {
+        let close = search + text[search..line_end].find("==")?;
+        let after_text = !text[..close].ends_with(char::is_whitespace);
+        if is_marker(text, close) && after_text {
+            return Some(close);
+        }
+        search = close + 1;
+    }
+    None
+}
+
+/// Returns whether the `==` at `index` stands alone rather than being part of a longer run of
+/// `=`.
+fn is_marker(text: &str, index: usize) -> bool {
+    !text[..index].ends_with('=') && !text[index + 2..].starts_with('=')
+}
+
+/// Splits text into plain parts and `~subscript~` or `^superscript^` parts, in order, without
+/// the markers. Each part is paired with its tag, `sub` or `sup`, if it has one.
+pub fn script_parts(text: &str) -> Vec<(Option<&'static str>, &str)> {
+    let mut parts = Vec::new();
+    let mut plain_start = 0;
+    let mut search = 0;
+
+    while let Some(found) = text[search..].find(['~', '^']) {
+        let open = search + found;
+        search = open + 1;
+        let marker = if text[open..].starts_with('~') {
+            '~'
+        } else {
+            '^'
+        };
+        if text[..open].ends_with(marker) {
+            continue;
+        }
+        let content_start = open + 1;
+        let content_len = text[content_start..]
+            .find(|c: char| c == marker || c.is_whitespace())
+            .unwrap_or(text.len() - content_start);
+        let close = content_start + content_len;
+        let closed = text[close..].starts_with(marker) && !text[close + 1..].starts_with(marker);
+        if content_len == 0 || !closed {
+            continue;
+        }
+
+        if plain_start < open {
+            parts.push((None, &text[plain_start..open]));
+        }
+        let tag = if marker == '~' { "sub" } else { "sup" };
+        parts.push((Some(tag), &text[content_start..close]));
+        plain_start = close + 1;
+        search = plain_start;
+    }
+
+    if plain_start < text.len() {
+        parts.push((None, &text[plain_start..]));
+    }
+    parts
+}
+
+/// Passes parser events through, splitting each backslash-escaped `markers` character off as
+/// raw HTML so that it can't open or close a mark once adjacent text events are merged.
+pub fn separate_escaped<'a>(
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+    markdown_input: &'a str,
+    markers: Vec<char>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> {
+    events.flat_map(move |(event, range)| match event {
+        Event::Text(text)
+            if text.starts_with(markers.as_slice()) && is_escaped(markdown_input, range.start) =>
+        {
+            let escaped = CowStr::from(text[..1].to_string());
+            let escaped = (Event::InlineHtml(escaped), range.start..range.start + 1);
+            let rest = (text.len() > 1).then(|| {
+                let rest = CowStr::from(text[1..].to_string());
+                (Event::Text(rest), range.start + 1..range.end)
+            });
+            vec![escaped].into_iter().chain(rest)
+        }
+        event => vec![(event, range)].into_iter().chain(None),
+    })
+}
+
+/// Returns whether the character at `index` is preceded by an odd number of backslashes.
+fn is_escaped(markdown_input: &str, index: usize) -> bool {
+    let backslashes = markdown_input.as_bytes()[..index]
+        .iter()
+        .rev()
+        .take_while(|byte| **byte == b'\\')
+        .count();
+    backslashes % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{Dialect, RenderSettings, parse_markdown};
+
+    fn render(dialect: Dialect, markdown: &str) -> String {
+        let _settings = RenderSettings {
+            dialect,
+            ..RenderSettings::default()
+        }
+        .scoped();
+        parse_markdown(markdown)
+    }
+
+    #[test]
+    fn highlights_marked_text() {
+        assert_eq!(
+            highlight_parts("a ==marked== b"),
+            [(false, "a "), (true, "marked"), (false, " b")]
+        );
+        let html = render(Dialect::default(), "Some ==marked text== here");
+        assert!(
+            html.contains("Some <mark>marked text</mark> here"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn code_is_not_highlighted() {
+        let html = render(Dialect::default(), "`==code==`\n\n```\n==block==\n```\n");
+        assert!(!html.contains("<mark>"), "{html}");
+        assert!(html.contains("<code>==code==</code>"), "{html}");
+    }
+
+    #[test]
+    fn unbalanced_markers_stay_as_written() {
+        assert_eq!(highlight_parts("==open"), [(false, "==open")]);
+        assert_eq!(highlight_parts("a == b"), [(false, "a == b")]);
+        assert_eq!(highlight_parts("a ==b ==c"), [(false, "a ==b ==c")]);
+        assert_eq!(highlight_parts("===x==="), [(false, "===x===")]);
+        // A highlight doesn't run on to the next line
+        assert_eq!(highlight_parts("==a\nb=="), [(false, "==a\nb==")]);
+
+        let html = render(Dialect::default(), r"==open and \==escaped==");
+        assert!(!html.contains("<mark>"), "{html}");
+    }
+
+    /// The dialect of `--sub-sup`
+    const SUB_SUP: Dialect = Dialect {
+        tables: true,
+        footnotes: true,
+        strikethrough: true,
+        tasklists: true,
+        highlight: true,
+        sub_sup: true,
+        math: false,
+        crossref: false,
+    };
+
+    #[test]
+    fn renders_subscripts_and_superscripts() {
+        assert_eq!(
+            script_parts("H~2~O"),
+            [(None, "H"), (Some("sub"), "2"), (None, "O")]
+        );
+        let html = render(SUB_SUP, "H~2~O and x^2^");
+        assert!(html.contains("H<sub>2</sub>O and x<sup>2</sup>"), "{html}");
+
+        // Off by default
+        let html = render(Dialect::default(), "H~2~O and x^2^");
+        assert!(html.contains("H~2~O and x^2^"), "{html}");
+    }
+
+    #[test]
+    fn strikethrough_is_unaffected_by_sub_sup() {
+        assert_eq!(script_parts("~~struck~~"), [(None, "~~struck~~")]);
+        let html = render(SUB_SUP, "~~struck~~ and H~2~O");
+        assert!(html.contains("<del>struck</del>"), "{html}");
+        assert!(html.contains("H<sub>2</sub>O"), "{html}");
+    }
+}
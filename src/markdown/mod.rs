@@ -1,5 +1,38 @@
 //! Markdown module: provides parsing utilities for markdown to HTML.
 
+mod anchors;
+mod autolink;
+mod code_colors;
+mod diagram_files;
+mod dialect;
+mod emoji;
+mod footnotes;
+pub mod front_matter;
+mod marks;
+mod outline;
 mod parser;
+mod sanitize;
+mod settings;
+mod tables;
+mod tasks;
+mod wikilink;
 
-pub use parser::{highlight_markdown_with_theme, parse_markdown, parse_markdown_with_theme};
+pub use anchors::slug;
+pub use code_colors::{is_dark_color, parse_hex_color, set_code_background};
+pub use diagram_files::{DIAGRAM_EXTENSIONS, diagram_language, file_markdown};
+pub use dialect::{Dialect, dialect, set_dialect};
+pub use front_matter::front_matter_title;
+pub use outline::{
+    DocumentOutline, OutlineCodeBlock, OutlineHeading, OutlineLink, document_outline,
+};
+pub use parser::{
+    count_words, first_heading, highlight_markdown_with_theme, load_custom_theme, parse_markdown,
+    parse_markdown_fragment, parse_markdown_with_theme, set_autolinks,
+    set_client_side_highlighting, set_code_fold_lines, set_emoji_shortcodes,
+    set_inline_code_language, set_smart_punctuation, set_syntax_theme, set_system_dark_mode,
+    syntax_theme_names, system_dark_mode,
+};
+pub use sanitize::{sanitize_html_enabled, set_sanitize_html};
+pub use settings::{RenderSettings, ScopedSettings};
+pub use tasks::set_task_checked;
+pub use wikilink::set_wiki_base;
@@ -0,0 +1,151 @@
+//! A document's structure without its rendering, for `--dump-json` and tools that index
+//! markdown: headings with the anchors the page gives them, code blocks and links.
+
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Parser, Tag, TagEnd};
+use serde::Serialize;
+
+use super::anchors::HeadingAnchors;
+use super::front_matter::{front_matter_title, split_front_matter};
+use super::parser::{count_words, document_options, first_heading, info_string_language};
+use super::wikilink::wiki_href;
+
+/// Outline of a markdown document, serialized as JSON by `--dump-json`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DocumentOutline {
+    /// Front matter `title`, otherwise the first level-1 heading
+    pub title: Option<String>,
+    pub headings: Vec<OutlineHeading>,
+    pub code_blocks: Vec<OutlineCodeBlock>,
+    pub links: Vec<OutlineLink>,
+    /// Words of prose, as in the stats overlay
+    pub word_count: usize,
+}
+
+/// A heading and the anchor that `#slug` links reach it by.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineHeading {
+    /// 1 to 6
+    pub level: u8,
+    pub text: String,
+    pub slug: String,
+    /// 1-based source line, counting any front matter
+    pub line: usize,
+}
+
+/// A fenced or indented code block.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineCodeBlock {
+    /// Language of a fenced block's info string, e.g. `rust` for `rust,ignore`
+    pub language: Option<String>,
+    /// 1-based source line of the block's first line, counting any front matter
+    pub line: usize,
+    /// Lines of code in the block
+    pub lines: usize,
+}
+
+/// A link's destination and text; `[[wiki links]]` are given as the URL they open.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineLink {
+    pub url: String,
+    pub text: String,
+}
+
+/// Returns the outline of a markdown document, parsed with the same dialect it is rendered
+/// with, so heading slugs match the page's anchors.
+pub fn document_outline(markdown_input: &str) -> DocumentOutline {
+    let body = split_front_matter(markdown_input).map_or(markdown_input, |(_, body)| body);
+    // Lines before the body belong to the front matter
+    let first_line = markdown_input[..markdown_input.len() - body.len()]
+        .matches('\n')
+        .count();
+    let line_at = |offset: usize| first_line + body[..offset].matches('\n').count() + 1;
+
+    let mut outline = DocumentOutline {
+        title: front_matter_title(markdown_input).or_else(|| first_heading(markdown_input)),
+        word_count: count_words(markdown_input),
+        ..Default::default()
+    };
+    let mut anchors = HeadingAnchors::default();
+    // Heading, code block and link being read; a link may be inside a heading
+    let mut heading: Option<(u8, usize, String)> = None;
+    let mut code_block: Option<OutlineCodeBlock> = None;
+    let mut link: Option<OutlineLink> = None;
+
+    for (event, range) in Parser::new_ext(body, document_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((level as u8, line_at(range.start), String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, line, text)) = heading.take() {
+                    let slug = anchors.anchor(&text);
+                    outline.headings.push(OutlineHeading {
+                        level,
+                        text: text.trim().to_string(),
+                        slug,
+                        line,
+                    });
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let (language, line) = match kind {
+                    // The code starts on the line after the opening fence
+                    CodeBlockKind::Fenced(info) => (
+                        Some(info_string_language(&info).to_string())
+                            .filter(|language| !language.is_empty()),
+                        line_at(range.start) + 1,
+                    ),
+                    CodeBlockKind::Indented => (None, line_at(range.start)),
+                };
+                code_block = Some(OutlineCodeBlock {
+                    language,
+                    line,
+                    lines: 0,
+                });
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                outline.code_blocks.extend(code_block.take());
+            }
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            }) => {
+                let url = match link_type {
+                    LinkType::WikiLink { .. } => wiki_href(&dest_url),
+                    _ => dest_url.to_string(),
+                };
+                link = Some(OutlineLink {
+                    url,
+                    text: String::new(),
+                });
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some(mut link) = link.take() {
+                    link.text = link.text.trim().to_string();
+                    outline.links.push(link);
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(code_block) = &mut code_block {
+                    code_block.lines += text.lines().count();
+                    continue;
+                }
+                if let Some((_, _, heading_text)) = &mut heading {
+                    heading_text.push_str(&text);
+                }
+                if let Some(link) = &mut link {
+                    link.text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(link) = &mut link {
+                    link.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    outline
+}
@@ -1,15 +1,213 @@
-use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd, html};
+use log::{info, warn};
+use pulldown_cmark::{
+    BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag,
+    TagEnd, TextMergeWithOffset, html,
+};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 use syntect::easy::HighlightLines;
-use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 use syntect::util::LinesWithEndings;
 
+use super::anchors::HeadingAnchors;
+use super::autolink::autolink_html;
+use super::code_colors;
+use super::dialect::dialect;
+use super::emoji::expand_shortcodes;
+use super::footnotes::Footnotes;
+use super::front_matter::split_front_matter;
+use super::marks::{highlight_parts, script_parts, separate_escaped};
+use super::sanitize::{HtmlSanitizer, sanitize_html_enabled};
+use super::tables::TableCells;
+use super::wikilink::wiki_href;
 use crate::gui::types::ThemeMode;
 use crate::plugins::{PluginContext, manager::PLUGIN_MANAGER};
 
 const LIGHT_THEME: &str = "InspiredGitHub";
 const DARK_THEME: &str = "base16-ocean.dark";
 
+/// User-supplied syntax theme that overrides the built-in light/dark themes.
+static CUSTOM_THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Highlighted code blocks are cached, since streaming re-renders the whole document on
+/// every append. Past this many entries the cache starts over.
+const MAX_CACHED_CODE_BLOCKS: usize = 512;
+
+/// Highlighted code block HTML keyed by a hash of `(language, code)`, for one syntax theme.
+#[derive(Default)]
+struct CodeBlockCache {
+    theme_name: String,
+    blocks: HashMap<u64, String>,
+}
+
+static CODE_BLOCK_CACHE: OnceLock<Mutex<CodeBlockCache>> = OnceLock::new();
+
+/// Whether code blocks are left for highlight.js in the page instead of syntect.
+static CLIENT_SIDE_HIGHLIGHTING: AtomicBool = AtomicBool::new(false);
+
+/// Switches code blocks between server-side (syntect) and client-side (highlight.js) highlighting.
+pub fn set_client_side_highlighting(enabled: bool) {
+    CLIENT_SIDE_HIGHLIGHTING.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether code blocks are left for highlight.js.
+pub(super) fn client_side_highlighting() -> bool {
+    CLIENT_SIDE_HIGHLIGHTING.load(Ordering::Relaxed)
+}
+
+/// Highlighted code blocks longer than this many lines start folded; 0 never folds.
+static CODE_FOLD_LINES: AtomicUsize = AtomicUsize::new(0);
+
+/// Folds highlighted code blocks after `lines` lines behind a "Show more" button. 0 turns
+/// folding off.
+pub fn set_code_fold_lines(lines: usize) {
+    CODE_FOLD_LINES.store(lines, Ordering::Relaxed);
+}
+
+/// Returns the line count past which code blocks start folded, 0 if they never do.
+pub(super) fn code_fold_lines() -> usize {
+    CODE_FOLD_LINES.load(Ordering::Relaxed)
+}
+
+/// Whether the system appearance is dark, which decides the syntax theme for `ThemeMode::System`.
+static SYSTEM_DARK_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Records the current system appearance for resolving `ThemeMode::System`.
+pub fn set_system_dark_mode(dark: bool) {
+    SYSTEM_DARK_MODE.store(dark, Ordering::Relaxed);
+}
+
+/// Returns the system appearance last recorded with [`set_system_dark_mode`].
+pub fn system_dark_mode() -> bool {
+    SYSTEM_DARK_MODE.load(Ordering::Relaxed)
+}
+
+/// Whether straight quotes and dashes are converted to typographic ones.
+static SMART_PUNCTUATION: AtomicBool = AtomicBool::new(false);
+
+/// Whether bare URLs in text become links.
+static AUTOLINKS: AtomicBool = AtomicBool::new(true);
+
+/// Whether `:shortcode:` emoji are expanded.
+static EMOJI_SHORTCODES: AtomicBool = AtomicBool::new(true);
+
+/// Turns expansion of GitHub emoji shortcodes such as `:rocket:` on or off.
+pub fn set_emoji_shortcodes(enabled: bool) {
+    EMOJI_SHORTCODES.store(enabled, Ordering::Relaxed);
+}
+
+/// Turns conversion of `"quotes"`, `--` and `...` to typographic punctuation on or off.
+pub fn set_smart_punctuation(enabled: bool) {
+    SMART_PUNCTUATION.store(enabled, Ordering::Relaxed);
+}
+
+/// Turns linking of bare `https://` and `www.` URLs on or off.
+pub fn set_autolinks(enabled: bool) {
+    AUTOLINKS.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether punctuation is made typographic.
+pub(super) fn smart_punctuation() -> bool {
+    SMART_PUNCTUATION.load(Ordering::Relaxed)
+}
+
+/// Returns whether bare URLs are linked.
+pub(super) fn autolinks() -> bool {
+    AUTOLINKS.load(Ordering::Relaxed)
+}
+
+/// Returns whether emoji shortcodes are expanded.
+pub(super) fn emoji_shortcodes() -> bool {
+    EMOJI_SHORTCODES.load(Ordering::Relaxed)
+}
+
+/// Loads a `.tmTheme` file to use for all syntax highlighting.
+///
+/// If the file cannot be read or parsed, a warning is logged and the built-in themes stay in use.
+pub fn load_custom_theme(path: &str) {
+    match ThemeSet::get_theme(path) {
+        Ok(theme) => {
+            info!("Loaded custom syntax theme from {path}");
+            if CUSTOM_THEME.set(theme).is_err() {
+                warn!("Custom syntax theme already loaded, ignoring {path}");
+            }
+        }
+        Err(e) => warn!("Failed to load syntax theme {path}: {e}. Using built-in theme."),
+    }
+}
+
+/// Built-in theme chosen from the View menu, used instead of the light/dark defaults.
+static SYNTAX_THEME: Mutex<Option<String>> = Mutex::new(None);
+
+/// Highlights code with the named built-in theme, or with the light/dark defaults for `None`.
+pub fn set_syntax_theme(name: Option<String>) {
+    *SYNTAX_THEME.lock().unwrap_or_else(|e| e.into_inner()) = name;
+}
+
+/// Returns the built-in theme chosen with [`set_syntax_theme`], if any.
+pub(super) fn syntax_theme() -> Option<String> {
+    SYNTAX_THEME
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Language inline code is highlighted as, from `--inline-lang`; `None` leaves it plain.
+static INLINE_CODE_LANGUAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Highlights every `inline code` span as `language`, e.g. `rust`, or leaves them plain for
+/// `None`.
+pub fn set_inline_code_language(language: Option<String>) {
+    *INLINE_CODE_LANGUAGE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = language;
+}
+
+/// Returns the language inline code is highlighted as, if any.
+pub(super) fn inline_code_language() -> Option<String> {
+    INLINE_CODE_LANGUAGE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Returns the names of the built-in syntax themes, sorted.
+pub fn syntax_theme_names() -> Vec<String> {
+    ThemeSet::load_defaults().themes.into_keys().collect()
+}
+
+/// Returns whether the page is dark in this mode, following the system for `ThemeMode::System`.
+fn is_dark(theme_mode: &ThemeMode) -> bool {
+    match theme_mode {
+        ThemeMode::Light => false,
+        ThemeMode::Dark => true,
+        ThemeMode::System => system_dark_mode(),
+    }
+}
+
+/// Returns the custom theme if one was loaded, then the theme chosen from the menu, otherwise
+/// the built-in theme for the mode.
+fn select_theme<'a>(ts: &'a ThemeSet, theme_mode: &ThemeMode) -> &'a Theme {
+    if let Some(theme) = CUSTOM_THEME.get() {
+        return theme;
+    }
+    let chosen = SYNTAX_THEME.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(theme) = chosen.as_deref().and_then(|name| ts.themes.get(name)) {
+        return theme;
+    }
+
+    let theme_name = if is_dark(theme_mode) {
+        DARK_THEME
+    } else {
+        LIGHT_THEME
+    };
+
+    &ts.themes[theme_name]
+}
+
 /// Parses a string of Markdown text and converts it into an HTML string.
 ///
 /// Enables GitHub-style extensions like tables, footnotes, strikethrough, and task lists.
@@ -18,33 +216,442 @@ pub fn parse_markdown(markdown_input: &str) -> String {
 }
 
 /// Parses a string of Markdown text and converts it into an HTML string with theme-aware syntax highlighting.
+///
+/// A leading YAML front matter block is stripped from the body and rendered as a metadata header.
 pub fn parse_markdown_with_theme(markdown_input: &str, theme_mode: &ThemeMode) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
+    match split_front_matter(markdown_input) {
+        Some((front_matter, body)) => {
+            let first_line = markdown_input[..markdown_input.len() - body.len()]
+                .matches('\n')
+                .count();
+            let mut html_output = front_matter.to_html();
+            html_output.push_str(&render_markdown(body, theme_mode, first_line));
+            html_output
+        }
+        None => render_markdown(markdown_input, theme_mode, 0),
+    }
+}
 
-    let ps = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
+/// Common short names for languages, mapped to tokens syntect's default syntaxes understand.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("mjs", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("console", "bash"),
+    ("py", "python"),
+    ("py3", "python"),
+    ("yml", "yaml"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("c++", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("cs", "c#"),
+    ("csharp", "c#"),
+    ("objc", "objective-c"),
+    ("golang", "go"),
+    ("md", "markdown"),
+    ("text", "txt"),
+    ("plaintext", "txt"),
+];
 
-    // Choose theme based on mode
-    let theme_name = match theme_mode {
-        ThemeMode::Light => LIGHT_THEME,
-        ThemeMode::Dark => DARK_THEME,
-        ThemeMode::System => LIGHT_THEME, // Default to light for system mode
+/// Returns the language token of a fenced code block's info string, e.g. `rust` for
+/// `rust,ignore` or `python {.numberLines}`, and an empty one for attributes alone.
+pub(super) fn info_string_language(info: &str) -> &str {
+    info.trim_start()
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '{')
+        .next()
+        .unwrap_or("")
+}
+
+/// Maps a language alias such as `js` or `sh` to its canonical name.
+fn resolve_language_alias(language: &str) -> &str {
+    LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(language))
+        .map_or(language, |(_, canonical)| canonical)
+}
+
+/// Finds the syntax for a code block's info string, falling back to plain text.
+fn find_code_syntax<'a>(ps: &'a SyntaxSet, info: &str) -> &'a SyntaxReference {
+    let language = info_string_language(info);
+    ps.find_syntax_by_token(resolve_language_alias(language))
+        .or_else(|| ps.find_syntax_by_token(language))
+        .unwrap_or_else(|| ps.find_syntax_plain_text())
+}
+
+/// Escapes text for use inside a double-quoted HTML attribute.
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Emits a code block with a `language-xxx` class for highlight.js to highlight in the page.
+fn client_code_block(code: &str, language: &str) -> String {
+    let attr_escaped_source = escape_attribute(code);
+    let escaped_code = code.replace('&', "&amp;").replace('<', "&lt;");
+    let language: String = resolve_language_alias(info_string_language(language))
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '#'))
+        .collect();
+    let language = if language.is_empty() {
+        "plaintext".to_string()
+    } else {
+        language
     };
 
-    let theme = &ts.themes[theme_name];
+    format!(
+        r#"<div class="code-block" data-code-source="{attr_escaped_source}"><div class="code-block-buttons"><button class="code-copy-btn" onclick="copyCodeBlock(this)" title="Copy code">Copy</button></div><pre><code class="language-{language}">{escaped_code}</code></pre></div>"#
+    )
+}
+
+/// Returns a syntect-highlighted code block, reusing the HTML from an earlier render of the
+/// same code with the same theme on the same background.
+fn cached_code_block(
+    code: &str,
+    language: &str,
+    ps: &SyntaxSet,
+    theme: &Theme,
+    dark: bool,
+) -> String {
+    let fold_lines = CODE_FOLD_LINES.load(Ordering::Relaxed);
+    let mut hasher = DefaultHasher::new();
+    (language, code, fold_lines).hash(&mut hasher);
+    let key = hasher.finish();
+    // Colors are fitted to the background, so a custom theme renders differently when dark
+    let background = code_colors::code_background()
+        .unwrap_or_else(|| if dark { "dark" } else { "light" }.to_string());
+    let theme_name = format!(
+        "{} on {background}",
+        theme.name.as_deref().unwrap_or_default()
+    );
+
+    let cache = CODE_BLOCK_CACHE.get_or_init(Default::default);
+    {
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.theme_name != theme_name {
+            // Colors are baked into the HTML, so a theme change invalidates every entry
+            cache.theme_name = theme_name.clone();
+            cache.blocks.clear();
+        }
+        if let Some(html) = cache.blocks.get(&key) {
+            return html.clone();
+        }
+    }
+
+    let html = highlight_code_block(code, language, ps, theme, dark, fold_lines);
+
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.theme_name == theme_name {
+        if cache.blocks.len() >= MAX_CACHED_CODE_BLOCKS {
+            cache.blocks.clear();
+        }
+        cache.blocks.insert(key, html.clone());
+    }
+    html
+}
+
+/// Highlights a code block with syntect, adding a copy button.
+///
+/// Only the theme's foreground colors are used, adjusted to stay readable on the page's dark or
+/// light code background. Blocks longer than `fold_lines` (when non-zero) show only that many
+/// lines until expanded; the copy button still copies the whole source.
+fn highlight_code_block(
+    code: &str,
+    language: &str,
+    ps: &SyntaxSet,
+    theme: &Theme,
+    dark: bool,
+    fold_lines: usize,
+) -> String {
+    let syntax = find_code_syntax(ps, language);
+
+    // Raw source for the copy button
+    let attr_escaped_source = escape_attribute(code);
+
+    let line_count = LinesWithEndings::from(code).count();
+    let folded = fold_lines > 0 && line_count > fold_lines;
+    let class = if folded {
+        "code-block folded"
+    } else {
+        "code-block"
+    };
+
+    let mut h = HighlightLines::new(syntax, theme);
+    let mut html = format!(
+        r#"<div class="{class}" data-code-source="{attr_escaped_source}"><div class="code-block-buttons"><button class="code-copy-btn" onclick="copyCodeBlock(this)" title="Copy code">Copy</button></div><pre><code>"#
+    );
+    for (index, line) in LinesWithEndings::from(code).enumerate() {
+        if folded && index == fold_lines {
+            html.push_str(r#"<span class="code-fold-overflow">"#);
+        }
+        let ranges = h.highlight_line(line, ps).unwrap();
+        for (style, text) in ranges {
+            let color = code_colors::span_color(style.foreground, dark);
+            let escaped_text = text.replace('&', "&amp;").replace('<', "&lt;");
+            html.push_str(&format!(
+                "<span style=\"color:{color}\">{escaped_text}</span>"
+            ));
+        }
+    }
+    if folded {
+        let hidden = line_count - fold_lines;
+        let noun = if hidden == 1 { "line" } else { "lines" };
+        html.push_str(&format!(
+            r#"</span></code></pre><button class="code-fold-btn" onclick="toggleCodeFold(this)" data-hidden-lines="{hidden}">Show {hidden} more {noun}…</button></div>"#
+        ));
+    } else {
+        html.push_str("</code></pre></div>");
+    }
+    html
+}
+
+/// Highlights an inline code span with syntect, using the same readable colors as code blocks.
+fn highlight_inline_code(
+    code: &str,
+    syntax: &SyntaxReference,
+    ps: &SyntaxSet,
+    theme: &Theme,
+    dark: bool,
+) -> String {
+    let mut h = HighlightLines::new(syntax, theme);
+    // The syntaxes expect whole lines, ending in a newline, which is left out of the output
+    let line = format!("{code}\n");
+    let Ok(ranges) = h.highlight_line(&line, ps) else {
+        return format!(
+            "<code>{}</code>",
+            code.replace('&', "&amp;").replace('<', "&lt;")
+        );
+    };
+    let mut html = String::from("<code class=\"inline-highlighted\">");
+    for (style, text) in ranges {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        if text.is_empty() {
+            continue;
+        }
+        let color = code_colors::span_color(style.foreground, dark);
+        let escaped_text = text.replace('&', "&amp;").replace('<', "&lt;");
+        html.push_str(&format!(
+            "<span style=\"color:{color}\">{escaped_text}</span>"
+        ));
+    }
+    html.push_str("</code>");
+    html
+}
+
+/// Returns whether `event` starts or ends a block, rather than something inline.
+fn is_block_boundary(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => !matches!(
+            tag,
+            Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Superscript
+                | Tag::Subscript
+                | Tag::Link { .. }
+                | Tag::Image { .. }
+        ),
+        Event::End(tag) => !matches!(
+            tag,
+            TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Superscript
+                | TagEnd::Subscript
+                | TagEnd::Link
+                | TagEnd::Image
+        ),
+        Event::Rule => true,
+        _ => false,
+    }
+}
+
+/// Returns the CSS class suffix, icon and title for a GitHub alert blockquote.
+fn alert_parts(kind: BlockQuoteKind) -> (&'static str, &'static str, &'static str) {
+    match kind {
+        BlockQuoteKind::Note => ("note", "ℹ️", "Note"),
+        BlockQuoteKind::Tip => ("tip", "💡", "Tip"),
+        BlockQuoteKind::Important => ("important", "❗", "Important"),
+        BlockQuoteKind::Warning => ("warning", "⚠️", "Warning"),
+        BlockQuoteKind::Caution => ("caution", "🛑", "Caution"),
+    }
+}
+
+/// Counts the words of prose in a document, ignoring front matter, code blocks and raw HTML.
+pub fn count_words(markdown_input: &str) -> usize {
+    let body = split_front_matter(markdown_input).map_or(markdown_input, |(_, body)| body);
+
+    let mut in_code_block = false;
+    let mut words = 0;
+    for event in Parser::new_ext(body, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) | Event::Code(text) if !in_code_block => {
+                words += text.split_whitespace().count();
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
+
+/// Returns the plain text of the document's first level-1 heading, ignoring any front matter.
+pub fn first_heading(markdown_input: &str) -> Option<String> {
+    let body = split_front_matter(markdown_input).map_or(markdown_input, |(_, body)| body);
+
+    let mut heading: Option<String> = None;
+    for event in Parser::new(body) {
+        match event {
+            Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            }) => heading = Some(String::new()),
+            Event::End(TagEnd::Heading(HeadingLevel::H1)) => {
+                return heading
+                    .map(|text| text.trim().to_string())
+                    .filter(|text| !text.is_empty());
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parses a chunk from the middle of a streamed document.
+///
+/// Unlike `parse_markdown`, a leading `---` block is never treated as front matter.
+/// `first_line` is the chunk's zero-based line position within the whole document, so that
+/// task checkboxes refer to the right source lines.
+pub fn parse_markdown_fragment(markdown_input: &str, first_line: usize) -> String {
+    render_markdown(markdown_input, &ThemeMode::System, first_line)
+}
+
+/// Returns the parser options documents are rendered with: the dialect's, and smart
+/// punctuation if it's on.
+pub(super) fn document_options() -> Options {
+    let mut options = dialect().options();
+    if SMART_PUNCTUATION.load(Ordering::Relaxed) {
+        options.insert(Options::ENABLE_SMART_PUNCTUATION);
+    }
+    options
+}
+
+/// Renders markdown whose first line is line `first_line` of the full document.
+fn render_markdown(markdown_input: &str, theme_mode: &ThemeMode, first_line: usize) -> String {
+    let dialect = dialect();
+    let options = document_options();
+    let autolinks = AUTOLINKS.load(Ordering::Relaxed);
+    let emoji_shortcodes = EMOJI_SHORTCODES.load(Ordering::Relaxed);
+    let sanitize_html = sanitize_html_enabled();
+
+    let ps = SyntaxSet::load_defaults_newlines();
+    let ts = ThemeSet::load_defaults();
+
+    let theme = select_theme(&ts, theme_mode);
+    let dark = is_dark(theme_mode);
+    // Inline code is only highlighted for a language syntect knows
+    let inline_language = INLINE_CODE_LANGUAGE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let inline_syntax = inline_language
+        .map(|language| find_code_syntax(&ps, &language))
+        .filter(|syntax| syntax.name != ps.find_syntax_plain_text().name);
 
     let parser = Parser::new_ext(markdown_input, options);
     let mut html_output = String::new();
     let mut code_block_text = String::new();
     let mut code_block_language = String::new();
     let mut in_code_block = false;
+    // Links and images can't contain autolinks
+    let mut link_depth = 0;
+    // Whether we're inside a `<https://…>` or `<name@example.com>` link, whose text is its URL
+    let mut in_autolink = false;
+    // Image whose alt text is being collected
+    let mut image: Option<(Tag, String)> = None;
+    // Source line tracking for task checkboxes
+    let mut task_line = first_line;
+    let mut task_scan_pos = 0;
+    // Footnote definitions are moved to a section at the end
+    let mut footnotes = Footnotes::default();
+    let mut sanitizer = HtmlSanitizer::default();
+    let mut table_cells = TableCells::default();
+    let mut headings = HeadingAnchors::default();
 
-    for event in parser {
+    let mut mark_characters = Vec::new();
+    if dialect.highlight {
+        mark_characters.push('=');
+    }
+    if dialect.sub_sup {
+        mark_characters.extend(['~', '^']);
+    }
+    let events = separate_escaped(parser.into_offset_iter(), markdown_input, mark_characters);
+    // Merged so that URLs split across text events are linked whole
+    for (event, range) in TextMergeWithOffset::new(events) {
+        // Raw HTML runs to the end of its block, with the text of a removed `<script>` or
+        // `<style>` in between
+        if sanitize_html && is_block_boundary(&event) {
+            html_output.push_str(&sanitizer.finish());
+        } else if sanitize_html
+            && sanitizer.is_removing()
+            && matches!(
+                event,
+                Event::Text(_)
+                    | Event::Code(_)
+                    | Event::InlineMath(_)
+                    | Event::DisplayMath(_)
+                    | Event::SoftBreak
+                    | Event::HardBreak
+            )
+        {
+            continue;
+        }
+        if let Event::Text(text) | Event::Code(text) = &event {
+            headings.push_text(text);
+        }
+        // An image's description arrives as events of its own and becomes its `alt` text
+        if image.is_some() {
+            match event {
+                Event::End(TagEnd::Image) => {
+                    if let Some((tag, alt)) = image.take() {
+                        link_depth -= 1;
+                        let events = [
+                            Event::Start(tag),
+                            Event::Text(alt.into()),
+                            Event::End(TagEnd::Image),
+                        ];
+                        html::push_html(&mut html_output, events.into_iter());
+                    }
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, alt)) = &mut image {
+                        alt.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
         match event {
+            Event::Html(raw) | Event::InlineHtml(raw) if sanitize_html => {
+                html_output.push_str(&sanitizer.push(&raw));
+            }
             Event::Start(Tag::CodeBlock(kind)) => {
                 in_code_block = true;
                 if let CodeBlockKind::Fenced(lang) = kind {
@@ -58,7 +665,9 @@ pub fn parse_markdown_with_theme(markdown_input: &str, theme_mode: &ThemeMode) -
                 let context = PluginContext {
                     theme_mode: theme_mode.clone(),
                     is_streaming: false,
-                    content_id: format!("block_{}", html_output.len()),
+                    // Unique across the chunks of a streamed document, which start at
+                    // different lines
+                    content_id: format!("block_{first_line}_{}", html_output.len()),
                 };
 
                 if let Some(plugin_result) = PLUGIN_MANAGER.process_code_block(
@@ -68,41 +677,168 @@ pub fn parse_markdown_with_theme(markdown_input: &str, theme_mode: &ThemeMode) -
                 ) {
                     // Plugin handled the code block
                     html_output.push_str(&plugin_result.html);
+                } else if CLIENT_SIDE_HIGHLIGHTING.load(Ordering::Relaxed) {
+                    // Leave highlighting to highlight.js in the page
+                    html_output
+                        .push_str(&client_code_block(&code_block_text, &code_block_language));
                 } else {
                     // Fallback to standard syntax highlighting
-                    let syntax = ps
-                        .find_syntax_by_token(&code_block_language)
-                        .unwrap_or_else(|| ps.find_syntax_by_token("txt").unwrap());
-
-                    let mut h = HighlightLines::new(syntax, theme);
-                    let mut html = String::from("<pre><code>");
-                    for line in LinesWithEndings::from(&code_block_text) {
-                        let ranges = h.highlight_line(line, &ps).unwrap();
-                        let mut line_html = String::new();
-                        for (style, text) in ranges {
-                            let fg = style.foreground;
-                            let color = format!("#{:02x}{:02x}{:02x}", fg.r, fg.g, fg.b);
-                            let escaped_text = text.replace('&', "&amp;").replace('<', "&lt;");
-                            line_html.push_str(&format!(
-                                "<span style=\"color:{color}\">{escaped_text}</span>"
-                            ));
-                        }
-                        html.push_str(&line_html);
-                    }
-                    html.push_str("</code></pre>");
-                    html_output.push_str(&html);
+                    html_output.push_str(&cached_code_block(
+                        &code_block_text,
+                        &code_block_language,
+                        &ps,
+                        theme,
+                        dark,
+                    ));
                 }
 
                 code_block_text.clear();
                 code_block_language.clear();
             }
+            Event::Start(Tag::BlockQuote(Some(kind))) => {
+                let (class, icon, title) = alert_parts(kind);
+                html_output.push_str(&format!(
+                    "<div class=\"admonition admonition-{class}\">\n<p class=\"admonition-title\"><span class=\"admonition-icon\">{icon}</span> {title}</p>\n"
+                ));
+            }
+            Event::End(TagEnd::BlockQuote(Some(_))) => {
+                html_output.push_str("</div>\n");
+            }
+            Event::TaskListMarker(checked) => {
+                task_line += markdown_input[task_scan_pos..range.start]
+                    .matches('\n')
+                    .count();
+                task_scan_pos = range.start;
+                let checked = if checked { " checked=\"\"" } else { "" };
+                html_output.push_str(&format!(
+                    "<input type=\"checkbox\" class=\"task-checkbox\" data-line=\"{task_line}\"{checked}/>\n"
+                ));
+            }
+            Event::InlineMath(math) => {
+                html_output.push_str(&format!(
+                    "<span class=\"latex-math math-inline math-text\" data-latex=\"{}\"></span>",
+                    escape_attribute(&math)
+                ));
+            }
+            Event::DisplayMath(math) => {
+                html_output.push_str(&format!(
+                    "<span class=\"latex-math math-display math-text\" data-latex=\"{}\"></span>",
+                    escape_attribute(&math)
+                ));
+            }
+            Event::FootnoteReference(label) => {
+                html_output.push_str(&footnotes.reference_html(&label));
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                headings.start(level, html_output.len());
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                headings.end(&mut html_output);
+            }
+            Event::Start(Tag::Table(alignments)) => {
+                table_cells.start_table(alignments);
+                html_output.push_str("<table>");
+            }
+            Event::Start(Tag::TableHead) => {
+                table_cells.start_row(true);
+                html_output.push_str("<thead><tr>");
+            }
+            Event::Start(Tag::TableRow) => {
+                table_cells.start_row(false);
+                html_output.push_str("<tr>");
+            }
+            Event::Start(Tag::TableCell) => {
+                html_output.push_str(&table_cells.start_cell());
+            }
+            Event::End(TagEnd::TableCell) => {
+                html_output.push_str(&table_cells.end_cell());
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                footnotes.start_definition(&label, html_output.len());
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                footnotes.end_definition(&mut html_output);
+            }
+            Event::Start(Tag::Link {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                ..
+            }) => {
+                link_depth += 1;
+                html_output.push_str(&format!(
+                    "<a class=\"wikilink\" href=\"{}\">",
+                    escape_attribute(&wiki_href(&dest_url))
+                ));
+            }
+            Event::Start(tag @ Tag::Image { .. }) => {
+                link_depth += 1;
+                image = Some((tag, String::new()));
+            }
+            Event::Start(tag @ Tag::Link { .. }) => {
+                link_depth += 1;
+                in_autolink = matches!(
+                    tag,
+                    Tag::Link {
+                        link_type: LinkType::Autolink | LinkType::Email,
+                        ..
+                    }
+                );
+                html::push_html(&mut html_output, std::iter::once(Event::Start(tag)));
+            }
+            Event::End(tag @ TagEnd::Link) => {
+                link_depth -= 1;
+                in_autolink = false;
+                html::push_html(&mut html_output, std::iter::once(Event::End(tag)));
+            }
+            Event::Text(text) if in_code_block => {
+                code_block_text.push_str(&text);
+            }
+            Event::Code(code) if inline_syntax.is_some() => {
+                if let Some(syntax) = inline_syntax {
+                    html_output.push_str(&highlight_inline_code(&code, syntax, &ps, theme, dark));
+                }
+            }
             Event::Text(text) => {
-                if in_code_block {
-                    code_block_text.push_str(&text);
+                // Inline code arrives as `Event::Code`, so it is never expanded, and an
+                // autolink's text has to stay the URL it links to
+                let text = if emoji_shortcodes && !in_autolink && text.contains(':') {
+                    CowStr::from(expand_shortcodes(&text).into_owned())
                 } else {
-                    let mut temp_html = String::new();
-                    html::push_html(&mut temp_html, std::iter::once(Event::Text(text)));
-                    html_output.push_str(&temp_html);
+                    text
+                };
+                let parts = if dialect.highlight {
+                    highlight_parts(&text)
+                } else {
+                    vec![(false, &*text)]
+                };
+                for (highlighted, part) in parts {
+                    if highlighted {
+                        html_output.push_str("<mark>");
+                    }
+                    let pieces = if dialect.sub_sup {
+                        script_parts(part)
+                    } else {
+                        vec![(None, part)]
+                    };
+                    for (tag, piece) in pieces {
+                        if let Some(tag) = tag {
+                            html_output.push_str(&format!("<{tag}>"));
+                        }
+                        if autolinks && link_depth == 0 {
+                            html_output.push_str(&autolink_html(piece));
+                        } else {
+                            html::push_html(
+                                &mut html_output,
+                                std::iter::once(Event::Text(piece.into())),
+                            );
+                        }
+                        if let Some(tag) = tag {
+                            html_output.push_str(&format!("</{tag}>"));
+                        }
+                    }
+                    if highlighted {
+                        html_output.push_str("</mark>");
+                    }
                 }
             }
             e => {
@@ -113,41 +849,197 @@ pub fn parse_markdown_with_theme(markdown_input: &str, theme_mode: &ThemeMode) -
         }
     }
 
-    html_output
+    html_output.push_str(&sanitizer.finish());
+    html_output.push_str(&footnotes.section_html());
+
+    let context = PluginContext {
+        theme_mode: theme_mode.clone(),
+        is_streaming: false,
+        content_id: "document".to_string(),
+    };
+    PLUGIN_MANAGER.process_document(html_output, &context)
 }
 
 /// Highlights markdown syntax and returns it as HTML with theme-aware syntax highlighting.
-pub fn highlight_markdown_with_theme(markdown_input: &str, theme_mode: &ThemeMode) -> String {
+///
+/// `ThemeMode::System` follows the system appearance. With `line_numbers`, each source line is
+/// wrapped in a `source-line` span that the stylesheet numbers. With `show_whitespace`, spaces
+/// and tabs are wrapped in spans the stylesheet marks, and trailing ones are highlighted. The
+/// `source-view` class on the `<pre>` lets the viewer preview math under the pointer.
+pub fn highlight_markdown_with_theme(
+    markdown_input: &str,
+    theme_mode: &ThemeMode,
+    line_numbers: bool,
+    show_whitespace: bool,
+) -> String {
     let ps = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
 
     let syntax = ps.find_syntax_by_extension("md").unwrap();
 
-    // Choose theme based on mode
-    let theme_name = match theme_mode {
-        ThemeMode::Light => LIGHT_THEME,
-        ThemeMode::Dark => DARK_THEME,
-        ThemeMode::System => LIGHT_THEME, // Default to light for system mode
-    };
-
-    let theme = &ts.themes[theme_name];
+    let theme = select_theme(&ts, theme_mode);
+    let dark = is_dark(theme_mode);
     let mut h = HighlightLines::new(syntax, theme);
 
     let mut html_output = String::new();
-    html_output.push_str("<pre style=\"background-color: var(--pre-bg-color); padding: 16px; border-radius: 6px; overflow: auto; white-space: pre-wrap; word-wrap: break-word;\"><code>");
+    let class = if line_numbers {
+        "source-view source-lines"
+    } else {
+        "source-view"
+    };
+    html_output.push_str(&format!("<pre class=\"{class}\" style=\"background-color: var(--pre-bg-color); padding: 16px; border-radius: 6px; overflow: auto; white-space: pre-wrap; word-wrap: break-word;\"><code>"));
 
     for line in LinesWithEndings::from(markdown_input) {
+        if line_numbers {
+            html_output.push_str("<span class=\"source-line\">");
+        }
+        let trailing_start = line
+            .trim_end_matches(['\n', '\r'])
+            .trim_end_matches([' ', '\t'])
+            .len();
+        let mut line_offset = 0;
         let ranges = h.highlight_line(line, &ps).unwrap();
         for (style, text) in ranges {
-            let fg = style.foreground;
-            let color = format!("#{:02x}{:02x}{:02x}", fg.r, fg.g, fg.b);
-            let escaped_text = text.replace('&', "&amp;").replace('<', "&lt;");
+            let color = code_colors::span_color(style.foreground, dark);
+            let escaped_text = if show_whitespace {
+                whitespace_html(text, line_offset, trailing_start)
+            } else {
+                text.replace('&', "&amp;").replace('<', "&lt;")
+            };
+            line_offset += text.len();
             html_output.push_str(&format!(
                 "<span style=\"color:{color}\">{escaped_text}</span>"
             ));
         }
+        if line_numbers {
+            html_output.push_str("</span>");
+        }
     }
 
     html_output.push_str("</code></pre>");
     html_output
 }
+
+/// Escapes a piece of a source line that starts `line_offset` bytes into the line, wrapping
+/// each space and tab in a span the stylesheet marks. Those from `trailing_start` on end the
+/// line and are flagged as trailing.
+fn whitespace_html(text: &str, line_offset: usize, trailing_start: usize) -> String {
+    let mut html = String::with_capacity(text.len());
+    for (index, c) in text.char_indices() {
+        let class = match c {
+            ' ' => "ws-space",
+            '\t' => "ws-tab",
+            '&' => {
+                html.push_str("&amp;");
+                continue;
+            }
+            '<' => {
+                html.push_str("&lt;");
+                continue;
+            }
+            c => {
+                html.push(c);
+                continue;
+            }
+        };
+        let trailing = if line_offset + index >= trailing_start {
+            " ws-trailing"
+        } else {
+            ""
+        };
+        html.push_str(&format!("<span class=\"{class}{trailing}\">{c}</span>"));
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::RenderSettings;
+    use crate::plugins::manager::initialize_plugins;
+
+    #[test]
+    fn math_code_block_is_rendered_by_the_katex_plugin() {
+        initialize_plugins().unwrap();
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown("```math\nx^2\n```");
+        assert!(html.contains("latex-container"), "{html}");
+    }
+
+    #[test]
+    fn source_view_wraps_each_line_when_numbered() {
+        let markdown = "# Title\n\nSome text\n";
+        let numbered = highlight_markdown_with_theme(markdown, &ThemeMode::Light, true, false);
+        assert!(numbered.starts_with(r#"<pre class="source-view source-lines""#));
+        assert_eq!(numbered.matches(r#"<span class="source-line">"#).count(), 3);
+
+        let plain = highlight_markdown_with_theme(markdown, &ThemeMode::Light, false, false);
+        assert!(plain.starts_with(r#"<pre class="source-view""#));
+        assert!(!plain.contains("source-line"));
+    }
+
+    #[test]
+    fn info_string_language_ignores_attributes() {
+        assert_eq!(info_string_language("rust,ignore {.class}"), "rust");
+        assert_eq!(info_string_language("python {.numberLines}"), "python");
+        assert_eq!(info_string_language("{.class}"), "");
+        assert_eq!(info_string_language(""), "");
+    }
+
+    #[test]
+    fn language_aliases_find_their_syntaxes() {
+        let ps = SyntaxSet::load_defaults_newlines();
+        for (info, syntax) in [
+            ("js", "JavaScript"),
+            ("sh", "Bourne Again Shell (bash)"),
+            ("zsh", "Bourne Again Shell (bash)"),
+            ("yml", "YAML"),
+            ("py", "Python"),
+            ("rs", "Rust"),
+            ("JS", "JavaScript"),
+            ("rust,ignore {.class}", "Rust"),
+            ("no-such-language", "Plain Text"),
+        ] {
+            assert_eq!(find_code_syntax(&ps, info).name, syntax, "{info}");
+        }
+    }
+
+    #[test]
+    fn cached_code_block_matches_a_fresh_highlight() {
+        let _settings = RenderSettings::default().scoped();
+        let ps = SyntaxSet::load_defaults_newlines();
+        let ts = ThemeSet::load_defaults();
+        let theme = select_theme(&ts, &ThemeMode::Light);
+        let code = "fn cached() -> u32 {\n    42\n}\n";
+
+        let first = cached_code_block(code, "rust", &ps, theme, false);
+        let hit = cached_code_block(code, "rust", &ps, theme, false);
+        assert_eq!(hit, first);
+        assert_eq!(
+            first,
+            highlight_code_block(code, "rust", &ps, theme, false, 0)
+        );
+    }
+
+    #[test]
+    fn inline_code_is_highlighted_in_the_inline_language() {
+        let _settings = RenderSettings {
+            inline_code_language: Some("rust".into()),
+            ..RenderSettings::default()
+        }
+        .scoped();
+        let html = parse_markdown("Call `let x = 1;` first.");
+        assert!(
+            html.contains(r#"<code class="inline-highlighted"><span style="color:"#),
+            "{html}"
+        );
+        assert!(html.matches("<span style=").count() > 1, "{html}");
+    }
+
+    #[test]
+    fn inline_code_is_plain_without_an_inline_language() {
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown("Call `let x = 1;` first.");
+        assert!(html.contains("<code>let x = 1;</code>"), "{html}");
+    }
+}
@@ -0,0 +1,430 @@
+//! Optional cleanup of raw HTML written in markdown, enabled with `--sanitize-html`.
+//!
+//! `<script>` and `<style>` elements are removed with their contents, embedding tags,
+//! `<meta>`, `<base>` and SVG animations are dropped, and event handler attributes and
+//! `javascript:` URLs are stripped. Other tags, such as `<kbd>`, `<details>` and `<sub>`, pass
+//! through unchanged.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether raw HTML is sanitized.
+static SANITIZE_HTML: AtomicBool = AtomicBool::new(false);
+
+/// Turns sanitizing of raw HTML in markdown on or off.
+pub fn set_sanitize_html(enabled: bool) {
+    SANITIZE_HTML.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether raw HTML is sanitized.
+pub fn sanitize_html_enabled() -> bool {
+    SANITIZE_HTML.load(Ordering::Relaxed)
+}
+
+/// Elements removed together with everything inside them
+const REMOVED_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// Elements whose tags are removed, leaving any fallback content in place. `<meta>` can
+/// redirect the page, `<base>` changes where every link goes, and SVG `<animate>` and `<set>`
+/// can set an `href` to any value.
+const DROPPED_TAGS: [&str; 7] = [
+    "iframe", "object", "embed", "meta", "base", "animate", "set",
+];
+
+/// Attributes holding a URL, which must not use the `javascript:` scheme
+const URL_ATTRIBUTES: [&str; 5] = ["href", "src", "action", "formaction", "xlink:href"];
+
+/// Sanitizes the raw HTML of one document. Markdown delivers raw HTML in pieces (a line of an
+/// HTML block, or a single inline tag), so state carries over from one piece to the next.
+#[derive(Debug, Default)]
+pub struct HtmlSanitizer {
+    /// Start of a tag cut off at the end of the previous piece
+    pending_tag: String,
+    /// Removed element whose closing tag hasn't been seen yet
+    removing: Option<&'static str>,
+    /// Whether the previous piece ended inside an `<!-- comment`
+    in_comment: bool,
+}
+
+impl HtmlSanitizer {
+    /// Returns the sanitized form of the next piece of raw HTML.
+    pub fn push(&mut self, html: &str) -> String {
+        let input = std::mem::take(&mut self.pending_tag) + html;
+        let mut output = String::new();
+        let mut rest = input.as_str();
+
+        while !rest.is_empty() {
+            if self.in_comment {
+                match rest.find("-->") {
+                    Some(end) => {
+                        output.push_str(&rest[..end + 3]);
+                        rest = &rest[end + 3..];
+                        self.in_comment = false;
+                    }
+                    None => {
+                        output.push_str(rest);
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            if let Some(element) = self.removing {
+                match find_closing_tag(rest, element) {
+                    Some(end) => {
+                        rest = &rest[end..];
+                        self.removing = None;
+                    }
+                    None => break,
+                }
+                continue;
+            }
+
+            let Some(start) = rest.find('<') else {
+                output.push_str(rest);
+                break;
+            };
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with("<!--") {
+                output.push_str("<!--");
+                rest = &rest[4..];
+                self.in_comment = true;
+                continue;
+            }
+            if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+                // A lone `<` in text
+                output.push('<');
+                rest = &rest[1..];
+                continue;
+            }
+
+            match tag_end(rest) {
+                Some(end) => {
+                    output.push_str(&self.sanitize_tag(&rest[..end]));
+                    rest = &rest[end..];
+                }
+                None => {
+                    // The tag continues in the next piece
+                    self.pending_tag = rest.to_string();
+                    break;
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Returns whether the pieces so far opened a `<script>` or `<style>` element that hasn't
+    /// been closed, so text between pieces belongs to it.
+    pub fn is_removing(&self) -> bool {
+        self.removing.is_some()
+    }
+
+    /// Ends the current run of raw HTML at the end of its block. A tag left
+    /// unfinished is closed so its attributes can't run into what follows. A `<script>` or
+    /// `<style>` element stays open until its closing tag, even in a later block, as it does
+    /// in the browser.
+    pub fn finish(&mut self) -> String {
+        self.in_comment = false;
+        let tag = std::mem::take(&mut self.pending_tag);
+        if tag.is_empty() {
+            String::new()
+        } else {
+            self.sanitize_tag(&format!("{tag}>"))
+        }
+    }
+
+    /// Returns a complete tag such as `<a href="x" onclick="y">` without unsafe parts, or
+    /// nothing if the whole tag is removed.
+    fn sanitize_tag(&mut self, tag: &str) -> String {
+        let closing = tag.starts_with("</");
+        let body = tag.trim_start_matches("</").trim_start_matches('<');
+        let name_len = body
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .unwrap_or(body.len());
+        let name = body[..name_len].to_ascii_lowercase();
+
+        if let Some(element) = REMOVED_ELEMENTS.iter().find(|element| **element == name) {
+            let self_closing = tag.trim_end_matches('>').trim_end().ends_with('/');
+            if !closing && !self_closing {
+                self.removing = Some(*element);
+            }
+            return String::new();
+        }
+        if DROPPED_TAGS.contains(&name.as_str()) {
+            return String::new();
+        }
+        if closing {
+            return tag.to_string();
+        }
+
+        let mut output = format!("<{}", &body[..name_len]);
+        for attribute in attributes(&body[name_len..]) {
+            let attribute_name = attribute.name.to_ascii_lowercase();
+            let unsafe_url = URL_ATTRIBUTES.contains(&attribute_name.as_str())
+                && attribute.value.is_some_and(is_unsafe_url);
+            if attribute_name.starts_with("on") || unsafe_url {
+                continue;
+            }
+            output.push(' ');
+            output.push_str(attribute.raw);
+        }
+        if body.trim_end_matches('>').trim_end().ends_with('/') {
+            output.push_str(" /");
+        }
+        output.push('>');
+        output
+    }
+}
+
+/// Returns the length of the tag at the start of `html`, up to and including its `>`, or `None`
+/// if the tag isn't closed yet. Quoted attribute values may contain `>`.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            (None, _) => {}
+        }
+    }
+    None
+}
+
+/// Returns the offset just past `</element>` in `html`, ignoring case.
+fn find_closing_tag(html: &str, element: &str) -> Option<usize> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find(&format!("</{element}"))?;
+    let end = lower[start..].find('>')?;
+    Some(start + end + 1)
+}
+
+/// One attribute of a tag, as written.
+struct Attribute<'a> {
+    name: &'a str,
+    /// Unquoted value, if the attribute has one
+    value: Option<&'a str>,
+    /// The whole attribute, e.g. `href="page.html"`
+    raw: &'a str,
+}
+
+/// Splits the attribute part of a tag (after its name, including the closing `>`) into
+/// attributes.
+fn attributes(html: &str) -> Vec<Attribute<'_>> {
+    let bytes = html.as_bytes();
+    let mut attributes = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        // Skip whitespace and the `/` of self-closing tags
+        while index < bytes.len() && (bytes[index].is_ascii_whitespace() || bytes[index] == b'/') {
+            index += 1;
+        }
+        if index >= bytes.len() || bytes[index] == b'>' {
+            break;
+        }
+
+        let start = index;
+        while index < bytes.len()
+            && !bytes[index].is_ascii_whitespace()
+            && !matches!(bytes[index], b'=' | b'>' | b'/')
+        {
+            index += 1;
+        }
+        let name = &html[start..index];
+        if name.is_empty() {
+            // A stray `=`
+            index += 1;
+            continue;
+        }
+
+        let mut value = None;
+        if index < bytes.len() && bytes[index] == b'=' {
+            index += 1;
+            match bytes.get(index) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let value_start = index + 1;
+                    let value_end = html[value_start..]
+                        .find(quote as char)
+                        .map_or(bytes.len(), |end| value_start + end);
+                    value = Some(&html[value_start..value_end]);
+                    index = (value_end + 1).min(bytes.len());
+                }
+                _ => {
+                    let value_start = index;
+                    while index < bytes.len()
+                        && !bytes[index].is_ascii_whitespace()
+                        && bytes[index] != b'>'
+                    {
+                        index += 1;
+                    }
+                    value = Some(&html[value_start..index]);
+                }
+            }
+        }
+
+        attributes.push(Attribute {
+            name,
+            value,
+            raw: &html[start..index],
+        });
+    }
+
+    attributes
+}
+
+/// Returns whether a URL uses the `javascript:` scheme, or may hide it. Browsers skip
+/// whitespace and control characters in a scheme and decode every named reference such as
+/// `&Tab;`, so a scheme with any of those left in is rejected too.
+fn is_unsafe_url(url: &str) -> bool {
+    let decoded = decode_character_references(url);
+    // A colon after the path, query or fragment starts isn't a scheme
+    let Some(end) = decoded.find([':', '/', '?', '#']) else {
+        return false;
+    };
+    if !decoded[end..].starts_with(':') {
+        return false;
+    }
+    let scheme = decoded[..end].trim_matches(|c: char| c.is_whitespace() || c.is_control());
+    let hidden = scheme
+        .chars()
+        .any(|c| c == '&' || c.is_whitespace() || c.is_control());
+    hidden || scheme.eq_ignore_ascii_case("javascript")
+}
+
+/// Decodes numeric character references such as `&#106;` and `&#x6A;`, plus `&colon;`, in an
+/// attribute value.
+fn decode_character_references(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.len() >= 7 && rest[..7].eq_ignore_ascii_case("&colon;") {
+            decoded.push(':');
+            rest = &rest[7..];
+            continue;
+        }
+        let (digits, radix) = match rest.as_bytes().get(1..3) {
+            Some([b'#', b'x' | b'X']) => (&rest[3..], 16),
+            Some([b'#', _]) => (&rest[2..], 10),
+            _ => ("", 10),
+        };
+        let len = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        match u32::from_str_radix(&digits[..len], radix)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                decoded.push(c);
+                // The terminating `;` is optional
+                let after = &digits[len..];
+                rest = after.strip_prefix(';').unwrap_or(after);
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{RenderSettings, parse_markdown};
+
+    fn render_sanitized(markdown: &str) -> String {
+        let _settings = RenderSettings {
+            sanitize_html: true,
+            ..RenderSettings::default()
+        }
+        .scoped();
+        parse_markdown(markdown)
+    }
+
+    #[test]
+    fn removes_inline_script_with_its_text() {
+        let html = render_sanitized("Hello <script>alert(1)</script> world");
+        assert!(!html.contains("alert"), "{html}");
+        assert!(html.contains("Hello  world"), "{html}");
+    }
+
+    #[test]
+    fn removes_script_block_with_its_text() {
+        let html = render_sanitized("<script>\nalert(1)\n</script>\n\nAfter");
+        assert!(!html.contains("alert"), "{html}");
+        assert!(!html.contains("script"), "{html}");
+        assert!(html.contains("After"), "{html}");
+    }
+
+    #[test]
+    fn removes_script_text_after_a_blank_line() {
+        let html = render_sanitized("Text <script>\nalert(1)\n\nsteal()\n</script>\n\nAfter");
+        assert!(!html.contains("alert"), "{html}");
+        assert!(!html.contains("steal"), "{html}");
+        assert!(html.contains("After"), "{html}");
+    }
+
+    #[test]
+    fn strips_javascript_urls_hidden_by_references() {
+        for url in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java&#x09;script:alert(1)",
+            "java&Tab;script:alert(1)",
+            "java&NewLine;script&colon;alert(1)",
+            "&#106;avascript:alert(1)",
+            " javascript:alert(1)",
+        ] {
+            let html = render_sanitized(&format!("<a href=\"{url}\">link</a>"));
+            assert!(html.contains("<a>link</a>"), "{url}: {html}");
+        }
+        for url in ["https://example.com/?a=1&b=2", "page.html?x=a:b", "#top"] {
+            let html = render_sanitized(&format!("<a href=\"{url}\">link</a>"));
+            assert!(html.contains(&format!("href=\"{url}\"")), "{url}: {html}");
+        }
+    }
+
+    #[test]
+    fn drops_redirects_and_svg_animations() {
+        let html = render_sanitized(concat!(
+            "<meta http-equiv=\"refresh\" content=\"0;url=javascript:alert(1)\">\n\n",
+            "<base href=\"https://evil.example/\">\n\n",
+            "<svg><a><animate attributeName=href values=\"javascript:alert(1)\"/>",
+            "<set attributeName=href to=\"javascript:alert(1)\"/><text>x</text></a></svg>",
+        ));
+        assert!(!html.contains("javascript"), "{html}");
+        assert!(!html.contains("<meta"), "{html}");
+        assert!(!html.contains("<base"), "{html}");
+        assert!(html.contains("<text>x</text>"), "{html}");
+    }
+
+    #[test]
+    fn keeps_details() {
+        let html = render_sanitized(
+            "<details open>\n<summary>More</summary>\n\nHidden *text*\n\n</details>",
+        );
+        assert!(html.contains("<details open>"), "{html}");
+        assert!(html.contains("<summary>More</summary>"), "{html}");
+        assert!(html.contains("<em>text</em>"), "{html}");
+        assert!(html.contains("</details>"), "{html}");
+    }
+
+    #[test]
+    fn strips_event_handler_attributes() {
+        let html = render_sanitized(
+            "<div onclick=\"steal()\" class=\"note\">\n\nText <img src=\"a.png\" onerror=\"steal()\">\n\n</div>",
+        );
+        assert!(!html.contains("steal"), "{html}");
+        assert!(html.contains(r#"<div class="note">"#), "{html}");
+        assert!(html.contains(r#"<img src="a.png">"#), "{html}");
+    }
+}
@@ -0,0 +1,113 @@
+//! The renderer's process-wide settings taken together, so one render can use its own and put
+//! the previous ones back afterwards.
+
+use std::sync::{Mutex, MutexGuard};
+
+use super::code_colors::{code_background, set_code_background};
+use super::dialect::{Dialect, dialect, set_dialect};
+use super::parser::{
+    autolinks, client_side_highlighting, code_fold_lines, emoji_shortcodes, inline_code_language,
+    set_autolinks, set_client_side_highlighting, set_code_fold_lines, set_emoji_shortcodes,
+    set_inline_code_language, set_smart_punctuation, set_syntax_theme, smart_punctuation,
+    syntax_theme,
+};
+use super::sanitize::{sanitize_html_enabled, set_sanitize_html};
+
+/// Held while a render runs with its own settings.
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Every setting that changes how markdown renders. The defaults are the viewer's defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub dialect: Dialect,
+    pub smart_punctuation: bool,
+    pub autolinks: bool,
+    pub emoji_shortcodes: bool,
+    pub sanitize_html: bool,
+    /// Built-in syntax theme, or the light/dark defaults for `None`
+    pub syntax_theme: Option<String>,
+    /// Code block background as `#rrggbb`, or the theme's for `None`
+    pub code_background: Option<String>,
+    pub client_side_highlighting: bool,
+    /// Line count past which code blocks start folded; 0 never folds
+    pub code_fold_lines: usize,
+    /// Language inline code is highlighted as
+    pub inline_code_language: Option<String>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            dialect: Dialect::default(),
+            smart_punctuation: false,
+            autolinks: true,
+            emoji_shortcodes: true,
+            sanitize_html: false,
+            syntax_theme: None,
+            code_background: None,
+            client_side_highlighting: false,
+            code_fold_lines: 0,
+            inline_code_language: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Returns the settings renders currently use.
+    pub fn current() -> Self {
+        Self {
+            dialect: dialect(),
+            smart_punctuation: smart_punctuation(),
+            autolinks: autolinks(),
+            emoji_shortcodes: emoji_shortcodes(),
+            sanitize_html: sanitize_html_enabled(),
+            syntax_theme: syntax_theme(),
+            code_background: code_background(),
+            client_side_highlighting: client_side_highlighting(),
+            code_fold_lines: code_fold_lines(),
+            inline_code_language: inline_code_language(),
+        }
+    }
+
+    /// Makes these the settings every render uses from now on.
+    pub fn apply(&self) {
+        set_dialect(self.dialect);
+        set_smart_punctuation(self.smart_punctuation);
+        set_autolinks(self.autolinks);
+        set_emoji_shortcodes(self.emoji_shortcodes);
+        set_sanitize_html(self.sanitize_html);
+        set_syntax_theme(self.syntax_theme.clone());
+        set_code_background(self.code_background.as_deref());
+        set_client_side_highlighting(self.client_side_highlighting);
+        set_code_fold_lines(self.code_fold_lines);
+        set_inline_code_language(self.inline_code_language.clone());
+    }
+
+    /// Applies these settings until the returned guard is dropped, which restores the previous
+    /// ones.
+    ///
+    /// Guards are taken one at a time, so renders through `scoped` settings never see each
+    /// other's. Rendering on another thread without a guard still sees whichever settings are
+    /// in effect.
+    pub fn scoped(&self) -> ScopedSettings {
+        let lock = SETTINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = Self::current();
+        self.apply();
+        ScopedSettings {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+/// Settings applied by [`RenderSettings::scoped`], restored when this is dropped.
+pub struct ScopedSettings {
+    previous: RenderSettings,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for ScopedSettings {
+    fn drop(&mut self) {
+        self.previous.apply();
+    }
+}
@@ -0,0 +1,91 @@
+//! Table cells with the column alignment given in the delimiter row (`:---`, `:---:`, `---:`).
+//!
+//! The HTML writer only knows which cells are header cells and how they are aligned while it
+//! sees the whole table, and events are rendered one at a time, so that state is kept here.
+
+use pulldown_cmark::Alignment;
+
+/// Position within the table being rendered.
+#[derive(Debug, Default)]
+pub struct TableCells {
+    alignments: Vec<Alignment>,
+    in_head: bool,
+    /// Column of the next cell in the current row
+    column: usize,
+}
+
+impl TableCells {
+    /// Starts a table with the given column alignments.
+    pub fn start_table(&mut self, alignments: Vec<Alignment>) {
+        self.alignments = alignments;
+        self.in_head = false;
+        self.column = 0;
+    }
+
+    /// Starts the header row or a body row.
+    pub fn start_row(&mut self, head: bool) {
+        self.in_head = head;
+        self.column = 0;
+    }
+
+    /// Returns the opening tag of the next cell, aligned like its column.
+    pub fn start_cell(&self) -> String {
+        let element = self.element();
+        match self.alignments.get(self.column) {
+            Some(Alignment::Left) => format!("<{element} style=\"text-align: left\">"),
+            Some(Alignment::Center) => format!("<{element} style=\"text-align: center\">"),
+            Some(Alignment::Right) => format!("<{element} style=\"text-align: right\">"),
+            Some(Alignment::None) | None => format!("<{element}>"),
+        }
+    }
+
+    /// Returns the closing tag of the current cell and moves on to the next column.
+    pub fn end_cell(&mut self) -> String {
+        self.column += 1;
+        format!("</{}>", self.element())
+    }
+
+    fn element(&self) -> &'static str {
+        if self.in_head { "th" } else { "td" }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{RenderSettings, parse_markdown};
+
+    #[test]
+    fn cells_are_th_and_td_aligned_like_their_column() {
+        let _settings = RenderSettings::default().scoped();
+        let html = parse_markdown(
+            "| Left | Center | Right | None |\n|:-----|:------:|------:|------|\n| 1 | 2 | 3 | 4 |\n",
+        );
+
+        assert!(
+            html.contains(r#"<th style="text-align: left">Left</th>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<th style="text-align: center">Center</th>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<th style="text-align: right">Right</th>"#),
+            "{html}"
+        );
+        assert!(html.contains("<th>None</th>"), "{html}");
+        assert!(
+            html.contains(r#"<td style="text-align: left">1</td>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<td style="text-align: center">2</td>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<td style="text-align: right">3</td>"#),
+            "{html}"
+        );
+        assert!(html.contains("<td>4</td>"), "{html}");
+    }
+}
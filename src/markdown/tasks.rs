@@ -0,0 +1,39 @@
+//! Editing GFM task list items (`- [ ]` / `- [x]`) in markdown source.
+
+const TASK_MARKERS: [&str; 3] = ["[ ]", "[x]", "[X]"];
+
+/// Sets the task marker on the given zero-based source line to `checked`.
+///
+/// Line endings and all other text are preserved. Returns `None` if the line does not exist
+/// or contains no task marker.
+pub fn set_task_checked(source: &str, line: usize, checked: bool) -> Option<String> {
+    let mut output = String::with_capacity(source.len());
+    let mut found = false;
+
+    for (index, text) in source.split_inclusive('\n').enumerate() {
+        if index == line {
+            output.push_str(&set_marker(text, checked)?);
+            found = true;
+        } else {
+            output.push_str(text);
+        }
+    }
+
+    found.then_some(output)
+}
+
+/// Rewrites the first task marker in a line. Only list markers, blockquote markers and
+/// indentation can precede a task marker, so the first match is always the right one.
+fn set_marker(line: &str, checked: bool) -> Option<String> {
+    let position = TASK_MARKERS
+        .iter()
+        .filter_map(|marker| line.find(marker))
+        .min()?;
+    let mark = if checked { 'x' } else { ' ' };
+
+    Some(format!(
+        "{}[{mark}]{}",
+        &line[..position],
+        &line[position + 3..]
+    ))
+}
@@ -0,0 +1,49 @@
+//! Obsidian-style `[[Page Name]]` and `[[Page Name|alias]]` links.
+
+use log::warn;
+use std::sync::OnceLock;
+
+/// Prefix for wiki link targets given with `--wiki-base`. Without one, targets are relative to
+/// the document's directory.
+static WIKI_BASE: OnceLock<String> = OnceLock::new();
+
+/// Resolves wiki links against `base` (e.g. `file:///Users/me/vault/`).
+pub fn set_wiki_base(base: &str) {
+    let base = if base.ends_with('/') {
+        base.to_string()
+    } else {
+        format!("{base}/")
+    };
+    if WIKI_BASE.set(base).is_err() {
+        warn!("Wiki link base already set, ignoring it");
+    }
+}
+
+/// Returns the href for a wiki link target such as `Page Name` or `Page Name#Section`.
+pub fn wiki_href(target: &str) -> String {
+    let (page, section) = match target.split_once('#') {
+        Some((page, section)) => (page, Some(section)),
+        None => (target, None),
+    };
+    let base = WIKI_BASE.get().map_or("", String::as_str);
+
+    let mut href = if page.trim().is_empty() {
+        String::new() // `[[#Section]]` links within the current page
+    } else {
+        format!("{base}{}.md", slugify(page))
+    };
+    if let Some(section) = section {
+        href.push('#');
+        href.push_str(&slugify(section));
+    }
+    href
+}
+
+/// Lowercases a page name and joins its words with hyphens: `My Page!` becomes `my-page`.
+fn slugify(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
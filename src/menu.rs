@@ -1,21 +1,86 @@
+#![allow(unexpected_cfgs)] // Suppress objc crate cfg warnings
+
 use cacao::appkit::menu::{Menu, MenuItem};
+use cacao::events::EventModifierFlag;
 use log::{debug, error};
+use objc::{msg_send, sel, sel_impl};
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::mpsc;
 
-use crate::gui::types::{FontFamily, ThemeMode};
+use crate::gui::{
+    self,
+    types::{FontFamily, ThemeMode},
+};
+use crate::markdown;
+use crate::recent_files;
 
 #[derive(Debug)]
 pub enum MenuMessage {
     ToggleMode,
     Copy,
+    CopyMarkdown,
+    CopyHtml,
     SelectAll,
     SetFontFamily(FontFamily),
     IncreaseFontSize,
     DecreaseFontSize,
     ResetFontSize,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    EnlargeDiagrams,
+    ShrinkDiagrams,
+    ResetDiagramSize,
+    NextHeading,
+    PrevHeading,
+    CycleContentWidth,
+    CycleLineHeight,
+    CycleTabWidth,
+    ToggleClientSideHighlighting,
+    ToggleStats,
+    ToggleSmartPunctuation,
+    ToggleAutolinks,
+    ToggleEmojiShortcodes,
+    ToggleAutoScroll,
+    ToggleTypewriter,
+    ToggleLineNumbers,
+    ToggleWhitespace,
     SetTheme(ThemeMode),
+    CycleTheme,
+    /// Highlight code with the named built-in theme, or the default for the theme when `None`.
+    SetSyntaxTheme(Option<String>),
+    ToggleFullScreen,
+    ReloadConfig,
+    Reload,
+    /// A task checkbox on the given source line was clicked in the preview.
+    SetTaskChecked {
+        line: usize,
+        checked: bool,
+    },
+    Print,
+    SaveAs,
+    /// The page is missing streamed content and should be rebuilt.
+    ResyncContent,
+    /// Empty the view, e.g. between runs of a long streaming session.
+    Clear,
+    /// The page gave up appending a streamed chunk; the whole page should be reloaded.
+    AppendFailed,
+    /// Choose a markdown file to show with the open panel.
+    Open,
+    OpenFile(String),
+    ClearRecentFiles,
+    /// A link was clicked in the page.
+    OpenLink(String),
+    ToggleInternalLinks,
+    ToggleConfirmLinks,
+    /// Open the Settings window, or bring it to the front.
+    ShowSettings,
+    /// A popup in the Settings window was changed.
+    SettingsChanged,
+    /// The Settings window closed; its settings should be saved.
+    SettingsClosed,
 }
 
 use std::sync::LazyLock;
@@ -41,6 +106,9 @@ pub fn dispatch_menu_message(message: MenuMessage) {
                     Ok(_) => debug!("Message sent successfully"),
                     Err(e) => error!("Failed to send message: {e:?}"),
                 }
+                // Messages from the web view or a panel don't always come with an event that
+                // updates the app, and without polling nothing else would pick them up
+                gui::wake_main_thread();
             } else {
                 error!("MENU_SENDER is None!");
             }
@@ -51,6 +119,59 @@ pub fn dispatch_menu_message(message: MenuMessage) {
     }
 }
 
+/// Builds the File → Open Recent submenu from the remembered paths.
+fn open_recent_menu() -> MenuItem {
+    let mut items: Vec<MenuItem> = recent_files::recent_files()
+        .into_iter()
+        .map(|path| {
+            let title = Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            MenuItem::new(title).action(move || {
+                dispatch_menu_message(MenuMessage::OpenFile(path.clone()));
+            })
+        })
+        .collect();
+    items.push(MenuItem::Separator);
+    items.push(MenuItem::new("Clear Menu").action(|| {
+        dispatch_menu_message(MenuMessage::ClearRecentFiles);
+    }));
+
+    let item = MenuItem::new("Open Recent");
+    if let MenuItem::Custom(objc) = &item {
+        let submenu = Menu::new("Open Recent", items);
+        unsafe {
+            let _: () = msg_send![&**objc, setSubmenu: &*submenu.0];
+        }
+    }
+    item
+}
+
+/// Builds the View → Syntax Theme submenu from the built-in syntect themes.
+fn syntax_theme_menu() -> MenuItem {
+    let mut items = vec![
+        MenuItem::new("Automatic").action(|| {
+            dispatch_menu_message(MenuMessage::SetSyntaxTheme(None));
+        }),
+        MenuItem::Separator,
+    ];
+    items.extend(markdown::syntax_theme_names().into_iter().map(|name| {
+        MenuItem::new(name.clone()).action(move || {
+            dispatch_menu_message(MenuMessage::SetSyntaxTheme(Some(name.clone())));
+        })
+    }));
+
+    let item = MenuItem::new("Syntax Theme");
+    if let MenuItem::Custom(objc) = &item {
+        let submenu = Menu::new("Syntax Theme", items);
+        unsafe {
+            let _: () = msg_send![&**objc, setSubmenu: &*submenu.0];
+        }
+    }
+    item
+}
+
 pub fn create_menus() -> Vec<Menu> {
     vec![
         // App menu
@@ -59,6 +180,13 @@ pub fn create_menus() -> Vec<Menu> {
             vec![
                 MenuItem::About("Homo".to_string()),
                 MenuItem::Separator,
+                MenuItem::new("Settings…").key(",").action(|| {
+                    dispatch_menu_message(MenuMessage::ShowSettings);
+                }),
+                MenuItem::new("Reload Config").action(|| {
+                    dispatch_menu_message(MenuMessage::ReloadConfig);
+                }),
+                MenuItem::Separator,
                 MenuItem::Quit,
             ],
         ),
@@ -67,7 +195,20 @@ pub fn create_menus() -> Vec<Menu> {
             "File",
             vec![
                 MenuItem::new("New").key("n"),
-                MenuItem::new("Open...").key("o"),
+                MenuItem::new("Open…").key("o").action(|| {
+                    dispatch_menu_message(MenuMessage::Open);
+                }),
+                open_recent_menu(),
+                MenuItem::Separator,
+                MenuItem::new("Save As…")
+                    .key("S")
+                    .modifiers(&[EventModifierFlag::Command])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::SaveAs);
+                    }),
+                MenuItem::new("Print…").key("p").action(|| {
+                    dispatch_menu_message(MenuMessage::Print);
+                }),
                 MenuItem::Separator,
                 MenuItem::CloseWindow,
             ],
@@ -79,10 +220,25 @@ pub fn create_menus() -> Vec<Menu> {
                 MenuItem::new("Copy").key("c").action(|| {
                     dispatch_menu_message(MenuMessage::Copy);
                 }),
+                MenuItem::new("Copy as Markdown")
+                    .key("C")
+                    .modifiers(&[EventModifierFlag::Command])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::CopyMarkdown);
+                    }),
+                MenuItem::new("Copy as HTML")
+                    .key("C")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Option])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::CopyHtml);
+                    }),
                 MenuItem::Separator,
                 MenuItem::new("Select All").key("a").action(|| {
                     dispatch_menu_message(MenuMessage::SelectAll);
                 }),
+                MenuItem::new("Clear")
+                    .key("k")
+                    .action(|| dispatch_menu_message(MenuMessage::Clear)),
             ],
         ),
         // View menu
@@ -92,6 +248,41 @@ pub fn create_menus() -> Vec<Menu> {
                 MenuItem::new("Toggle Mode").key("t").action(|| {
                     dispatch_menu_message(MenuMessage::ToggleMode);
                 }),
+                MenuItem::new("Reload").key("r").action(|| {
+                    dispatch_menu_message(MenuMessage::Reload);
+                }),
+                MenuItem::new("Show Word Count").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleStats);
+                }),
+                MenuItem::new("Toggle Auto-scroll").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleAutoScroll);
+                }),
+                MenuItem::new("Toggle Typewriter Effect").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleTypewriter);
+                }),
+                MenuItem::new("Toggle Source Line Numbers").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleLineNumbers);
+                }),
+                MenuItem::new("Toggle Invisible Characters").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleWhitespace);
+                }),
+                MenuItem::new("Toggle Markdown Links in HOMO").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleInternalLinks);
+                }),
+                MenuItem::new("Toggle Link Confirmation").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleConfirmLinks);
+                }),
+                MenuItem::Separator,
+                // The arrow keys' function-key characters, NSDownArrowFunctionKey and
+                // NSUpArrowFunctionKey
+                MenuItem::new("Next Heading").key("\u{F701}").action(|| {
+                    dispatch_menu_message(MenuMessage::NextHeading);
+                }),
+                MenuItem::new("Previous Heading")
+                    .key("\u{F700}")
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::PrevHeading);
+                    }),
                 MenuItem::Separator,
                 MenuItem::new("System Font").key("1").action(|| {
                     dispatch_menu_message(MenuMessage::SetFontFamily(FontFamily::System));
@@ -115,16 +306,93 @@ pub fn create_menus() -> Vec<Menu> {
                 MenuItem::new("System Theme").key("s").action(|| {
                     dispatch_menu_message(MenuMessage::SetTheme(ThemeMode::System));
                 }),
+                MenuItem::new("Cycle Theme")
+                    .key("T")
+                    .modifiers(&[EventModifierFlag::Command])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::CycleTheme);
+                    }),
+                syntax_theme_menu(),
+                MenuItem::Separator,
+                MenuItem::new("Enter Full Screen")
+                    .key("f")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Control])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::ToggleFullScreen);
+                    }),
+                MenuItem::Separator,
+                MenuItem::new("Cycle Content Width")
+                    .key("w")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Option])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::CycleContentWidth);
+                    }),
+                MenuItem::new("Cycle Line Height").action(|| {
+                    dispatch_menu_message(MenuMessage::CycleLineHeight);
+                }),
+                MenuItem::new("Cycle Tab Width").action(|| {
+                    dispatch_menu_message(MenuMessage::CycleTabWidth);
+                }),
+                MenuItem::new("Toggle Live Code Highlighting").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleClientSideHighlighting);
+                }),
+                MenuItem::new("Toggle Smart Punctuation").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleSmartPunctuation);
+                }),
+                MenuItem::new("Toggle Autolinks").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleAutolinks);
+                }),
+                MenuItem::new("Toggle Emoji Shortcodes").action(|| {
+                    dispatch_menu_message(MenuMessage::ToggleEmojiShortcodes);
+                }),
                 MenuItem::Separator,
-                MenuItem::new("Increase Font Size").key("=").action(|| {
-                    dispatch_menu_message(MenuMessage::IncreaseFontSize);
+                MenuItem::new("Zoom In").key("=").action(|| {
+                    dispatch_menu_message(MenuMessage::ZoomIn);
                 }),
-                MenuItem::new("Decrease Font Size").key("-").action(|| {
-                    dispatch_menu_message(MenuMessage::DecreaseFontSize);
+                MenuItem::new("Zoom Out").key("-").action(|| {
+                    dispatch_menu_message(MenuMessage::ZoomOut);
                 }),
-                MenuItem::new("Reset Font Size").key("0").action(|| {
-                    dispatch_menu_message(MenuMessage::ResetFontSize);
+                MenuItem::new("Actual Size").key("0").action(|| {
+                    dispatch_menu_message(MenuMessage::ZoomReset);
                 }),
+                MenuItem::Separator,
+                MenuItem::new("Enlarge Diagrams")
+                    .key("=")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Control])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::EnlargeDiagrams);
+                    }),
+                MenuItem::new("Shrink Diagrams")
+                    .key("-")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Control])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::ShrinkDiagrams);
+                    }),
+                MenuItem::new("Actual Diagram Size")
+                    .key("0")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Control])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::ResetDiagramSize);
+                    }),
+                MenuItem::Separator,
+                MenuItem::new("Increase Font Size")
+                    .key("=")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Option])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::IncreaseFontSize);
+                    }),
+                MenuItem::new("Decrease Font Size")
+                    .key("-")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Option])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::DecreaseFontSize);
+                    }),
+                MenuItem::new("Reset Font Size")
+                    .key("0")
+                    .modifiers(&[EventModifierFlag::Command, EventModifierFlag::Option])
+                    .action(|| {
+                        dispatch_menu_message(MenuMessage::ResetFontSize);
+                    }),
             ],
         ),
         // Window menu
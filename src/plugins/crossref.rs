@@ -0,0 +1,357 @@
+//! Numbered figures and equations with `@fig:label` references, enabled with `--crossref`.
+//!
+//! Labels follow pandoc-crossref: `![Caption](plot.png){#fig:plot}` turns an image into a
+//! captioned figure, `$$E = mc^2$$ {#eq:energy}` numbers a display equation, and a paragraph
+//! `Figure: Caption {#fig:flow}`, e.g. below a diagram, captions what precedes it. `@fig:plot`
+//! and `@eq:energy` then read "Figure 1" and "Equation 1".
+//!
+//! This plugin only marks labels and references up; the page numbers them in document order.
+//! Streamed content is only ever appended, so numbers already shown never change.
+
+use crate::markdown;
+use crate::plugins::{Plugin, PluginContext, PluginResult};
+
+/// Label kinds, by prefix: `fig` for figures and `eq` for equations
+const KINDS: [&str; 2] = ["fig", "eq"];
+
+/// Elements whose text is left alone when linking references
+const SKIPPED_ELEMENTS: [&str; 5] = ["a", "code", "pre", "script", "style"];
+
+/// Figure and equation numbering plugin
+pub struct CrossRefPlugin {
+    initialized: bool,
+}
+
+impl CrossRefPlugin {
+    pub fn new() -> Self {
+        Self { initialized: false }
+    }
+}
+
+impl Default for CrossRefPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns whether a character may appear in a label such as `fig:data-flow_2`.
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Parses a `kind:label` id at the start of `text`, returning the id.
+fn id_at(text: &str) -> Option<&str> {
+    let kind = KINDS.iter().find(|kind| {
+        text.strip_prefix(**kind)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })?;
+    let label = &text[kind.len() + 1..];
+    let len = label.find(|c| !is_label_char(c)).unwrap_or(label.len());
+    (len > 0).then(|| &text[..kind.len() + 1 + len])
+}
+
+/// Parses a `{#kind:label}` attribute at the start of `text`, returning the id and the
+/// attribute's length.
+fn attribute_at(text: &str) -> Option<(&str, usize)> {
+    let id = id_at(text.strip_prefix("{#")?)?;
+    text[2 + id.len()..]
+        .starts_with('}')
+        .then_some((id, id.len() + 3))
+}
+
+/// Returns the kind of an id such as `fig:plot`.
+fn kind(id: &str) -> &str {
+    id.split(':').next().unwrap_or_default()
+}
+
+/// Empty element the page fills with the number, e.g. "Figure 2:" or "(3)"
+fn number_slot(id: &str) -> String {
+    format!(
+        "<span class=\"crossref-number\" data-crossref=\"{}\"></span>",
+        kind(id)
+    )
+}
+
+/// Turns `<p><img … />{#fig:label}</p>` into a figure captioned with the image's alt text.
+fn image_figures(html: &str) -> String {
+    const START: &str = "<p><img ";
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(START) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let figure = rest.find("/>").and_then(|tag_end| {
+            let image = &rest[3..tag_end + 2];
+            let (id, len) = attribute_at(&rest[tag_end + 2..])?;
+            let after = &rest[tag_end + 2 + len..];
+            if kind(id) != "fig" || !after.starts_with("</p>") {
+                return None;
+            }
+            let caption = image
+                .split_once(" alt=\"")
+                .and_then(|(_, alt)| alt.split_once('"'))
+                .map_or("", |(alt, _)| alt);
+            let html = format!(
+                "<figure class=\"crossref-figure\" id=\"{id}\">{image}<figcaption>{} {caption}</figcaption></figure>",
+                number_slot(id)
+            );
+            Some((html, tag_end + 2 + len + "</p>".len()))
+        });
+        match figure {
+            Some((figure, len)) => {
+                output.push_str(&figure);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push_str(START);
+                rest = &rest[START.len()..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Turns a `<p>Figure: Caption {#fig:label}</p>` paragraph into a numbered caption.
+fn caption_paragraphs(html: &str) -> String {
+    const START: &str = "<p>Figure: ";
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(START) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let caption = rest.find("</p>").and_then(|end| {
+            let text = &rest[START.len()..end];
+            let attribute = text.rfind("{#")?;
+            let (id, len) = attribute_at(&text[attribute..])?;
+            if kind(id) != "fig" || attribute + len != text.len() {
+                return None;
+            }
+            let html = format!(
+                "<p class=\"crossref-caption\" id=\"{id}\">{} {}</p>",
+                number_slot(id),
+                text[..attribute].trim_end()
+            );
+            Some((html, end + "</p>".len()))
+        });
+        match caption {
+            Some((caption, len)) => {
+                output.push_str(&caption);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push_str(START);
+                rest = &rest[START.len()..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Numbers display math followed by `{#eq:label}`.
+fn numbered_equations(html: &str) -> String {
+    const START: &str = "<span class=\"latex-math math-display";
+    const END: &str = "></span>";
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(START) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(END).map(|end| end + END.len()) else {
+            break;
+        };
+        let math = &rest[..end];
+        let after = &rest[end..];
+        let spaces = after.len() - after.trim_start().len();
+        match attribute_at(&after[spaces..]).filter(|(id, _)| kind(id) == "eq") {
+            Some((id, len)) => {
+                output.push_str(&format!(
+                    "<span class=\"crossref-equation\" id=\"{id}\">{math}{}</span>",
+                    number_slot(id)
+                ));
+                rest = &rest[end + spaces + len..];
+            }
+            None => {
+                output.push_str(math);
+                rest = &rest[end..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Returns the length of the tag at the start of `html`, up to and including its `>`. Quoted
+/// attribute values, such as a diagram's source, may contain `>`.
+fn tag_len(html: &str) -> usize {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return index + 1,
+            (None, _) => {}
+        }
+    }
+    html.len()
+}
+
+/// Links `@fig:label` and `@eq:label` in text to what they refer to. The page replaces the
+/// link text with the number once the target is on the page.
+fn link_references(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut skipped_depth = 0usize;
+    // Mermaid diagrams keep their source as the text of a `<div class="mermaid">`
+    let mut in_diagram = false;
+    let mut rest = html;
+    while let Some(start) = rest.find(['<', '@']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with('<') {
+            let tag = &rest[..tag_len(rest)];
+            let closing = tag.starts_with("</");
+            let name: String = tag
+                .trim_start_matches("</")
+                .trim_start_matches('<')
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if SKIPPED_ELEMENTS.contains(&name.as_str()) && !tag.ends_with("/>") {
+                if closing {
+                    skipped_depth = skipped_depth.saturating_sub(1);
+                } else {
+                    skipped_depth += 1;
+                }
+            } else if name == "div" && !closing && tag.contains("class=\"mermaid\"") {
+                in_diagram = true;
+            } else if name == "div" && closing {
+                in_diagram = false;
+            }
+            output.push_str(tag);
+            rest = &rest[tag.len()..];
+            continue;
+        }
+
+        let after_word = output
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        match id_at(&rest[1..]) {
+            Some(id) if skipped_depth == 0 && !in_diagram && !after_word => {
+                output.push_str(&format!(
+                    "<a class=\"crossref\" href=\"#{id}\" data-crossref-target=\"{id}\">@{id}</a>"
+                ));
+                rest = &rest[1 + id.len()..];
+            }
+            _ => {
+                output.push('@');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+impl Plugin for CrossRefPlugin {
+    fn name(&self) -> &'static str {
+        "crossref"
+    }
+
+    fn version(&self) -> &'static str {
+        "1.0.0"
+    }
+
+    fn handles_language(&self, _language: &str) -> bool {
+        false // Works on whole documents, not code blocks
+    }
+
+    fn process_code_block(
+        &self,
+        _content: &str,
+        _language: &str,
+        _context: &PluginContext,
+    ) -> Option<PluginResult> {
+        None
+    }
+
+    fn process_document(&self, html: &str, _context: &PluginContext) -> Option<String> {
+        if !markdown::dialect().crossref || !(html.contains("{#") || html.contains('@')) {
+            return None;
+        }
+        let html = image_figures(html);
+        let html = caption_paragraphs(&html);
+        let html = numbered_equations(&html);
+        Some(link_references(&html))
+    }
+
+    fn get_javascript(&self, _context: &PluginContext) -> Option<String> {
+        if !markdown::dialect().crossref {
+            return None;
+        }
+        let javascript = r#"
+// Cross-reference Plugin JavaScript
+
+// Numbers figures and equations in document order and fills in the references to them. Runs
+// again after every append; content is only added at the end, so shown numbers stay put.
+window.numberCrossReferences = function() {
+    const names = { fig: 'Figure', eq: 'Equation' };
+    const counts = {};
+    const references = {};
+    document.querySelectorAll('[data-crossref]').forEach((slot) => {
+        const kind = slot.getAttribute('data-crossref');
+        counts[kind] = (counts[kind] || 0) + 1;
+        const number = counts[kind];
+        slot.textContent = kind === 'eq' ? '(' + number + ')' : names[kind] + ' ' + number + ':';
+        const target = slot.closest('[id]');
+        if (target) {
+            references[target.id] = names[kind] + ' ' + number;
+        }
+    });
+    document.querySelectorAll('a.crossref').forEach((link) => {
+        const reference = references[link.getAttribute('data-crossref-target')];
+        if (reference) {
+            link.textContent = reference;
+        }
+        // A target that hasn't streamed in yet keeps the link as written
+        link.classList.toggle('crossref-missing', !reference);
+    });
+};
+
+if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', window.numberCrossReferences);
+} else {
+    window.numberCrossReferences();
+}
+"#;
+
+        Some(javascript.to_string())
+    }
+
+    fn get_css(&self, _context: &PluginContext) -> Option<String> {
+        None // Caption styles are part of the page stylesheet
+    }
+
+    fn get_external_scripts(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Initializing cross-reference plugin v{}", self.version());
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Shutting down cross-reference plugin");
+        self.initialized = false;
+        Ok(())
+    }
+}
@@ -1,14 +1,66 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+
+use crate::config;
 use crate::gui::types::ThemeMode;
-use crate::plugins::{Plugin, PluginContext, PluginResult};
+use crate::plugins::{Plugin, PluginContext, PluginResult, cdn_url};
+
+/// Pinned KaTeX release; every page, including after a mode toggle, loads it from here
+const KATEX_DIST: &str = "katex@0.16.22/dist";
+
+/// File in the config directory holding a map of macro names to expansions, e.g.
+/// `{"\\R": "\\mathbb{R}"}`
+const MACROS_FILE_NAME: &str = "katex-macros.json";
 
 /// LaTeX/Math rendering plugin using KaTeX
 pub struct LatexPlugin {
     initialized: bool,
+    /// KaTeX macros from `katex-macros.json`, merged over the built-in ones
+    file_macros: BTreeMap<String, String>,
+    /// Extra KaTeX macros from the user config, merged over the built-in ones and the file's
+    custom_macros: BTreeMap<String, String>,
 }
 
 impl LatexPlugin {
     pub fn new() -> Self {
-        Self { initialized: false }
+        Self {
+            initialized: false,
+            file_macros: BTreeMap::new(),
+            custom_macros: BTreeMap::new(),
+        }
+    }
+}
+
+/// Reads the macros in `~/.config/homo/katex-macros.json`. A missing file means no macros; an
+/// unreadable or malformed one is reported and ignored, leaving the built-in macros.
+fn load_macros_file() -> BTreeMap<String, String> {
+    let Some(path) = config::config_dir().map(|dir| dir.join(MACROS_FILE_NAME)) else {
+        return BTreeMap::new();
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return BTreeMap::new(),
+        Err(e) => {
+            log::warn!("Couldn't read KaTeX macros from {}: {e}", path.display());
+            return BTreeMap::new();
+        }
+    };
+    match serde_json::from_str(&contents) {
+        Ok(macros) => macros,
+        Err(e) => {
+            log::warn!(
+                "Ignoring KaTeX macros in {}, which should map macro names to expansions: {e}",
+                path.display()
+            );
+            BTreeMap::new()
+        }
+    }
+}
+
+impl Default for LatexPlugin {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -126,6 +178,11 @@ impl Plugin for LatexPlugin {
             }
         };
 
+        let file_macros =
+            serde_json::to_string(&self.file_macros).unwrap_or_else(|_| "{}".to_string());
+        let custom_macros =
+            serde_json::to_string(&self.custom_macros).unwrap_or_else(|_| "{}".to_string());
+
         let javascript = format!(
             r#"
 // LaTeX Plugin JavaScript
@@ -138,6 +195,7 @@ if (typeof katex !== 'undefined') {{
     window.katexOptions = {{
         {theme_config}
     }};
+    Object.assign(window.katexOptions.macros, {file_macros}, {custom_macros});
     
     // Function to render LaTeX expressions
     window.renderLatexExpressions = function() {{
@@ -319,6 +377,21 @@ window.renderNewLatexExpressions = function(container) {{
     vertical-align: middle;
 }
 
+/* `$...$` and `$$...$$` math flows with the surrounding text */
+.latex-math.math-text {
+    background: none;
+    border: none;
+    border-radius: 0;
+    padding: 0;
+    margin: 0;
+    min-height: 0;
+}
+
+.latex-math.math-display.math-text {
+    display: block;
+    margin: 16px 0;
+}
+
 .latex-raw {
     margin: 0;
 }
@@ -372,19 +445,47 @@ window.renderNewLatexExpressions = function(container) {{
     }
 
     fn get_external_scripts(&self) -> Vec<String> {
-        vec!["https://cdn.jsdelivr.net/npm/katex@0.16.22/dist/katex.min.js".to_string()]
+        vec![
+            cdn_url(&format!("{KATEX_DIST}/katex.min.js")),
+            // mhchem (`\ce{...}` chemical equations) registers itself with KaTeX, so it loads second
+            cdn_url(&format!("{KATEX_DIST}/contrib/mhchem.min.js")),
+        ]
     }
 
     fn get_external_css(&self) -> Vec<String> {
-        vec!["https://cdn.jsdelivr.net/npm/katex@0.16.22/dist/katex.min.css".to_string()]
+        vec![cdn_url(&format!("{KATEX_DIST}/katex.min.css"))]
     }
 
     fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         log::info!("Initializing LaTeX plugin v{}", self.version());
+        self.file_macros = load_macros_file();
+        if !self.file_macros.is_empty() {
+            log::info!(
+                "Loaded {} KaTeX macros from {MACROS_FILE_NAME}",
+                self.file_macros.len()
+            );
+        }
         self.initialized = true;
         Ok(())
     }
 
+    fn configure(
+        &mut self,
+        settings: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Expected shape: {"macros": {"\\R": "\\mathbb{R}"}}
+        let custom_macros = match settings.get("macros") {
+            Some(macros) => serde_json::from_value(macros.clone())?,
+            None => BTreeMap::new(),
+        };
+        log::info!(
+            "LaTeX plugin configured with {} custom macros",
+            custom_macros.len()
+        );
+        self.custom_macros = custom_macros;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         log::info!("Shutting down LaTeX plugin");
@@ -392,3 +493,25 @@ window.renderNewLatexExpressions = function(container) {{
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::manager::PluginManager;
+
+    #[test]
+    fn mhchem_loads_after_katex() {
+        let manager = PluginManager::new();
+        manager
+            .register_plugin(Box::new(LatexPlugin::new()))
+            .unwrap();
+        let scripts = manager.get_all_external_scripts();
+
+        let katex = scripts.iter().position(|s| s.ends_with("/katex.min.js"));
+        let mhchem = scripts
+            .iter()
+            .position(|s| s.ends_with("/contrib/mhchem.min.js"));
+        assert!(katex.is_some(), "{scripts:?}");
+        assert!(mhchem > katex, "{scripts:?}");
+    }
+}
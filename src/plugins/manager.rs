@@ -1,12 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::config::AppConfig;
 use crate::plugins::{Plugin, PluginContext, PluginResult};
 
 /// Plugin manager that handles registration and execution of plugins
 pub struct PluginManager {
     plugins: RwLock<Vec<Box<dyn Plugin>>>,
     language_map: RwLock<HashMap<String, usize>>, // Maps language to plugin index
+    /// Plugin named for a language, chosen over the first plugin claiming it
+    language_overrides: RwLock<HashMap<String, String>>,
+    disabled_plugins: RwLock<HashSet<String>>,
 }
 
 impl PluginManager {
@@ -15,9 +20,96 @@ impl PluginManager {
         Self {
             plugins: RwLock::new(Vec::new()),
             language_map: RwLock::new(HashMap::new()),
+            language_overrides: RwLock::new(HashMap::new()),
+            disabled_plugins: RwLock::new(HashSet::new()),
         }
     }
 
+    /// Returns whether the named plugin has not been disabled by the user config
+    fn is_enabled(&self, name: &str) -> bool {
+        self.disabled_plugins
+            .read()
+            .map(|disabled| !disabled.contains(name))
+            .unwrap_or(true)
+    }
+
+    /// Apply user configuration to all registered plugins
+    ///
+    /// A plugin that rejects its settings keeps its previous configuration.
+    pub fn apply_config(&self, config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let mut plugins = self
+            .plugins
+            .write()
+            .map_err(|_| "Failed to acquire plugins write lock")?;
+
+        for plugin in plugins.iter_mut() {
+            let settings = config
+                .plugins
+                .get(plugin.name())
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            if let Err(e) = plugin.configure(&settings) {
+                log::warn!("Plugin {} rejected its configuration: {}", plugin.name(), e);
+            }
+        }
+
+        let mut disabled = self
+            .disabled_plugins
+            .write()
+            .map_err(|_| "Failed to acquire disabled plugins write lock")?;
+        *disabled = config.disabled_plugins.iter().cloned().collect();
+
+        self.set_language_overrides(config.language_overrides.clone());
+
+        log::info!("Applied plugin configuration");
+        Ok(())
+    }
+
+    /// Makes the named plugin handle code blocks in `language`, even if another plugin
+    /// registered before it also claims the language, or it doesn't claim it at all.
+    pub fn set_language_override(&self, language: &str, plugin_name: &str) {
+        if let Ok(mut overrides) = self.language_overrides.write() {
+            overrides.insert(language.to_string(), plugin_name.to_string());
+        }
+        if let Ok(mut language_map) = self.language_map.write() {
+            language_map.remove(language);
+        }
+    }
+
+    /// Replaces all language overrides, e.g. with those of a reloaded config.
+    fn set_language_overrides(&self, overrides: HashMap<String, String>) {
+        if let Ok(mut language_overrides) = self.language_overrides.write() {
+            *language_overrides = overrides;
+        }
+        if let Ok(mut language_map) = self.language_map.write() {
+            language_map.clear();
+        }
+    }
+
+    /// Returns the index of the plugin that handles `language`: the one named by an override,
+    /// or else the first registered plugin claiming it.
+    fn resolve_plugin(&self, plugins: &[Box<dyn Plugin>], language: &str) -> Option<usize> {
+        let overridden = self
+            .language_overrides
+            .read()
+            .ok()
+            .and_then(|overrides| overrides.get(language).cloned());
+        if let Some(plugin_name) = overridden {
+            match plugins
+                .iter()
+                .position(|plugin| plugin.name() == plugin_name)
+            {
+                Some(index) => return Some(index),
+                None => log::warn!(
+                    "No plugin named {plugin_name} for {language} code blocks, using the default"
+                ),
+            }
+        }
+        plugins
+            .iter()
+            .position(|plugin| plugin.handles_language(language))
+    }
+
     /// Register a plugin with the manager
     pub fn register_plugin(
         &self,
@@ -57,21 +149,40 @@ impl PluginManager {
             && let Some(&plugin_index) = language_map.get(language)
             && let Some(plugin) = plugins.get(plugin_index)
         {
+            if !self.is_enabled(plugin.name()) {
+                return None;
+            }
             return plugin.process_code_block(content, language, context);
         }
 
-        // If no cached mapping, search through plugins
-        for (index, plugin) in plugins.iter().enumerate() {
-            if plugin.handles_language(language) {
-                // Cache this mapping for future use
-                if let Ok(mut language_map) = self.language_map.write() {
-                    language_map.insert(language.to_string(), index);
-                }
-                return plugin.process_code_block(content, language, context);
-            }
+        // If no cached mapping, resolve it, overrides first
+        let index = self.resolve_plugin(&plugins, language)?;
+        let plugin = &plugins[index];
+        if !self.is_enabled(plugin.name()) {
+            return None;
+        }
+        // Cache this mapping for future use
+        if let Ok(mut language_map) = self.language_map.write() {
+            language_map.insert(language.to_string(), index);
         }
+        plugin.process_code_block(content, language, context)
+    }
 
-        None
+    /// Runs every enabled plugin's document transform over rendered HTML
+    ///
+    /// Plugins run in the order they were registered, each seeing the previous one's output.
+    pub fn process_document(&self, html: String, context: &PluginContext) -> String {
+        let plugins = match self.plugins.read() {
+            Ok(plugins) => plugins,
+            Err(_) => return html,
+        };
+
+        plugins
+            .iter()
+            .filter(|p| self.is_enabled(p.name()))
+            .fold(html, |html, plugin| {
+                plugin.process_document(&html, context).unwrap_or(html)
+            })
     }
 
     /// Get all JavaScript from registered plugins
@@ -83,7 +194,7 @@ impl PluginManager {
 
         let mut all_js = Vec::new();
 
-        for plugin in plugins.iter() {
+        for plugin in plugins.iter().filter(|p| self.is_enabled(p.name())) {
             if let Some(js) = plugin.get_javascript(context) {
                 all_js.push(js);
             }
@@ -101,7 +212,7 @@ impl PluginManager {
 
         let mut all_css = Vec::new();
 
-        for plugin in plugins.iter() {
+        for plugin in plugins.iter().filter(|p| self.is_enabled(p.name())) {
             if let Some(css) = plugin.get_css(context) {
                 all_css.push(css);
             }
@@ -117,15 +228,17 @@ impl PluginManager {
             Err(_) => return Vec::new(),
         };
 
-        let mut all_scripts = Vec::new();
+        let mut all_scripts: Vec<String> = Vec::new();
 
-        for plugin in plugins.iter() {
-            all_scripts.extend(plugin.get_external_scripts());
+        for plugin in plugins.iter().filter(|p| self.is_enabled(p.name())) {
+            for script in plugin.get_external_scripts() {
+                // Remove duplicates, keeping load order: extensions follow the script they extend
+                if !all_scripts.contains(&script) {
+                    all_scripts.push(script);
+                }
+            }
         }
 
-        // Remove duplicates
-        all_scripts.sort();
-        all_scripts.dedup();
         all_scripts
     }
 
@@ -138,7 +251,7 @@ impl PluginManager {
 
         let mut all_css = Vec::new();
 
-        for plugin in plugins.iter() {
+        for plugin in plugins.iter().filter(|p| self.is_enabled(p.name())) {
             all_css.extend(plugin.get_external_css());
         }
 
@@ -190,8 +303,17 @@ lazy_static::lazy_static! {
     pub static ref PLUGIN_MANAGER: PluginManager = PluginManager::new();
 }
 
+/// Set once the default plugins have been registered
+static PLUGINS_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
 /// Initialize the plugin system with default plugins
+///
+/// Later calls do nothing, so both the app and [`crate::render_document`] can call it.
 pub fn initialize_plugins() -> Result<(), Box<dyn std::error::Error>> {
+    if PLUGINS_INITIALIZED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
     // Register the Mermaid plugin
     let mermaid_plugin = Box::new(crate::plugins::mermaid::MermaidPlugin::new());
     PLUGIN_MANAGER.register_plugin(mermaid_plugin)?;
@@ -200,6 +322,160 @@ pub fn initialize_plugins() -> Result<(), Box<dyn std::error::Error>> {
     let latex_plugin = Box::new(crate::plugins::katex::LatexPlugin::new());
     PLUGIN_MANAGER.register_plugin(latex_plugin)?;
 
+    // Register the PlantUML plugin
+    let plantuml_plugin = Box::new(crate::plugins::plantuml::PlantUmlPlugin::new());
+    PLUGIN_MANAGER.register_plugin(plantuml_plugin)?;
+
+    // Register the cross-reference plugin last, so it sees the HTML of rendered math
+    let crossref_plugin = Box::new(crate::plugins::crossref::CrossRefPlugin::new());
+    PLUGIN_MANAGER.register_plugin(crossref_plugin)?;
+
     log::info!("Plugin system initialized");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    /// Plugin that renders a code block as its name and current settings.
+    struct EchoPlugin {
+        name: &'static str,
+        settings: Value,
+    }
+
+    impl EchoPlugin {
+        fn boxed(name: &'static str) -> Box<dyn Plugin> {
+            Box::new(Self {
+                name,
+                settings: Value::Null,
+            })
+        }
+    }
+
+    impl Plugin for EchoPlugin {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn version(&self) -> &'static str {
+            "1.0.0"
+        }
+
+        fn handles_language(&self, language: &str) -> bool {
+            language == "echo"
+        }
+
+        fn process_code_block(
+            &self,
+            _content: &str,
+            _language: &str,
+            _context: &PluginContext,
+        ) -> Option<PluginResult> {
+            Some(PluginResult {
+                html: format!("{} {}", self.name, self.settings),
+                javascript: None,
+                css: None,
+            })
+        }
+
+        fn get_javascript(&self, _context: &PluginContext) -> Option<String> {
+            None
+        }
+
+        fn get_css(&self, _context: &PluginContext) -> Option<String> {
+            None
+        }
+
+        fn get_external_scripts(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        fn configure(&mut self, settings: &Value) -> Result<(), Box<dyn std::error::Error>> {
+            self.settings = settings.clone();
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    fn render(manager: &PluginManager, language: &str) -> Option<String> {
+        let context = PluginContext {
+            theme_mode: Default::default(),
+            is_streaming: false,
+            content_id: "test".to_string(),
+        };
+        manager
+            .process_code_block("", language, &context)
+            .map(|result| result.html)
+    }
+
+    fn config(plugins: Value) -> AppConfig {
+        AppConfig {
+            plugins: serde_json::from_value(plugins).unwrap(),
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn reapplying_config_reconfigures_plugins() {
+        let manager = PluginManager::new();
+        manager.register_plugin(EchoPlugin::boxed("echo")).unwrap();
+
+        manager
+            .apply_config(&config(json!({"echo": {"size": 1}})))
+            .unwrap();
+        assert_eq!(render(&manager, "echo").unwrap(), r#"echo {"size":1}"#);
+
+        manager
+            .apply_config(&config(json!({"echo": {"size": 2}})))
+            .unwrap();
+        assert_eq!(render(&manager, "echo").unwrap(), r#"echo {"size":2}"#);
+
+        // A section removed from the config leaves the plugin unconfigured
+        manager.apply_config(&AppConfig::default()).unwrap();
+        assert_eq!(render(&manager, "echo").unwrap(), "echo null");
+    }
+
+    #[test]
+    fn language_override_picks_the_named_plugin() {
+        let manager = PluginManager::new();
+        manager.register_plugin(EchoPlugin::boxed("first")).unwrap();
+        manager
+            .register_plugin(EchoPlugin::boxed("second"))
+            .unwrap();
+        // The first plugin claiming a language handles it, and the choice is cached
+        assert_eq!(render(&manager, "echo").unwrap(), "first null");
+
+        let overrides = AppConfig {
+            language_overrides: HashMap::from([
+                ("echo".to_string(), "second".to_string()),
+                ("other".to_string(), "second".to_string()),
+            ]),
+            ..AppConfig::default()
+        };
+        manager.apply_config(&overrides).unwrap();
+        assert_eq!(render(&manager, "echo").unwrap(), "second null");
+        // An override also hands a plugin languages it doesn't claim
+        assert_eq!(render(&manager, "other").unwrap(), "second null");
+
+        manager.apply_config(&AppConfig::default()).unwrap();
+        assert_eq!(render(&manager, "echo").unwrap(), "first null");
+        assert_eq!(render(&manager, "other"), None);
+    }
+
+    #[test]
+    fn override_naming_a_missing_plugin_falls_back() {
+        let manager = PluginManager::new();
+        manager.register_plugin(EchoPlugin::boxed("first")).unwrap();
+        manager.set_language_override("echo", "missing");
+        assert_eq!(render(&manager, "echo").unwrap(), "first null");
+    }
+}
@@ -1,5 +1,8 @@
 use crate::gui::types::ThemeMode;
-use crate::plugins::{Plugin, PluginContext, PluginResult};
+use crate::plugins::{Plugin, PluginContext, PluginResult, cdn_url};
+
+/// Pinned Mermaid release; every page, including after a mode toggle, loads it from here
+const MERMAID_SCRIPT_PATH: &str = "mermaid@11.9.0/dist/mermaid.min.js";
 
 /// Mermaid diagram rendering plugin
 pub struct MermaidPlugin {
@@ -12,6 +15,12 @@ impl MermaidPlugin {
     }
 }
 
+impl Default for MermaidPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Plugin for MermaidPlugin {
     fn name(&self) -> &'static str {
         "mermaid"
@@ -109,6 +118,69 @@ impl Plugin for MermaidPlugin {
             r#"
 // Mermaid Plugin JavaScript
 
+// Replace a diagram that failed to render with the error and its numbered source, marking
+// the line Mermaid blames when it names one
+window.showMermaidError = function(element, error, source) {{
+    const message = (error && error.message) ? error.message : String(error);
+    let errorLine = null;
+    if (error && error.hash && error.hash.loc) {{
+        errorLine = error.hash.loc.first_line;
+    }} else {{
+        const match = message.match(/line (\d+)/i);
+        if (match) errorLine = parseInt(match[1], 10);
+    }}
+
+    const box = document.createElement('div');
+    box.className = 'mermaid-error';
+    const title = document.createElement('div');
+    title.className = 'mermaid-error-message';
+    title.textContent = 'Mermaid rendering error: ' + message;
+    box.appendChild(title);
+
+    const pre = document.createElement('pre');
+    pre.className = 'mermaid-error-source';
+    source.split('\n').forEach((text, index) => {{
+        const line = document.createElement('span');
+        line.className = 'mermaid-error-line';
+        if (index + 1 === errorLine) line.classList.add('mermaid-error-highlight');
+        line.textContent = text + '\n';
+        pre.appendChild(line);
+    }});
+    box.appendChild(pre);
+
+    element.innerHTML = '';
+    element.appendChild(box);
+}};
+
+// Diagram types laid out along a timeline, which stay legible only at their natural width
+const WIDE_MERMAID_TYPES = ['sequenceDiagram', 'gantt', 'timeline', 'gitGraph', 'journey'];
+
+// Returns the diagram type, the first word after any front matter, directives and comments
+window.mermaidDiagramType = function(source) {{
+    const lines = source.split('\n');
+    let index = 0;
+    if (lines[0] && lines[0].trim() === '---') {{
+        index = lines.findIndex((line, lineIndex) => lineIndex > 0 && line.trim() === '---') + 1;
+    }}
+    for (const line of lines.slice(index)) {{
+        const text = line.trim();
+        if (text && !text.startsWith('%%')) return text.split(/[\s:;]/)[0];
+    }}
+    return '';
+}};
+
+// Wide diagram types keep their natural size and scroll sideways instead of shrinking to
+// fit; other diagrams, such as flowcharts, keep fitting the page width
+window.fitMermaidDiagram = function(element, source) {{
+    const svg = element.querySelector('svg');
+    if (!svg || !WIDE_MERMAID_TYPES.includes(window.mermaidDiagramType(source))) return;
+    element.classList.add('mermaid-wide');
+    const viewBox = svg.viewBox && svg.viewBox.baseVal;
+    if (viewBox && viewBox.width) {{
+        svg.style.width = viewBox.width + 'px';
+    }}
+}};
+
 // Initialize Mermaid when available
 if (typeof mermaid !== 'undefined') {{
     mermaid.initialize({{
@@ -131,10 +203,11 @@ if (typeof mermaid !== 'undefined') {{
                 element.innerHTML = '';
                 const {{ svg }} = await mermaid.render(`mermaidChart${{Date.now()}}_${{index}}`, graphDefinition);
                 element.innerHTML = svg;
+                window.fitMermaidDiagram(element, graphDefinition);
                 console.log('Successfully rendered diagram', index);
             }} catch (error) {{
                 console.error('Mermaid rendering error for diagram', index, ':', error);
-                element.innerHTML = '<div style="color: red; padding: 10px; font-family: monospace;">Mermaid rendering error: ' + error.message + '</div>';
+                window.showMermaidError(element, error, graphDefinition);
             }}
         }});
     }};
@@ -208,9 +281,10 @@ window.renderNewMermaidDiagrams = function(container) {{
             element.innerHTML = '';
             const {{ svg }} = await mermaid.render(`appendedChart${{Date.now()}}_${{index}}`, graphDefinition);
             element.innerHTML = svg;
+            window.fitMermaidDiagram(element, graphDefinition);
         }} catch (error) {{
             console.error('Mermaid rendering error for appended content:', error);
-            element.innerHTML = '<div style="color: red; padding: 10px;">Mermaid rendering error: ' + error.message + '</div>';
+            window.showMermaidError(element, error, graphDefinition);
         }}
     }});
 }};
@@ -279,6 +353,46 @@ window.renderNewMermaidDiagrams = function(container) {{
     margin: 0;
 }
 
+.mermaid-error {
+    border-left: 4px solid var(--admonition-caution);
+    text-align: left;
+}
+
+.mermaid-error-message {
+    padding: 8px 12px;
+    color: var(--admonition-caution);
+    font-weight: 600;
+}
+
+.mermaid-error-source {
+    margin: 0;
+    padding: 8px 0;
+    background: var(--pre-bg-color);
+    counter-reset: mermaid-error-line;
+    font-family: "SF Mono", "Menlo", "Monaco", monospace;
+    overflow: auto;
+}
+
+.mermaid-error-line {
+    display: block;
+    padding: 0 12px;
+}
+
+.mermaid-error-line::before {
+    counter-increment: mermaid-error-line;
+    content: counter(mermaid-error-line);
+    display: inline-block;
+    width: 2.5em;
+    margin-right: 1em;
+    text-align: right;
+    color: var(--muted-text-color);
+    user-select: none;
+}
+
+.mermaid-error-highlight {
+    background: rgba(209, 36, 47, 0.15);
+}
+
 .mermaid-raw code {
     display: block;
     padding: 16px;
@@ -294,7 +408,7 @@ window.renderNewMermaidDiagrams = function(container) {{
     }
 
     fn get_external_scripts(&self) -> Vec<String> {
-        vec!["https://cdn.jsdelivr.net/npm/mermaid@11.9.0/dist/mermaid.min.js".to_string()]
+        vec![cdn_url(MERMAID_SCRIPT_PATH)]
     }
 
     fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
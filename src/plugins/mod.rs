@@ -1,8 +1,66 @@
+use log::warn;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use crate::gui::types::ThemeMode;
 
+pub mod crossref;
 pub mod katex;
 pub mod manager;
 pub mod mermaid;
+pub mod plantuml;
+
+/// Set by `--offline`: plugins must not fetch anything from the network.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables offline mode for all plugins.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns whether plugins should avoid network requests.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// npm CDN that external scripts and stylesheets load from, unless a mirror is set.
+const DEFAULT_CDN_BASE: &str = "https://cdn.jsdelivr.net/npm/";
+
+/// Mirror of the npm CDN given with `--cdn-base` or `HOMO_CDN_BASE`.
+static CDN_BASE: OnceLock<String> = OnceLock::new();
+
+/// Loads external scripts and stylesheets from `base`, a mirror laid out like jsdelivr's
+/// `/npm/` tree (e.g. `https://mirror.example.com/npm/`). A base that isn't an http(s) URL is
+/// ignored with a warning, leaving jsdelivr in place.
+pub fn set_cdn_base(base: &str) {
+    let base = base.trim();
+    let host = base
+        .strip_prefix("https://")
+        .or_else(|| base.strip_prefix("http://"))
+        .map(|rest| rest.split('/').next().unwrap_or_default());
+    // The base ends up in `src` and `href` attributes
+    let attribute_safe = !base.contains(|c: char| c.is_whitespace() || "\"'<>".contains(c));
+    if host.is_none_or(str::is_empty) || !attribute_safe {
+        warn!("Ignoring CDN base {base:?}: expected a URL such as https://mirror.example.com/npm/");
+        return;
+    }
+
+    let base = if base.ends_with('/') {
+        base.to_string()
+    } else {
+        format!("{base}/")
+    };
+    if CDN_BASE.set(base).is_err() {
+        warn!("CDN base already set, ignoring it");
+    }
+}
+
+/// Returns the URL of an npm package file such as `mermaid@11.9.0/dist/mermaid.min.js` on the
+/// CDN.
+pub fn cdn_url(path: &str) -> String {
+    let base = CDN_BASE.get().map_or(DEFAULT_CDN_BASE, String::as_str);
+    format!("{base}{path}")
+}
 
 /// Context information passed to plugins during processing
 #[derive(Clone)]
@@ -43,6 +101,14 @@ pub trait Plugin: Send + Sync {
         context: &PluginContext,
     ) -> Option<PluginResult>;
 
+    /// Transforms the HTML of a whole rendered document, e.g. to link `#123` issue references.
+    ///
+    /// Runs after every code block has been processed. Returns `None` to leave the HTML as it
+    /// is, which is what plugins that only handle code blocks do.
+    fn process_document(&self, _html: &str, _context: &PluginContext) -> Option<String> {
+        None
+    }
+
     /// Get JavaScript code that needs to be injected into the page
     fn get_javascript(&self, context: &PluginContext) -> Option<String>;
 
@@ -60,6 +126,16 @@ pub trait Plugin: Send + Sync {
     /// Called when the plugin is initialized
     fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>>;
 
+    /// Applies plugin-specific settings from the user config file.
+    ///
+    /// `settings` is `Value::Null` when the config has no section for this plugin.
+    fn configure(
+        &mut self,
+        _settings: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(()) // Default implementation ignores configuration
+    }
+
     /// Called when the plugin is shut down
     #[allow(dead_code)]
     fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>>;
@@ -0,0 +1,275 @@
+use std::io::Write;
+
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+
+use crate::plugins::{Plugin, PluginContext, PluginResult, is_offline};
+
+const DEFAULT_SERVER: &str = "https://www.plantuml.com/plantuml";
+
+/// PlantUML's base64 variant alphabet
+const ENCODE_ALPHABET: &[u8; 64] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+
+/// PlantUML diagram rendering plugin, backed by a PlantUML server
+pub struct PlantUmlPlugin {
+    initialized: bool,
+    server: String,
+}
+
+impl PlantUmlPlugin {
+    pub fn new() -> Self {
+        Self {
+            initialized: false,
+            server: DEFAULT_SERVER.to_string(),
+        }
+    }
+
+    /// Returns the server URL that renders `source` as SVG.
+    fn diagram_url(&self, source: &str) -> Result<String, std::io::Error> {
+        Ok(format!("{}/svg/{}", self.server, encode_source(source)?))
+    }
+}
+
+/// Returns the PlantUML source for a code block. Graphviz blocks are passed to PlantUML's own
+/// Graphviz support inside `@startdot`, unless they already start with it.
+fn plantuml_source(content: &str, language: &str) -> String {
+    if is_graphviz(language) && !content.trim_start().starts_with("@startdot") {
+        format!("@startdot\n{}\n@enddot\n", content.trim_end())
+    } else {
+        content.to_string()
+    }
+}
+
+/// Returns whether a code block language names a Graphviz diagram.
+fn is_graphviz(language: &str) -> bool {
+    language == "dot" || language == "graphviz"
+}
+
+impl Default for PlantUmlPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes diagram source the way PlantUML servers expect: raw deflate, then PlantUML's
+/// own base64 alphabet.
+fn encode_source(source: &str) -> Result<String, std::io::Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(source.as_bytes())?;
+    let compressed = encoder.finish()?;
+
+    let mut encoded = String::with_capacity(compressed.len() * 4 / 3 + 4);
+    for chunk in compressed.chunks(3) {
+        let b1 = chunk[0];
+        let b2 = chunk.get(1).copied().unwrap_or(0);
+        let b3 = chunk.get(2).copied().unwrap_or(0);
+        for index in [
+            b1 >> 2,
+            ((b1 & 0x3) << 4) | (b2 >> 4),
+            ((b2 & 0xF) << 2) | (b3 >> 6),
+            b3 & 0x3F,
+        ] {
+            encoded.push(ENCODE_ALPHABET[index as usize] as char);
+        }
+    }
+
+    Ok(encoded)
+}
+
+impl Plugin for PlantUmlPlugin {
+    fn name(&self) -> &'static str {
+        "plantuml"
+    }
+
+    fn version(&self) -> &'static str {
+        "1.0.0"
+    }
+
+    fn handles_language(&self, language: &str) -> bool {
+        language == "plantuml" || language == "puml" || is_graphviz(language)
+    }
+
+    fn process_code_block(
+        &self,
+        content: &str,
+        language: &str,
+        _context: &PluginContext,
+    ) -> Option<PluginResult> {
+        if !self.handles_language(language) {
+            return None;
+        }
+
+        // Escape content for HTML display
+        let html_escaped_content = content
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+
+        // Escape content for HTML attribute
+        let attr_escaped_raw = content
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;");
+
+        let diagram_url = if is_offline() {
+            None
+        } else {
+            match self.diagram_url(&plantuml_source(content, language)) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    log::warn!("Failed to encode PlantUML diagram: {e}");
+                    None
+                }
+            }
+        };
+
+        let html = match diagram_url {
+            Some(url) => format!(
+                r#"<div class="plantuml-container" data-plantuml-source="{attr_escaped_raw}">
+                <div class="plantuml-buttons">
+                    <button class="plantuml-toggle-btn" onclick="togglePlantUmlView(this)" title="Toggle rendered/raw view">View</button>
+                    <button class="plantuml-copy-btn" onclick="copyPlantUmlCode(this)" title="Copy PlantUML source">Copy</button>
+                </div>
+                <div class="plantuml"><img src="{url}" alt="PlantUML diagram" onerror="showPlantUmlSource(this)"></div>
+                <pre class="plantuml-raw" style="display: none;"><code>{html_escaped_content}</code></pre>
+            </div>"#
+            ),
+            // Offline: show the source without contacting the server
+            None => format!(
+                r#"<div class="plantuml-container" data-plantuml-source="{attr_escaped_raw}">
+                <div class="plantuml-buttons">
+                    <button class="plantuml-copy-btn" onclick="copyPlantUmlCode(this)" title="Copy PlantUML source">Copy</button>
+                </div>
+                <pre class="plantuml-raw"><code>{html_escaped_content}</code></pre>
+            </div>"#
+            ),
+        };
+
+        Some(PluginResult {
+            html,
+            javascript: None, // JavaScript is provided globally
+            css: None,        // CSS is provided globally
+        })
+    }
+
+    fn get_javascript(&self, _context: &PluginContext) -> Option<String> {
+        let javascript = r#"
+// PlantUML Plugin JavaScript
+
+// Copy function for PlantUML diagrams
+window.copyPlantUmlCode = function(button) {
+    const container = button.closest('.plantuml-container');
+    window.webkit.messageHandlers.copyText.postMessage(container.getAttribute('data-plantuml-source'));
+};
+
+// Toggle function for PlantUML rendered/raw view
+window.togglePlantUmlView = function(button) {
+    const container = button.closest('.plantuml-container');
+    const renderedView = container.querySelector('.plantuml');
+    const rawView = container.querySelector('.plantuml-raw');
+
+    if (renderedView.style.display === 'none') {
+        renderedView.style.display = 'block';
+        rawView.style.display = 'none';
+        button.textContent = 'View';
+    } else {
+        renderedView.style.display = 'none';
+        rawView.style.display = 'block';
+        button.textContent = 'Raw';
+    }
+};
+
+// Fall back to the source when the server can't be reached
+window.showPlantUmlSource = function(image) {
+    const container = image.closest('.plantuml-container');
+    container.querySelector('.plantuml').style.display = 'none';
+    container.querySelector('.plantuml-raw').style.display = 'block';
+    const toggle = container.querySelector('.plantuml-toggle-btn');
+    if (toggle) {
+        toggle.remove();
+    }
+};
+"#;
+
+        Some(javascript.to_string())
+    }
+
+    fn get_css(&self, _context: &PluginContext) -> Option<String> {
+        let css = r#"
+/* PlantUML Plugin Styles */
+.plantuml-container {
+    position: relative;
+    margin: 16px 0;
+}
+
+.plantuml-buttons {
+    position: absolute;
+    top: 8px;
+    right: 8px;
+    z-index: 10;
+    display: flex;
+    gap: 4px;
+}
+
+.plantuml-toggle-btn,
+.plantuml-copy-btn {
+    padding: 4px 8px;
+    font-size: 12px;
+    color: inherit;
+    background: var(--pre-bg-color);
+    border: 1px solid var(--border-color);
+    border-radius: 4px;
+    cursor: pointer;
+}
+
+.plantuml {
+    /* Server-rendered diagrams have a light background in every theme */
+    background: #ffffff;
+    border: 1px solid var(--border-color);
+    border-radius: 6px;
+    padding: 16px;
+    overflow: auto;
+    text-align: center;
+}
+
+.plantuml img {
+    max-width: 100%;
+}
+
+.plantuml-raw {
+    margin: 0;
+}
+"#;
+
+        Some(css.to_string())
+    }
+
+    fn get_external_scripts(&self) -> Vec<String> {
+        Vec::new() // Diagrams are rendered by the server
+    }
+
+    fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Initializing PlantUML plugin v{}", self.version());
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn configure(
+        &mut self,
+        settings: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.server = match settings.get("server") {
+            None => DEFAULT_SERVER.to_string(),
+            Some(serde_json::Value::String(server)) => server.trim_end_matches('/').to_string(),
+            Some(_) => return Err("\"server\" must be a URL string".into()),
+        };
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Shutting down PlantUML plugin");
+        self.initialized = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! Recently opened files, listed in File → Open Recent.
+
+use std::path::Path;
+
+use crate::gui::user_defaults;
+
+const RECENT_FILES_KEY: &str = "RecentFiles";
+
+/// How many paths the Open Recent menu remembers.
+const MAX_RECENT_FILES: usize = 10;
+
+/// Returns the remembered paths, most recent first.
+pub fn recent_files() -> Vec<String> {
+    user_defaults::load_json(RECENT_FILES_KEY).unwrap_or_default()
+}
+
+/// Moves a path to the top of the list, dropping the oldest entry when the list is full.
+pub fn add_recent_file(path: &str) {
+    // Store absolute paths so entries still open from another working directory
+    let path = Path::new(path)
+        .canonicalize()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+
+    let mut files = recent_files();
+    files.retain(|file| *file != path);
+    files.insert(0, path);
+    files.truncate(MAX_RECENT_FILES);
+    user_defaults::save_json(RECENT_FILES_KEY, &files);
+}
+
+/// Forgets all remembered paths.
+pub fn clear_recent_files() {
+    user_defaults::save_json(RECENT_FILES_KEY, &Vec::<String>::new());
+}
@@ -0,0 +1,562 @@
+//! A small HTTP server for viewing a document from another machine, started with `--serve PORT`
+//! in builds with the `server` feature.
+//!
+//! Every request for the page renders the file afresh with [`render_document`]. Pages keep a
+//! WebSocket open to `/live`, and when the file changes the server tells them to reload. Images
+//! next to the document are served too, so relative image paths keep working.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use log::{debug, info, warn};
+
+use crate::content::file_url_path;
+use crate::error::AppError;
+use crate::export::{RenderOptions, render_document};
+use crate::gui::types::StylePreferences;
+use crate::markdown;
+
+/// How often the file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a connection may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a write to a page's WebSocket may block, so a page that stops reading is dropped
+/// instead of holding up notifications to every other page
+const LIVE_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest request head accepted
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+
+/// Largest WebSocket frame accepted from a page, which only ever sends pings and closes
+const MAX_FRAME_BYTES: u64 = 64 * 1024;
+
+/// Path of the live reload WebSocket
+const LIVE_PATH: &str = "/live";
+
+/// Appended to the page's key to accept a WebSocket connection (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// WebSocket opcodes
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Message telling pages that the document changed
+const RELOAD_MESSAGE: &str = "reload";
+
+/// Image types served from the document's directory
+const IMAGE_TYPES: [(&str, &str); 7] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+];
+
+/// Standard base64 alphabet, for the WebSocket handshake
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Reloads the page when the server says the document changed, returning to the same scroll
+/// position, and reconnects if the connection drops.
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    const scrollKey = 'homo-scroll';
+    const saved = sessionStorage.getItem(scrollKey);
+    if (saved !== null) {
+        sessionStorage.removeItem(scrollKey);
+        window.addEventListener('load', () => window.scrollTo(0, Number(saved)));
+    }
+
+    function connect() {
+        const socket = new WebSocket('ws://' + location.host + '/live');
+        socket.onmessage = (event) => {
+            if (event.data === 'reload') {
+                sessionStorage.setItem(scrollKey, String(window.scrollY));
+                location.reload();
+            }
+        };
+        socket.onclose = () => setTimeout(connect, 1000);
+    }
+    connect();
+})();
+</script>"#;
+
+/// State shared by the connections and the file watcher.
+struct Server {
+    /// The markdown file being served
+    document: PathBuf,
+    options: RenderOptions,
+    /// Sockets of the pages to notify when the document changes
+    live_pages: Mutex<Vec<TcpStream>>,
+}
+
+/// Serves `path` rendered with `style_preferences` on every interface at `port`, until the
+/// process is stopped.
+pub fn serve(path: &str, port: u16, style_preferences: StylePreferences) -> Result<(), AppError> {
+    let document = fs::canonicalize(path)?;
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    info!("Serving {} on port {port}", document.display());
+    println!("Serving {path} at http://localhost:{port}/ (Ctrl+C to stop)");
+
+    let server = Arc::new(Server {
+        document,
+        options: RenderOptions {
+            style_preferences,
+            dialect: markdown::dialect(),
+            sanitize_html: markdown::sanitize_html_enabled(),
+        },
+        live_pages: Mutex::new(Vec::new()),
+    });
+
+    let watcher = Arc::clone(&server);
+    thread::spawn(move || watcher.watch());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = Arc::clone(&server);
+                thread::spawn(move || {
+                    if let Err(e) = server.handle(stream) {
+                        debug!("Connection ended: {e}");
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to accept a connection: {e}"),
+        }
+    }
+    Ok(())
+}
+
+impl Server {
+    /// Tells every open page to reload whenever the file's modification time changes.
+    fn watch(&self) {
+        let mut last_modified = modified(&self.document);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let modified = modified(&self.document);
+            if modified != last_modified {
+                last_modified = modified;
+                debug!("{} changed, reloading pages", self.document.display());
+                self.notify(RELOAD_MESSAGE);
+            }
+        }
+    }
+
+    /// Sends `message` to every open page, forgetting pages that have gone away or stopped
+    /// reading.
+    fn notify(&self, message: &str) {
+        let frame = websocket_frame(OPCODE_TEXT, message.as_bytes());
+        let mut pages = self.live_pages.lock().unwrap_or_else(|e| e.into_inner());
+        pages.retain_mut(|page| page.write_all(&frame).is_ok());
+    }
+
+    /// Answers one connection.
+    fn handle(&self, mut stream: TcpStream) -> Result<(), AppError> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let Some(request) = Request::read(&stream)? else {
+            return Ok(());
+        };
+        debug!("{} {}", request.method, request.target);
+
+        if request.method != "GET" {
+            return respond(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain; charset=utf-8",
+                b"Only GET is supported\n",
+            );
+        }
+
+        let path = request.target.split(['?', '#']).next().unwrap_or("/");
+        match path {
+            "/" => self.serve_page(&mut stream),
+            LIVE_PATH => self.serve_live(stream, &request),
+            _ => self.serve_image(&mut stream, path),
+        }
+    }
+
+    /// Sends the rendered document.
+    fn serve_page(&self, stream: &mut TcpStream) -> Result<(), AppError> {
+        let markdown_text = match fs::read_to_string(&self.document) {
+            Ok(text) => markdown::file_markdown(&self.document.to_string_lossy(), text),
+            Err(e) => {
+                warn!("Failed to read {}: {e}", self.document.display());
+                let message = format!("Failed to read the document: {e}\n");
+                return respond(
+                    stream,
+                    "500 Internal Server Error",
+                    "text/plain; charset=utf-8",
+                    message.as_bytes(),
+                );
+            }
+        };
+
+        let mut page = render_document(&markdown_text, &self.options);
+        let body_end = page.rfind("</body>").unwrap_or(page.len());
+        page.insert_str(body_end, LIVE_RELOAD_SCRIPT);
+        respond(
+            stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            page.as_bytes(),
+        )
+    }
+
+    /// Sends an image from the document's directory or below it.
+    fn serve_image(&self, stream: &mut TcpStream, path: &str) -> Result<(), AppError> {
+        let content_type = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| {
+                IMAGE_TYPES
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            })
+            .map(|(_, content_type)| *content_type);
+        let directory = self.document.parent().unwrap_or(Path::new("/"));
+        // Paths escaping the directory, e.g. with `..` or a symlink, aren't served
+        let file = file_url_path(&format!("file://{path}"))
+            .and_then(|path| fs::canonicalize(directory.join(path.trim_start_matches('/'))).ok())
+            .filter(|file| file.starts_with(directory));
+
+        match (content_type, file.and_then(|file| fs::read(file).ok())) {
+            (Some(content_type), Some(bytes)) => respond(stream, "200 OK", content_type, &bytes),
+            _ => respond(
+                stream,
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                b"Not found\n",
+            ),
+        }
+    }
+
+    /// Accepts a page's WebSocket and keeps it open for change notifications until the page
+    /// closes it.
+    fn serve_live(&self, mut stream: TcpStream, request: &Request) -> Result<(), AppError> {
+        let upgrade = request
+            .header("upgrade")
+            .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+        let Some(key) = request.header("sec-websocket-key").filter(|_| upgrade) else {
+            return respond(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain; charset=utf-8",
+                b"Expected a WebSocket connection\n",
+            );
+        };
+
+        let accept = base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()));
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+        )?;
+        stream.set_read_timeout(None)?;
+        // Also applies to the clone notifications are written to, which shares the socket
+        stream.set_write_timeout(Some(LIVE_WRITE_TIMEOUT))?;
+        self.live_pages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(stream.try_clone()?);
+
+        let result = self.read_live_frames(&mut stream);
+        // Makes the next notification fail, which forgets this page
+        let _ = stream.shutdown(Shutdown::Both);
+        result.map_err(AppError::from)
+    }
+
+    /// Reads frames from a page until it closes the connection, answering pings.
+    fn read_live_frames(&self, stream: &mut TcpStream) -> io::Result<()> {
+        loop {
+            let (opcode, payload) = read_frame(stream)?;
+            let reply = match opcode {
+                OPCODE_CLOSE => OPCODE_CLOSE,
+                OPCODE_PING => OPCODE_PONG,
+                _ => continue,
+            };
+            // Holding the lock keeps the reply from interleaving with a notification
+            let _pages = self.live_pages.lock().unwrap_or_else(|e| e.into_inner());
+            stream.write_all(&websocket_frame(reply, &payload))?;
+            if opcode == OPCODE_CLOSE {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The request line and headers of an HTTP request.
+struct Request {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Reads a request head, or returns `None` if the connection closed before sending one.
+    fn read(stream: &TcpStream) -> io::Result<Option<Self>> {
+        let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed request line",
+            ));
+        };
+        let mut request = Request {
+            method: method.to_string(),
+            target: target.to_string(),
+            headers: Vec::new(),
+        };
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "request headers cut off",
+                ));
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                return Ok(Some(request));
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                request
+                    .headers
+                    .push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+    }
+
+    /// Returns the value of a header, matching its name in any case.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Writes a complete response and leaves the connection to be closed.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), AppError> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    Ok(())
+}
+
+/// Returns when a file was last modified, or `None` if that can't be read, e.g. while an
+/// editor replaces it.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reads one WebSocket frame, returning its opcode and unmasked payload.
+fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            stream.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            stream.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if len > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "WebSocket frame too large",
+        ));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        stream.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    if masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+    Ok((opcode, payload))
+}
+
+/// Builds an unmasked, unfragmented WebSocket frame, as servers send them.
+fn websocket_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Encodes bytes as standard, padded base64.
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b1 = chunk[0];
+        let b2 = chunk.get(1).copied().unwrap_or(0);
+        let b3 = chunk.get(2).copied().unwrap_or(0);
+        let indices = [
+            b1 >> 2,
+            ((b1 & 0x3) << 4) | (b2 >> 4),
+            ((b2 & 0xF) << 2) | (b3 >> 6),
+            b3 & 0x3F,
+        ];
+        // A chunk of n bytes fills n + 1 characters; the rest is padding
+        for (position, index) in indices.into_iter().enumerate() {
+            if position <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// SHA-1 digest of `data`. Only the WebSocket handshake uses it, which is why a hash this
+/// weak is fine here.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for index in 16..80 {
+            words[index] =
+                (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                    .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn sha1_matches_known_digests() {
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        // Spans two blocks once padded
+        assert_eq!(
+            hex(&sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        for (input, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(input.as_bytes()), encoded);
+        }
+    }
+
+    #[test]
+    fn websocket_accept_matches_rfc_6455() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let accept = base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()));
+        assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+}
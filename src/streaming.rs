@@ -1,82 +1,342 @@
 //! Streaming logic for reading from stdin and sending HTML updates to the GUI.
 
-use crate::content::{ContentUpdate, DocumentContent};
+use crate::ansi::{self, AnsiRenderer};
+use crate::content::{ContentFormat, ContentUpdate, DocumentContent, plain_to_html};
 use crate::error::AppError;
-use crate::markdown;
+use crate::markdown::{self, front_matter};
+use crate::recent_files;
 use log::{debug, error, info};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read};
-use std::sync::mpsc;
+use std::os::fd::{AsFd, AsRawFd, RawFd};
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Send buffered terminal output or plain text at least this often while input keeps arriving
+const MAX_PREFORMATTED_CHUNK_LINES: usize = 500;
+
+/// Lines of piped input inspected when guessing whether it is plain text
+const PLAIN_TEXT_SAMPLE_LINES: usize = 20;
+
+/// Fewer lines than this are too little to tell plain text from a markdown paragraph
+const MIN_PLAIN_TEXT_LINES: usize = 3;
+
+/// Title of piped input when no `--title` is given
+const PIPE_TITLE: &str = "Piped Input";
+
+/// Piped input that pauses this long has its buffered lines shown without waiting for more
+const STALL_FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Stop holding back an unclosed `<details>` element after this many lines
+const MAX_DETAILS_LINES: usize = 500;
+
+/// Raw HTML that stays open past blank lines, with its closing tag
+const RAW_HTML_ELEMENTS: [(&str, &str); 5] = [
+    ("<script", "</script"),
+    ("<style", "</style"),
+    ("<pre", "</pre"),
+    ("<textarea", "</textarea"),
+    ("<!--", "-->"),
+];
+
+/// Give up treating a leading `---` as front matter after this many lines without a closing fence
+const MAX_FRONT_MATTER_LINES: usize = 50;
+
+/// Lines longer than this (e.g. minified output with no newline) are read in pieces of about
+/// this size, so one huge line can't hold up reading and rendering
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Buffered markdown is sent once it grows this large, even in the middle of a paragraph
+const MAX_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Marks where the oldest streamed content was trimmed, in HTML and markdown
+pub const TRIMMED_MARKER_HTML: &str =
+    "<p class=\"trimmed-marker\">… earlier output trimmed …</p>\n\n";
+
+/// Marks where the oldest streamed plain text or terminal output was trimmed
+const TRIMMED_MARKER_TEXT: &str = "… earlier output trimmed …\n";
+
+/// Ends a chunk that stops inside an overlong line, whose rest arrives with the next chunk.
+/// Only the HTML has it; the markdown keeps the line whole.
+const SPLIT_LINE_MARKER_HTML: &str = "<p class=\"split-line-marker\">… line continues …</p>\n";
+
+/// Most streamed markdown kept in memory, from `--max-buffer`; 0 keeps everything.
+static MAX_BUFFER_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Limits how much streamed markdown is kept; `None` keeps everything.
+pub fn set_max_buffer(bytes: Option<usize>) {
+    MAX_BUFFER_BYTES.store(bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Returns how much streamed markdown to keep, if limited.
+pub fn max_buffer() -> Option<usize> {
+    Some(MAX_BUFFER_BYTES.load(Ordering::Relaxed)).filter(|bytes| *bytes > 0)
+}
+
+/// Returns how much of an over-long buffer to keep. Trimming a quarter at a time means the
+/// page is rebuilt once per quarter of the limit rather than on every chunk.
+pub fn trim_target(max_bytes: usize) -> usize {
+    max_bytes / 4 * 3
+}
+
+/// Returns the marker put in place of trimmed text of the given format.
+pub fn trimmed_marker(format: &ContentFormat) -> &'static str {
+    match format {
+        ContentFormat::Markdown => TRIMMED_MARKER_HTML,
+        ContentFormat::Ansi | ContentFormat::Plain => TRIMMED_MARKER_TEXT,
+    }
+}
+
+/// Drops the oldest text of a stream that outgrew `--max-buffer`, putting a marker in its
+/// place. Markdown is cut only where a new, unindented block starts outside code blocks;
+/// other formats at any line. Returns whether anything was dropped.
+pub fn trim_to_max_buffer(text: &mut String, format: &ContentFormat) -> bool {
+    let Some(max_bytes) = max_buffer() else {
+        return false;
+    };
+    if text.len() <= max_bytes {
+        return false;
+    }
+    let min_cut = text.len() - trim_target(max_bytes);
+
+    let markdown = *format == ContentFormat::Markdown;
+    let mut code_fence: Option<(char, usize)> = None;
+    let mut after_blank_line = false;
+    let mut offset = 0;
+    let mut cut = None;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let block_start = code_fence.is_none()
+            && after_blank_line
+            && !trimmed.is_empty()
+            && !line.starts_with([' ', '\t']);
+        if offset >= min_cut && (block_start || !markdown) {
+            cut = Some(offset);
+            break;
+        }
+
+        if let Some((fence_char, fence_len, info)) = parse_fence(trimmed) {
+            match code_fence {
+                None => code_fence = Some((fence_char, fence_len)),
+                Some((open_char, open_len))
+                    if fence_char == open_char && fence_len >= open_len && info.is_empty() =>
+                {
+                    code_fence = None
+                }
+                Some(_) => {}
+            }
+        }
+        after_blank_line = trimmed.is_empty() && code_fence.is_none();
+        offset += line.len();
+    }
+
+    let Some(cut) = cut else {
+        return false;
+    };
+    text.replace_range(..cut, trimmed_marker(format));
+    true
+}
+
+/// How many lines streamed markdown accumulates before it is sent to the GUI. Lower values
+/// show content sooner; higher values rebuild the page less often.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlushThresholds {
+    /// Lines before the first update
+    pub first_lines: usize,
+    /// Lines after which an update is sent regardless of content. A blank line sends one once
+    /// half as many have accumulated.
+    pub flush_lines: usize,
+}
+
+impl Default for FlushThresholds {
+    fn default() -> Self {
+        Self {
+            first_lines: 5,
+            flush_lines: 10,
+        }
+    }
+}
+
+impl FlushThresholds {
+    /// Lines a paragraph break needs before it sends an update.
+    fn paragraph_lines(&self) -> usize {
+        (self.flush_lines / 2).max(1)
+    }
+}
 
 /// Tracks the state of markdown parsing during streaming
 #[derive(Debug, Clone)]
 struct StreamingState {
-    /// Whether we're currently inside a code block
-    in_code_block: bool,
+    /// Fence character and length of the code block we're inside, if any
+    code_fence: Option<(char, usize)>,
     /// The language of the current code block (if any)
     code_language: String,
+    /// Whether we're inside a leading YAML front matter block
+    in_front_matter: bool,
+    /// How many raw `<details>` elements are open, which are sent whole so the page doesn't
+    /// close them early
+    details_depth: usize,
     /// Accumulated markdown content
     markdown_buffer: String,
     /// Track if we've sent the first content update
     sent_first_update: bool,
     /// Lines accumulated since last update
     lines_since_update: usize,
+    /// Whether the buffer ends inside an overlong line whose rest is still to come
+    in_long_line: bool,
+    /// Whether the code block we're inside opened at the left margin, outside any list or quote
+    fence_at_margin: bool,
+    /// Closing tag of raw HTML such as `<script>` or `<!--` that runs on past blank lines
+    raw_html_end: Option<&'static str>,
+    /// Whether the last line ended a top-level block: a blank line, or the closing fence of a
+    /// code block at the margin
+    at_block_end: bool,
+    /// Whether the buffer starts a new top-level block rather than continuing the last chunk's
+    starts_block: bool,
+    /// Heading anchors sent so far, which a chunk rendered on its own doesn't know to avoid
+    heading_ids: HashSet<String>,
+    thresholds: FlushThresholds,
 }
 
 impl StreamingState {
-    fn new() -> Self {
+    fn new(thresholds: FlushThresholds) -> Self {
         Self {
-            in_code_block: false,
+            code_fence: None,
             code_language: String::new(),
+            in_front_matter: false,
+            details_depth: 0,
             markdown_buffer: String::new(),
             sent_first_update: false,
             lines_since_update: 0,
+            in_long_line: false,
+            fence_at_margin: false,
+            raw_html_end: None,
+            at_block_end: false,
+            starts_block: false,
+            heading_ids: HashSet::new(),
+            thresholds,
         }
     }
 
+    /// Processes a piece of an overlong line whose rest is still to come, and returns whether
+    /// we should send an update. The buffer may then end mid-line, so only its size can send
+    /// one.
+    fn process_partial_line(&mut self, piece: &str) -> bool {
+        self.markdown_buffer.push_str(piece);
+        self.in_long_line = true;
+        self.at_block_end = false;
+        self.code_fence.is_none()
+            && self.details_depth == 0
+            && !self.in_front_matter
+            && self.markdown_buffer.len() >= MAX_CHUNK_BYTES
+    }
+
     /// Processes a line and returns whether we should send an update
     fn process_line(&mut self, line: &str) -> bool {
+        // Normalize CRLF input so a stray '\r' never leaks into the buffer or language token
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        let is_first_line = !self.sent_first_update && self.markdown_buffer.is_empty();
         self.lines_since_update += 1;
         self.markdown_buffer.push_str(line);
         self.markdown_buffer.push('\n');
+        self.at_block_end = false;
+
+        // The end of an overlong line: what came before it was text, so it can't open or
+        // close a block
+        if std::mem::take(&mut self.in_long_line) {
+            return !self.in_front_matter && self.should_send(false);
+        }
+
+        // Keep a leading front matter block within the first update so it is parsed as a whole
+        if is_first_line && front_matter::is_fence(line) {
+            self.in_front_matter = true;
+            return false;
+        }
+        if self.in_front_matter {
+            if front_matter::is_closing_fence(line)
+                || self.lines_since_update >= MAX_FRONT_MATTER_LINES
+            {
+                self.in_front_matter = false;
+            }
+            return false;
+        }
 
         let trimmed = line.trim();
 
         // Check for code block start/end
-        if trimmed.starts_with("```") {
-            if !self.in_code_block {
-                // Starting a code block
-                self.in_code_block = true;
-                self.code_language = trimmed.strip_prefix("```").unwrap_or("").to_string();
-                debug!(
-                    "Starting code block with language: '{}'",
-                    self.code_language
-                );
-            } else {
-                // Ending a code block
-                self.in_code_block = false;
-                self.code_language.clear();
-                debug!("Ending code block");
-                // Always send update after code block ends
-                return true;
+        if let Some((fence_char, fence_len, info)) = parse_fence(trimmed) {
+            match self.code_fence {
+                None => {
+                    // Starting a code block
+                    self.code_fence = Some((fence_char, fence_len));
+                    self.code_language = info.to_string();
+                    self.fence_at_margin = line.starts_with(fence_char);
+                    debug!(
+                        "Starting code block with language: '{}'",
+                        self.code_language
+                    );
+                }
+                // Only a bare fence of the same kind, at least as long, closes the block;
+                // anything else (e.g. "```rust" inside a "````" block) is block content
+                Some((open_char, open_len))
+                    if fence_char == open_char && fence_len >= open_len && info.is_empty() =>
+                {
+                    // Ending a code block
+                    self.code_fence = None;
+                    self.code_language.clear();
+                    self.at_block_end = self.fence_at_margin
+                        && line.starts_with(fence_char)
+                        && self.details_depth == 0;
+                    debug!("Ending code block");
+                    // Always send update after code block ends, so the whole block
+                    // is parsed as one chunk, unless it sits in an open <details>
+                    return self.details_depth == 0;
+                }
+                Some(_) => {}
             }
         }
 
-        // Send update conditions (increased thresholds for better rapid streaming performance):
-        // IMPORTANT: Never send updates while inside a code block to prevent splitting
-        if !self.in_code_block {
-            // 1. First substantial content (after 5 lines, was 3)
-            if !self.sent_first_update && self.lines_since_update >= 5 {
+        if self.code_fence.is_none() {
+            self.track_details(trimmed);
+            self.track_raw_html(trimmed);
+        }
+        self.at_block_end = trimmed.is_empty()
+            && self.code_fence.is_none()
+            && self.details_depth == 0
+            && self.raw_html_end.is_none();
+
+        self.should_send(trimmed.is_empty())
+    }
+
+    /// Returns whether the buffer should be sent after a line outside front matter.
+    fn should_send(&self, blank_line: bool) -> bool {
+        // Send update conditions:
+        // IMPORTANT: Never send updates while inside a code block or <details> to prevent
+        // splitting
+        if self.code_fence.is_none() && self.details_depth == 0 {
+            // 1. First substantial content
+            if !self.sent_first_update && self.lines_since_update >= self.thresholds.first_lines {
                 return true;
             }
 
-            // 2. Send update after paragraph breaks (empty lines) with more accumulation
-            if trimmed.is_empty() && self.lines_since_update >= 5 {
+            // 2. Send update after paragraph breaks (empty lines) with some accumulation
+            if blank_line && self.lines_since_update >= self.thresholds.paragraph_lines() {
                 return true;
             }
 
             // 3. Send update after accumulating more lines to reduce rapid updates
-            if self.lines_since_update >= 10 {
+            if self.lines_since_update >= self.thresholds.flush_lines {
+                return true;
+            }
+
+            // 4. Send oversized content before it becomes too much to render at once
+            if self.markdown_buffer.len() >= MAX_CHUNK_BYTES {
                 return true;
             }
         }
@@ -84,10 +344,65 @@ impl StreamingState {
         false
     }
 
+    /// Counts `<details>` elements opened and closed on a line outside code blocks.
+    fn track_details(&mut self, trimmed_line: &str) {
+        let lower = trimmed_line.to_ascii_lowercase();
+        // Block-level HTML starts its line; a mention in prose doesn't open anything
+        if lower.starts_with("<details") {
+            self.details_depth += lower.matches("<details").count();
+        }
+        self.details_depth = self
+            .details_depth
+            .saturating_sub(lower.matches("</details").count());
+
+        if self.details_depth > 0 && self.lines_since_update >= MAX_DETAILS_LINES {
+            debug!("No closing </details> found, sending the buffered lines anyway");
+            self.details_depth = 0;
+        }
+    }
+
+    /// Notes raw HTML opened or closed on a line outside code blocks. Until it is closed, a
+    /// blank line doesn't end the block.
+    fn track_raw_html(&mut self, trimmed_line: &str) {
+        let lower = trimmed_line.to_ascii_lowercase();
+        if let Some(end) = self.raw_html_end {
+            if lower.contains(end) {
+                self.raw_html_end = None;
+            }
+            return;
+        }
+        self.raw_html_end = RAW_HTML_ELEMENTS
+            .iter()
+            .filter_map(|(start, end)| {
+                let open = lower.rfind(start)?;
+                (!lower[open..].contains(end)).then_some(*end)
+            })
+            .next();
+    }
+
     /// Marks that an update was sent and resets counters
     fn mark_update_sent(&mut self) {
         self.sent_first_update = true;
         self.lines_since_update = 0;
+        self.starts_block = self.at_block_end;
+    }
+
+    /// Remembers the heading anchors of rendered HTML, returning whether they are all new.
+    fn record_heading_ids(&mut self, html: &str) -> bool {
+        let mut all_new = true;
+        for id in heading_ids(html) {
+            all_new &= self.heading_ids.insert(id.to_string());
+        }
+        all_new
+    }
+
+    /// Whether buffered lines can be sent early without splitting a code block, `<details>` or
+    /// front matter
+    fn can_flush(&self) -> bool {
+        !self.markdown_buffer.is_empty()
+            && self.code_fence.is_none()
+            && self.details_depth == 0
+            && !self.in_front_matter
     }
 
     /// Gets the current markdown content
@@ -101,64 +416,373 @@ impl StreamingState {
     }
 }
 
-/// Reads from stdin line-by-line using state machine, sending incremental updates to the GUI.
-pub fn read_from_pipe_stateful(sender: mpsc::Sender<ContentUpdate>) -> Result<(), AppError> {
-    debug!("Starting stateful line-by-line reading from stdin");
-    let stdin = io::stdin();
-    let reader = BufReader::new(stdin);
-    let mut state = StreamingState::new();
+/// Recognizes a code fence line (three or more backticks or tildes), returning the fence
+/// character, its length and the trimmed info string.
+fn parse_fence(trimmed_line: &str) -> Option<(char, usize, &str)> {
+    let fence_char = trimmed_line
+        .chars()
+        .next()
+        .filter(|&c| c == '`' || c == '~')?;
+    let fence_len = trimmed_line
+        .chars()
+        .take_while(|&c| c == fence_char)
+        .count();
+    if fence_len < 3 {
+        return None;
+    }
+
+    let info = trimmed_line[fence_len..].trim();
+    // A backtick fence's info string can't contain backticks (that's inline code)
+    if fence_char == '`' && info.contains('`') {
+        return None;
+    }
+    Some((fence_char, fence_len, info))
+}
+
+/// Returns the anchors of the headings in rendered HTML, e.g. `intro` for `<h2 id="intro">`.
+fn heading_ids(html: &str) -> impl Iterator<Item = &str> {
+    html.match_indices("<h").filter_map(|(start, _)| {
+        let rest = &html[start + 2..];
+        let rest = rest.strip_prefix(|c: char| ('1'..='6').contains(&c))?;
+        let id = rest.strip_prefix(" id=\"")?;
+        id.find('"').map(|end| &id[..end])
+    })
+}
+
+/// Returns whether a chunk of markdown that starts a new top-level block renders the same on
+/// its own as at the end of the document.
+///
+/// A first line that is indented or a list item may continue the list before it. Footnotes and
+/// link reference definitions depend on the rest of the document, and raw HTML such as
+/// `<script>` or `<!--` may run on past blank lines, so chunks with those are left to a render
+/// of the whole document.
+fn renders_on_its_own(chunk: &str) -> bool {
+    let Some(first_line) = chunk.lines().find(|line| !line.trim().is_empty()) else {
+        return true;
+    };
+    let trimmed = first_line.trim_start();
+    if first_line.len() != trimmed.len()
+        || matches!(
+            trimmed.as_bytes(),
+            [b'-' | b'*' | b'+'] | [b'-' | b'*' | b'+', b' ' | b'\t', ..]
+        )
+        || is_ordered_list_item(trimmed)
+    {
+        return false;
+    }
+
+    let mut code_fence: Option<(char, usize)> = None;
+    for line in chunk.lines() {
+        let trimmed = line.trim();
+        if let Some((fence_char, fence_len, info)) = parse_fence(trimmed) {
+            match code_fence {
+                None => code_fence = Some((fence_char, fence_len)),
+                Some((open_char, open_len))
+                    if fence_char == open_char && fence_len >= open_len && info.is_empty() =>
+                {
+                    code_fence = None
+                }
+                Some(_) => {}
+            }
+            continue;
+        }
+        if code_fence.is_some() {
+            continue;
+        }
+
+        let lower = trimmed.to_ascii_lowercase();
+        let raw_html = lower.starts_with("<!")
+            || lower.starts_with("<?")
+            || RAW_HTML_ELEMENTS
+                .iter()
+                .any(|(start, _)| lower.contains(start));
+        let reference_definition = trimmed.starts_with('[') && trimmed.contains("]:");
+        if raw_html || reference_definition || trimmed.contains("[^") {
+            return false;
+        }
+    }
+    true
+}
+
+/// Computes the fraction of a file that has been read, clamped to `0.0..=1.0`.
+///
+/// An empty file is treated as fully read.
+pub fn progress_fraction(bytes_read: u64, total_bytes: u64) -> f64 {
+    if total_bytes == 0 {
+        return 1.0;
+    }
+    (bytes_read as f64 / total_bytes as f64).clamp(0.0, 1.0)
+}
+
+/// Builds the document for the first streamed update, preferring a front matter title.
+fn first_document(content: String, title: &str, file_path: Option<&str>) -> DocumentContent {
+    let html_content = markdown::parse_markdown(&content);
+    let title = markdown::front_matter_title(&content).unwrap_or_else(|| title.to_string());
+    DocumentContent::new(content, html_content, title, file_path.map(str::to_string))
+}
+
+/// A line of input, or a piece of one too long to read at once.
+struct LinePiece {
+    /// The text, including the line terminator if the piece ends the line
+    text: String,
+    /// Whether the line goes on in the next piece
+    continues: bool,
+}
+
+/// The next step of a line-oriented input.
+enum NextLine {
+    Line(LinePiece),
+    /// No input arrived within [`STALL_FLUSH_TIMEOUT`]
+    Stalled,
+    End,
+}
+
+/// Reads up to and including the next `\n` into `bytes`, like [`BufRead::read_until`], but
+/// stops after [`MAX_LINE_BYTES`] so a huge line arrives in pieces. A piece never ends inside
+/// a UTF-8 character. Returns the number of bytes read, 0 at end of input, and whether the
+/// line goes on in the next piece.
+fn read_line_piece<R: BufRead>(reader: &mut R, bytes: &mut Vec<u8>) -> io::Result<(usize, bool)> {
+    let start = bytes.len();
+    let mut continues = false;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            break;
+        }
+
+        let room = MAX_LINE_BYTES.saturating_sub(bytes.len() - start);
+        let (taken, done) = if room == 0 {
+            // Finish the character the piece was cut in
+            let continuation = available
+                .iter()
+                .take_while(|&&byte| byte & 0xc0 == 0x80)
+                .count();
+            continues = continuation < available.len();
+            (continuation, continues)
+        } else {
+            let allowed = &available[..available.len().min(room)];
+            match allowed.iter().position(|&byte| byte == b'\n') {
+                Some(newline) => (newline + 1, true),
+                None => (allowed.len(), false),
+            }
+        };
+        bytes.extend_from_slice(&available[..taken]);
+        reader.consume(taken);
+        if done {
+            break;
+        }
+    }
 
-    for (line_num, line_result) in reader.lines().enumerate() {
-        let line = match line_result {
-            Ok(line) => line,
+    Ok((bytes.len() - start, continues))
+}
+
+/// Reads the next line, or the next piece of an overlong line, as text. Returns `None` at end
+/// of input.
+fn read_text_line<R: BufRead>(reader: &mut R) -> io::Result<Option<LinePiece>> {
+    let mut bytes = Vec::new();
+    let (read, continues) = read_line_piece(reader, &mut bytes)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    let text =
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some(LinePiece { text, continues }))
+}
+
+/// Reads the next line directly from a buffered source, blocking until it arrives.
+fn read_next_line<R: BufRead>(reader: &mut R) -> io::Result<NextLine> {
+    Ok(match read_text_line(reader)? {
+        Some(piece) => NextLine::Line(piece),
+        None => NextLine::End,
+    })
+}
+
+/// Sends each line of `reader` until end of input, returning whether end of input was reached.
+/// Returns `false` after a read error or once the streaming side has stopped.
+fn forward_lines<R: BufRead>(
+    mut reader: R,
+    line_sender: &mpsc::Sender<io::Result<LinePiece>>,
+) -> bool {
+    loop {
+        let result = match read_text_line(&mut reader) {
+            Ok(None) => return true,
+            Ok(Some(piece)) => line_sender.send(Ok(piece)),
+            Err(e) => {
+                let _ = line_sender.send(Err(e));
+                return false;
+            }
+        };
+        if result.is_err() {
+            return false; // The streaming side has stopped
+        }
+    }
+}
+
+/// Reads lines on a separate thread so the consumer can notice when the input stalls.
+fn spawn_line_reader<R: BufRead + Send + 'static>(
+    reader: R,
+) -> mpsc::Receiver<io::Result<LinePiece>> {
+    let (line_sender, line_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        forward_lines(reader, &line_sender);
+    });
+    line_receiver
+}
+
+/// Reads a named pipe on a separate thread. A FIFO reaches end of input whenever its last
+/// writer closes it, so it is opened again to wait for the next writer instead of ending.
+fn spawn_fifo_reader(path: String) -> mpsc::Receiver<io::Result<LinePiece>> {
+    let (line_sender, line_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            // Blocks until a writer opens the pipe
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    let _ = line_sender.send(Err(e));
+                    return;
+                }
+            };
+            debug!("Writer connected to {path}");
+            if !forward_lines(BufReader::new(file), &line_sender) {
+                return;
+            }
+            debug!("Writer closed {path}, waiting for the next one");
+        }
+    });
+    line_receiver
+}
+
+/// Waits for the next line from a reader thread, reporting a stall after
+/// [`STALL_FLUSH_TIMEOUT`] without input.
+fn receive_line(lines: &mpsc::Receiver<io::Result<LinePiece>>) -> io::Result<NextLine> {
+    match lines.recv_timeout(STALL_FLUSH_TIMEOUT) {
+        Ok(Ok(piece)) => Ok(NextLine::Line(piece)),
+        Ok(Err(e)) => Err(e),
+        Err(RecvTimeoutError::Timeout) => Ok(NextLine::Stalled),
+        Err(RecvTimeoutError::Disconnected) => Ok(NextLine::End),
+    }
+}
+
+/// Sends the buffered lines as the first document or as an appended chunk, then clears the
+/// buffer. `lines_sent` is the document line the chunk starts at.
+fn send_buffered(
+    state: &mut StreamingState,
+    sender: &mpsc::Sender<ContentUpdate>,
+    title: &str,
+    file_path: Option<&str>,
+    lines_sent: usize,
+) -> Result<(), AppError> {
+    let content = state.get_content().to_string();
+    debug!("Sending update with {} bytes", content.len());
+
+    let update = if state.sent_first_update {
+        // For subsequent updates, use Append with just the new content chunk
+        let mut html_content = markdown::parse_markdown_fragment(&content, lines_sent);
+        let new_headings = state.record_heading_ids(&html_content);
+        let standalone = state.starts_block && new_headings && renders_on_its_own(&content);
+        if state.in_long_line {
+            html_content.push_str(SPLIT_LINE_MARKER_HTML);
+        }
+        ContentUpdate::Append {
+            markdown: content,
+            html: html_content,
+            standalone,
+        }
+    } else {
+        // First update: use FullReplace to establish initial content
+        let mut document = first_document(content, title, file_path);
+        state.record_heading_ids(&document.html);
+        if state.in_long_line {
+            document.html.push_str(SPLIT_LINE_MARKER_HTML);
+        }
+        ContentUpdate::FullReplace(Box::new(document))
+    };
+
+    sender.send(update)?;
+    state.mark_update_sent();
+    state.clear_buffer(); // Clear buffer after successful send
+    Ok(())
+}
+
+/// Reads markdown line-by-line using the state machine, sending incremental updates to the GUI.
+///
+/// `next_line` supplies the input; when it reports a stall, buffered lines are sent right
+/// away (unless that would split a code block) so slow producers still show up promptly.
+///
+/// When `total_bytes` is known, a `ContentUpdate::Progress` is sent after every
+/// content update so the GUI can display how much of the input has been read.
+fn stream_lines(
+    mut next_line: impl FnMut() -> io::Result<NextLine>,
+    sender: &mpsc::Sender<ContentUpdate>,
+    title: &str,
+    file_path: Option<&str>,
+    total_bytes: Option<u64>,
+    thresholds: FlushThresholds,
+) -> Result<(), AppError> {
+    let mut state = StreamingState::new(thresholds);
+    let mut bytes_read: u64 = 0;
+    // Lines read in full; a piece of an overlong line is part of line `line_num + 1`
+    let mut line_num = 0;
+    // Lines already sent to the GUI, i.e. the document line where the next chunk starts
+    let mut lines_sent = 0;
+
+    loop {
+        let piece = match next_line() {
+            Ok(NextLine::Line(piece)) => piece,
+            Ok(NextLine::Stalled) => {
+                if state.can_flush() {
+                    debug!("Input stalled, flushing buffered lines");
+                    if send_buffered(&mut state, sender, title, file_path, lines_sent).is_err() {
+                        info!("GUI receiver disconnected. Shutting down streaming thread.");
+                        return Ok(());
+                    }
+                    lines_sent = line_num;
+                }
+                continue;
+            }
+            Ok(NextLine::End) => break,
             Err(e) => {
                 error!("Failed to read line {}: {}", line_num + 1, e);
                 return Err(AppError::from(e));
             }
         };
-
-        debug!("Processing line {}: {:?}", line_num + 1, line);
+        bytes_read += piece.text.len() as u64;
 
         // Process the line and check if we should send an update
-        let should_update = state.process_line(&line);
-
-        if should_update {
-            let content = state.get_content().to_string();
+        let should_update = if piece.continues {
             debug!(
-                "Sending update with {} bytes after line {}",
-                content.len(),
+                "Processing {} bytes of line {}",
+                piece.text.len(),
                 line_num + 1
             );
+            state.process_partial_line(&piece.text)
+        } else {
+            line_num += 1;
+            // Strip the line terminator; a trailing '\r' from CRLF input is handled by
+            // `process_line`
+            let line = piece.text.strip_suffix('\n').unwrap_or(&piece.text);
+            debug!("Processing line {line_num}: {line:?}");
+            state.process_line(line)
+        };
 
-            // Parse just the new content chunk
-            let html_content = markdown::parse_markdown(&content);
-
-            let update = if state.sent_first_update {
-                // For subsequent updates, use Append with just the new content
-                ContentUpdate::Append {
-                    markdown: content,
-                    html: html_content,
-                }
-            } else {
-                // First update: use FullReplace to establish initial content
-                let document_content =
-                    DocumentContent::new(content, html_content, "Piped Input".to_string(), None);
-                ContentUpdate::FullReplace(document_content)
-            };
+        if should_update {
+            if send_buffered(&mut state, sender, title, file_path, lines_sent).is_err() {
+                info!("GUI receiver disconnected. Shutting down streaming thread.");
+                return Ok(());
+            }
+            debug!("Successfully sent content update after line {line_num}");
+            lines_sent = line_num;
 
-            match sender.send(update) {
-                Ok(()) => {
-                    debug!(
-                        "Successfully sent content update after line {}",
-                        line_num + 1
-                    );
-                    state.mark_update_sent();
-                    state.clear_buffer(); // Clear buffer after successful send
-                }
-                Err(e) => {
-                    error!("Failed to send content update: {e}");
+            if let Some(total) = total_bytes {
+                let fraction = progress_fraction(bytes_read, total);
+                if sender.send(ContentUpdate::Progress(fraction)).is_err() {
                     info!("GUI receiver disconnected. Shutting down streaming thread.");
-                    break;
+                    return Ok(());
                 }
             }
         }
@@ -166,39 +790,321 @@ pub fn read_from_pipe_stateful(sender: mpsc::Sender<ContentUpdate>) -> Result<()
 
     // Send any remaining content
     if !state.get_content().is_empty() {
-        let content = state.get_content().to_string();
-        let html_content = markdown::parse_markdown(&content);
+        send_buffered(&mut state, sender, title, file_path, lines_sent)?;
+        debug!("Successfully sent final content update");
+    }
+
+    // Let the GUI know reading is complete so it can remove the progress bar
+    if total_bytes.is_some() && sender.send(ContentUpdate::Progress(1.0)).is_err() {
+        debug!("GUI receiver disconnected before final progress update");
+    }
+
+    Ok(())
+}
 
-        let update = if state.sent_first_update {
-            ContentUpdate::Append {
-                markdown: content,
-                html: html_content,
+/// Streams input that isn't markdown as preformatted text: terminal output with ANSI escape
+/// codes as colored text, anything else verbatim.
+///
+/// Lines are sent whenever the input pauses, so colors carried across chunks are kept by the
+/// shared renderer.
+fn read_from_pipe_preformatted<R: Read>(
+    mut reader: BufReader<R>,
+    sender: &mpsc::Sender<ContentUpdate>,
+    format: ContentFormat,
+    title: &str,
+) -> Result<(), AppError> {
+    let mut renderer = AnsiRenderer::new();
+    let mut chunk = String::new();
+    let mut chunk_lines = 0;
+    let mut sent_first_update = false;
+    let mut bytes = Vec::new();
+
+    loop {
+        bytes.clear();
+        let (read, _) = read_line_piece(&mut reader, &mut bytes)?;
+        if read > 0 {
+            // Terminal output isn't guaranteed to be valid UTF-8
+            chunk.push_str(&String::from_utf8_lossy(&bytes));
+            chunk_lines += 1;
+        }
+
+        // Flush at the end of input, when no more input is buffered, or after a long burst
+        let at_end = read == 0;
+        let should_send =
+            at_end || reader.buffer().is_empty() || chunk_lines >= MAX_PREFORMATTED_CHUNK_LINES;
+        if should_send && !chunk.is_empty() {
+            let content = std::mem::take(&mut chunk);
+            chunk_lines = 0;
+            let html = match format {
+                ContentFormat::Ansi => renderer.render(&content),
+                _ => plain_to_html(&content),
+            };
+
+            let update = if sent_first_update {
+                // The renderer carries colors from chunk to chunk
+                ContentUpdate::Append {
+                    markdown: content,
+                    html,
+                    standalone: true,
+                }
+            } else {
+                let mut document = DocumentContent::new(content, html, title.to_string(), None);
+                document.format = format.clone();
+                ContentUpdate::FullReplace(Box::new(document))
+            };
+
+            if sender.send(update).is_err() {
+                info!("GUI receiver disconnected. Shutting down streaming thread.");
+                return Ok(());
             }
-        } else {
-            // Final content is also the first content
-            let document_content =
-                DocumentContent::new(content, html_content, "Piped Input".to_string(), None);
-            ContentUpdate::FullReplace(document_content)
-        };
+            sent_first_update = true;
+        }
 
-        match sender.send(update) {
-            Ok(()) => debug!("Successfully sent final content update"),
-            Err(e) => error!("Failed to send final content: {e}"),
+        if at_end {
+            return Ok(());
         }
     }
+}
+
+/// Guesses whether the start of the input is plain text such as a log rather than markdown:
+/// several lines with no blank line between them and no line that looks like markdown syntax.
+fn looks_like_plain_text(sample: &str) -> bool {
+    // The last line of the sample may be cut short
+    let lines: Vec<&str> = sample.lines().take(PLAIN_TEXT_SAMPLE_LINES).collect();
+    if lines.len() < MIN_PLAIN_TEXT_LINES {
+        return false;
+    }
+
+    lines.iter().all(|line| {
+        let trimmed = line.trim_start();
+        !trimmed.is_empty()
+            && !trimmed.starts_with(['#', '>', '|', '-', '*', '+', '`', '~', '<'])
+            && !trimmed.contains("](")
+            && !trimmed.contains("**")
+            && !trimmed.contains('`')
+            && !is_ordered_list_item(trimmed)
+    })
+}
+
+/// Returns whether a line starts like `1. item` or `1) item`.
+fn is_ordered_list_item(line: &str) -> bool {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    digits > 0 && matches!(&line.as_bytes()[digits..], [b'.' | b')', b' ', ..])
+}
+
+/// Reads from stdin line-by-line using state machine, sending incremental updates to the GUI.
+///
+/// Input that starts with ANSI escape codes is treated as terminal output, and input that
+/// doesn't look like markdown (or any input when `plain` is set) is shown verbatim.
+pub fn read_from_pipe_stateful(
+    sender: mpsc::Sender<ContentUpdate>,
+    plain: bool,
+    thresholds: FlushThresholds,
+    title: Option<&str>,
+) -> Result<(), AppError> {
+    debug!("Starting stateful line-by-line reading from stdin");
+    let title = title.unwrap_or(PIPE_TITLE);
+
+    // A named pipe redirected to stdin (`homo < /tmp/md`) is followed across writers, like
+    // one given as the file argument
+    if let Some(path) = stdin_fifo_path() {
+        info!("Following named pipe {} on stdin", path.display());
+        let lines = spawn_fifo_reader(path.to_string_lossy().into_owned());
+        stream_lines(
+            || receive_line(&lines),
+            &sender,
+            title,
+            None,
+            None,
+            thresholds,
+        )?;
+        return Ok(());
+    }
+
+    let mut reader = BufReader::new(io::stdin());
+
+    // Decide how to render from the first data available
+    let sample = String::from_utf8_lossy(reader.fill_buf()?).into_owned();
+    if ansi::contains_ansi(&sample) {
+        info!("ANSI escape codes detected, rendering input as terminal output");
+        read_from_pipe_preformatted(reader, &sender, ContentFormat::Ansi, title)?;
+        debug!("Finished reading from stdin");
+        return Ok(());
+    }
+    if plain || looks_like_plain_text(&sample) {
+        info!("Rendering input as plain text");
+        read_from_pipe_preformatted(reader, &sender, ContentFormat::Plain, title)?;
+        debug!("Finished reading from stdin");
+        return Ok(());
+    }
 
+    // Read on another thread so a pause in the input can flush what has arrived so far
+    let lines = spawn_line_reader(reader);
+    stream_lines(
+        || receive_line(&lines),
+        &sender,
+        title,
+        None,
+        None,
+        thresholds,
+    )?;
     debug!("Finished reading from stdin");
     Ok(())
 }
 
 /// Main entry point for reading from stdin pipes.
 /// Uses the new stateful line-by-line approach.
-pub fn read_from_pipe(sender: mpsc::Sender<ContentUpdate>) -> Result<(), AppError> {
-    read_from_pipe_stateful(sender)
+pub fn read_from_pipe(
+    sender: mpsc::Sender<ContentUpdate>,
+    plain: bool,
+    thresholds: FlushThresholds,
+    title: Option<&str>,
+) -> Result<(), AppError> {
+    read_from_pipe_stateful(sender, plain, thresholds, title)
+}
+
+/// Returns whether a path is a named pipe (created with `mkfifo`) rather than a regular file.
+pub fn is_fifo(path: &str) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+/// Returns the path of the named pipe stdin is redirected from, if it is one. An anonymous
+/// pipe (`cmd | homo`) is a FIFO too, but has no path and ends with its writer.
+fn stdin_fifo_path() -> Option<PathBuf> {
+    let stdin = File::from(io::stdin().as_fd().try_clone_to_owned().ok()?);
+    if !stdin.metadata().ok()?.file_type().is_fifo() {
+        return None;
+    }
+    // Opening `/dev/fd/0` on macOS only duplicates the shell's descriptor, which doesn't wait
+    // for the next writer, so the pipe is reopened by its path instead
+    descriptor_path(stdin.as_raw_fd())
+}
+
+/// Returns the file system path an open descriptor refers to.
+#[cfg(target_os = "macos")]
+fn descriptor_path(fd: RawFd) -> Option<PathBuf> {
+    use std::ffi::{CStr, OsStr, c_int};
+    use std::os::unix::ffi::OsStrExt;
+
+    const F_GETPATH: c_int = 50;
+    const MAXPATHLEN: usize = 1024;
+    unsafe extern "C" {
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+
+    let mut buffer = [0u8; MAXPATHLEN];
+    // SAFETY: F_GETPATH writes a NUL-terminated path of at most MAXPATHLEN bytes to the buffer
+    if unsafe { fcntl(fd, F_GETPATH, buffer.as_mut_ptr()) } == -1 {
+        return None;
+    }
+    let path = CStr::from_bytes_until_nul(&buffer).ok()?;
+    Some(PathBuf::from(OsStr::from_bytes(path.to_bytes())))
+}
+
+/// Returns the file system path an open descriptor refers to.
+#[cfg(not(target_os = "macos"))]
+fn descriptor_path(fd: RawFd) -> Option<PathBuf> {
+    // Anonymous pipes read as `pipe:[inode]`
+    let path = std::fs::read_link(format!("/proc/self/fd/{fd}")).ok()?;
+    path.is_absolute().then_some(path)
+}
+
+/// Streams markdown written to a named pipe, following it across writers: each time one
+/// closes the pipe, what it wrote is shown and the next writer's lines are appended.
+///
+/// The document has no file path, so it is never reloaded or edited through the pipe.
+pub fn read_from_fifo(
+    sender: mpsc::Sender<ContentUpdate>,
+    path: &str,
+    thresholds: FlushThresholds,
+) -> Result<(), AppError> {
+    info!("Following named pipe {path}");
+    let lines = spawn_fifo_reader(path.to_string());
+    stream_lines(
+        || receive_line(&lines),
+        &sender,
+        &file_title(path),
+        None,
+        None,
+        thresholds,
+    )
+}
+
+/// Returns the file name component of a path for use as a document title.
+fn file_title(filename: &str) -> String {
+    std::path::Path::new(filename)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("Untitled")
+        .to_string()
+}
+
+/// Splits a `file.md#section` argument into the file and the heading anchor to show. A path
+/// that exists as given is never split, so file names containing `#` still open.
+pub fn split_fragment(path: &str) -> (String, Option<String>) {
+    if !std::path::Path::new(path).exists()
+        && let Some((file, fragment)) = path.rsplit_once('#')
+        && !file.is_empty()
+        && !fragment.is_empty()
+    {
+        return (file.to_string(), Some(fragment.to_string()));
+    }
+    (path.to_string(), None)
+}
+
+/// Returns whether a document argument is an `http`/`https` URL rather than a file path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// Returns the last path segment of a URL for use as a document title.
+fn url_title(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let path = without_query
+        .split_once("://")
+        .map_or(without_query, |(_, rest)| rest)
+        .trim_end_matches('/');
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+/// Fetches a markdown document over HTTP(S), parses it and sends it to the GUI.
+pub fn read_from_url(sender: mpsc::Sender<ContentUpdate>, url: &str) -> Result<(), AppError> {
+    debug!("Fetching URL: {url}");
+    let response = ureq::get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => AppError::Network(format!(
+            "{url} returned HTTP {code} {}",
+            response.status_text()
+        )),
+        // The transport error message already names the URL
+        ureq::Error::Transport(transport) => AppError::Network(transport.to_string()),
+    })?;
+    let buffer = response
+        .into_string()
+        .map_err(|e| AppError::Network(format!("Failed to read response from {url}: {e}")))?;
+    debug!("Fetched {} bytes", buffer.len());
+    let buffer = markdown::file_markdown(url.split(['?', '#']).next().unwrap_or(url), buffer);
+
+    let html_content = markdown::parse_markdown(&buffer);
+    let title = markdown::front_matter_title(&buffer).unwrap_or_else(|| url_title(url));
+    debug!("URL title: {title}");
+
+    let mut document_content = DocumentContent::new(buffer, html_content, title, None);
+    document_content.source_url = Some(url.to_string());
+
+    debug!("Sending content update to GUI");
+    sender.send(ContentUpdate::FullReplace(Box::new(document_content)))?;
+    debug!("Successfully sent fetched content to GUI");
+    Ok(())
 }
 
 /// Reads the entire file, parses markdown, and sends ContentUpdate to the GUI.
-pub fn read_from_file(sender: mpsc::Sender<ContentUpdate>, filename: &str) -> Result<(), AppError> {
+///
+/// `fragment` names a heading anchor the window should scroll to once the file is shown.
+pub fn read_from_file(
+    sender: mpsc::Sender<ContentUpdate>,
+    filename: &str,
+    fragment: Option<&str>,
+) -> Result<(), AppError> {
     debug!("Opening file: {filename}");
     let mut file = File::open(filename)?;
     let mut buffer = String::new();
@@ -207,23 +1113,390 @@ pub fn read_from_file(sender: mpsc::Sender<ContentUpdate>, filename: &str) -> Re
     file.read_to_string(&mut buffer)?;
     let buffer_len = buffer.len();
     debug!("Read {buffer_len} bytes from file");
+    // A diagram file such as `flow.mmd` is shown as a document holding just that diagram
+    let buffer = markdown::file_markdown(filename, buffer);
 
     debug!("Parsing markdown");
     let html_content = markdown::parse_markdown(&buffer);
-    let title = std::path::Path::new(filename)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("Untitled")
-        .to_string();
+    let title = markdown::front_matter_title(&buffer).unwrap_or_else(|| file_title(filename));
     debug!("File title: {title}");
 
-    let document_content =
+    let mut document_content =
         DocumentContent::new(buffer, html_content, title, Some(filename.to_string()));
+    document_content.fragment = fragment.map(str::to_string);
 
     debug!("Sending content update to GUI");
-    match sender.send(ContentUpdate::FullReplace(document_content)) {
-        Ok(()) => debug!("Successfully sent file content to GUI"),
-        Err(e) => error!("Failed to send content to GUI: {e}"),
-    }
+    recent_files::add_recent_file(filename);
+
+    sender.send(ContentUpdate::FullReplace(Box::new(document_content)))?;
+    debug!("Successfully sent file content to GUI");
     Ok(())
 }
+
+/// Streams a file line-by-line like a pipe, reporting read progress to the GUI.
+pub fn read_from_file_streaming(
+    sender: mpsc::Sender<ContentUpdate>,
+    filename: &str,
+    thresholds: FlushThresholds,
+) -> Result<(), AppError> {
+    debug!("Opening file for streaming: {filename}");
+    let file = File::open(filename)?;
+    let total_bytes = file.metadata()?.len();
+    debug!("Streaming {total_bytes} bytes from file");
+    recent_files::add_recent_file(filename);
+
+    let title = file_title(filename);
+    let mut reader = BufReader::new(file);
+    stream_lines(
+        || read_next_line(&mut reader),
+        &sender,
+        &title,
+        Some(filename),
+        Some(total_bytes),
+        thresholds,
+    )?;
+    debug!("Finished streaming file: {filename}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Cursor;
+    use std::process::Command;
+
+    /// Streams `input` as a file of known size and returns every update sent.
+    fn stream(input: &str, thresholds: FlushThresholds) -> Vec<ContentUpdate> {
+        let (sender, receiver) = mpsc::channel();
+        let mut reader = Cursor::new(input.as_bytes().to_vec());
+        stream_lines(
+            || read_next_line(&mut reader),
+            &sender,
+            "Test",
+            None,
+            Some(input.len() as u64),
+            thresholds,
+        )
+        .unwrap();
+        drop(sender);
+        receiver.into_iter().collect()
+    }
+
+    /// Returns the markdown of each content update, in order.
+    fn markdown_chunks(updates: &[ContentUpdate]) -> Vec<&str> {
+        updates
+            .iter()
+            .filter_map(|update| match update {
+                ContentUpdate::FullReplace(document) => Some(document.markdown.as_str()),
+                ContentUpdate::Append { markdown, .. } => Some(markdown.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the HTML of each content update, in order.
+    fn html_chunks(updates: &[ContentUpdate]) -> Vec<&str> {
+        updates
+            .iter()
+            .filter_map(|update| match update {
+                ContentUpdate::FullReplace(document) => Some(document.html.as_str()),
+                ContentUpdate::Append { html, .. } => Some(html.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Builds the document from a stream's updates the way the viewer does.
+    fn document(updates: Vec<ContentUpdate>) -> (DocumentContent, Vec<bool>) {
+        let mut document = None;
+        let mut standalone_chunks = Vec::new();
+        for update in updates {
+            match update {
+                ContentUpdate::FullReplace(first) => document = Some(*first),
+                ContentUpdate::Append {
+                    markdown,
+                    html,
+                    standalone,
+                } => {
+                    let document = document.as_mut().unwrap();
+                    document.append_chunk(&markdown, &html, standalone);
+                    standalone_chunks.push(standalone);
+                }
+                _ => {}
+            }
+        }
+        (document.unwrap(), standalone_chunks)
+    }
+
+    #[test]
+    fn streamed_document_matches_a_whole_render() {
+        let _settings = markdown::RenderSettings::default().scoped();
+        let mut input = String::from("# Streamed\n\n");
+        for line in 0..30 {
+            input.push_str(&format!("Line {line} of a long paragraph\n"));
+        }
+        input.push('\n');
+        for item in 0..25 {
+            input.push_str(&format!("- item {item}\n"));
+        }
+        input.push_str("\n| n | square |\n|---|---:|\n");
+        for row in 0..25 {
+            input.push_str(&format!("| {row} | {} |\n", row * row));
+        }
+        input.push('\n');
+        for section in 0..6 {
+            input.push_str(&format!("## Notes\n\nSection {section}, see [^note].\n\n"));
+            input.push_str("Short paragraph.\n\nAnother one.\n\nAnd one more.\n\n");
+        }
+        input.push_str("[^note]: The note.\n");
+
+        let (document, standalone) = document(stream(&input, FlushThresholds::default()));
+        assert_eq!(document.html, markdown::parse_markdown(&input));
+        // Chunks cut mid-block are rendered with the whole document, the rest on their own
+        assert!(standalone.contains(&false), "{standalone:?}");
+        assert!(standalone.contains(&true), "{standalone:?}");
+    }
+
+    #[test]
+    fn chunks_that_continue_or_depend_on_the_document_are_not_standalone() {
+        assert!(renders_on_its_own(
+            "Paragraph\n\n## Heading\n\n```\n[^x] <!--\n```\n"
+        ));
+        assert!(!renders_on_its_own("- continued list\n"));
+        assert!(!renders_on_its_own("    indented continuation\n"));
+        assert!(!renders_on_its_own("2. ordered\n"));
+        assert!(!renders_on_its_own("Text[^1]\n"));
+        assert!(!renders_on_its_own("[ref]: https://example.com\n"));
+        assert!(!renders_on_its_own("<script>\n"));
+        assert!(!renders_on_its_own("<!-- open comment\n"));
+    }
+
+    #[test]
+    fn blank_line_inside_raw_html_does_not_end_the_block() {
+        let mut state = StreamingState::new(FlushThresholds::default());
+        for line in ["<script>", "let a = 1;", ""] {
+            state.process_line(line);
+        }
+        assert!(!state.at_block_end);
+        for line in ["</script>", ""] {
+            state.process_line(line);
+        }
+        assert!(state.at_block_end);
+
+        state.process_line("Text <!-- a comment");
+        state.process_line("");
+        assert!(!state.at_block_end);
+    }
+
+    #[test]
+    fn heading_ids_are_read_from_html() {
+        let html = r#"<h1 id="title">Title</h1><p>x</p><h2 id="notes-1">Notes</h2><hr>"#;
+        assert_eq!(heading_ids(html).collect::<Vec<_>>(), ["title", "notes-1"]);
+    }
+
+    #[test]
+    fn huge_line_without_newline_streams_in_chunks() {
+        let input = "word ".repeat(1024 * 1024);
+        let (sender, receiver) = mpsc::channel();
+        let reader_input = input.clone();
+        let streamer = thread::spawn(move || {
+            let mut reader = Cursor::new(reader_input.into_bytes());
+            stream_lines(
+                || read_next_line(&mut reader),
+                &sender,
+                "Test",
+                None,
+                None,
+                FlushThresholds::default(),
+            )
+        });
+
+        // The first part shows long before the whole line has been read
+        let first = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        let mut updates = vec![first];
+        updates.extend(receiver.iter());
+        streamer.join().unwrap().unwrap();
+
+        let chunks = markdown_chunks(&updates);
+        assert!(chunks.len() > 1);
+        assert!(
+            chunks
+                .iter()
+                .all(|chunk| chunk.len() <= MAX_CHUNK_BYTES + MAX_LINE_BYTES)
+        );
+        // Pieces are joined back into the line as it was, ended like every other line
+        assert_eq!(chunks.concat(), format!("{input}\n"));
+
+        let html = html_chunks(&updates);
+        let (last, split) = html.split_last().unwrap();
+        assert!(
+            split
+                .iter()
+                .all(|html| html.ends_with(SPLIT_LINE_MARKER_HTML))
+        );
+        assert!(!last.contains("split-line-marker"));
+    }
+
+    #[test]
+    fn pieces_of_a_long_line_count_as_one_line() {
+        let long_line = "x".repeat(MAX_CHUNK_BYTES + 1);
+        let input = format!("{long_line}\n\n- [ ] task\n");
+        let updates = stream(&input, FlushThresholds::default());
+
+        assert_eq!(markdown_chunks(&updates).concat(), input);
+        let html = html_chunks(&updates).concat();
+        assert!(html.contains(r#"data-line="2""#), "{html}");
+    }
+
+    #[test]
+    fn progress_fraction_is_clamped() {
+        assert_eq!(progress_fraction(50, 200), 0.25);
+        assert_eq!(progress_fraction(300, 200), 1.0);
+        assert_eq!(progress_fraction(0, 0), 1.0);
+    }
+
+    #[test]
+    fn progress_rises_to_one_for_a_file_of_known_size() {
+        let input: String = (1..=100).map(|n| format!("Line {n}\n\n")).collect();
+        let updates = stream(&input, FlushThresholds::default());
+        let progress: Vec<f64> = updates
+            .iter()
+            .filter_map(|update| match update {
+                ContentUpdate::Progress(fraction) => Some(*fraction),
+                _ => None,
+            })
+            .collect();
+
+        assert!(progress.len() > 2);
+        assert!(progress[0] < 1.0);
+        assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(progress.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn crlf_code_fence_leaves_no_carriage_return() {
+        let mut state = StreamingState::new(FlushThresholds::default());
+        state.process_line("```rust\r");
+        assert_eq!(state.code_language, "rust");
+
+        let updates = stream(
+            "```rust\r\nfn main() {}\r\n```\r\n",
+            FlushThresholds::default(),
+        );
+        let markdown = markdown_chunks(&updates).concat();
+        assert_eq!(markdown, "```rust\nfn main() {}\n```\n");
+        // Highlighted as Rust, so `fn` is a token of its own
+        let html = html_chunks(&updates).concat();
+        assert!(html.contains(">fn</span>"), "{html}");
+    }
+
+    /// Asserts that no chunk ends inside a fenced code block of the streamed document.
+    fn assert_code_blocks_whole(chunks: &[&str]) {
+        let markdown = chunks.concat();
+        let boundaries: Vec<usize> = chunks
+            .iter()
+            .scan(0, |offset, chunk| {
+                *offset += chunk.len();
+                Some(*offset)
+            })
+            .collect();
+        let parser = pulldown_cmark::Parser::new(&markdown).into_offset_iter();
+        for (event, range) in parser {
+            if let pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(_)) = event {
+                assert!(
+                    boundaries
+                        .iter()
+                        .all(|&boundary| boundary <= range.start || boundary >= range.end),
+                    "chunk boundary inside {:?}",
+                    &markdown[range]
+                );
+            }
+        }
+    }
+
+    /// Sends an update after every line that allows one.
+    const EAGER: FlushThresholds = FlushThresholds {
+        first_lines: 1,
+        flush_lines: 1,
+    };
+
+    #[test]
+    fn long_code_block_is_sent_in_one_chunk() {
+        let code: String = (1..=50).map(|n| format!("let line_{n} = {n};\n")).collect();
+        let input = format!("Intro\n\n```rust\n{code}```\n\nAfter\n");
+        let updates = stream(&input, EAGER);
+
+        let chunks = markdown_chunks(&updates);
+        assert!(chunks.len() > 2);
+        assert_code_blocks_whole(&chunks);
+        let html = html_chunks(&updates);
+        assert!(
+            html.iter()
+                .any(|html| html.contains("line_1") && html.contains("line_50"))
+        );
+    }
+
+    #[test]
+    fn nested_and_tilde_fences_are_not_split() {
+        let input = "Intro\n\n\
+            ````markdown\n```rust\nfn main() {}\n```\n\nMore\n````\n\n\
+            ~~~\n```\nNot a fence\n\n~~~\n\n\
+            After\n";
+        let updates = stream(input, EAGER);
+
+        let chunks = markdown_chunks(&updates);
+        assert_eq!(chunks.concat(), input);
+        assert!(chunks.len() > 3);
+        assert_code_blocks_whole(&chunks);
+    }
+
+    /// Creates a named pipe in a directory of its own, returning the directory and the pipe.
+    fn make_fifo(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("homo-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fifo = dir.join("pipe");
+        assert!(
+            Command::new("mkfifo")
+                .arg(&fifo)
+                .status()
+                .unwrap()
+                .success()
+        );
+        (dir, fifo)
+    }
+
+    #[test]
+    fn named_pipes_have_a_path_and_anonymous_ones_dont() {
+        let (dir, fifo) = make_fifo("fifo-path");
+        // Opened for writing too, so opening doesn't wait for a writer
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&fifo)
+            .unwrap();
+        assert_eq!(
+            descriptor_path(file.as_raw_fd()),
+            Some(fifo.canonicalize().unwrap())
+        );
+
+        let (reader, _writer) = io::pipe().unwrap();
+        assert_eq!(descriptor_path(reader.as_raw_fd()), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn named_pipe_is_followed_across_writers() {
+        let (dir, fifo) = make_fifo("fifo-follow");
+        let lines = spawn_fifo_reader(fifo.to_string_lossy().into_owned());
+        let mut received = Vec::new();
+        for text in ["first\n", "second\n"] {
+            // Each write opens the pipe and closes it again, ending that writer
+            fs::write(&fifo, text).unwrap();
+            let piece = lines.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+            received.push(piece.text);
+        }
+        assert_eq!(received, ["first\n", "second\n"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}